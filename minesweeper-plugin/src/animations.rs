@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+
+use crate::components::Coordinates;
+use crate::audio::save_settings;
+use crate::config::{CoverAnimation, GameConfig};
+use crate::effects::{BURST_PARTICLES, spawn_burst};
+
+
+/// A cover leaving a revealed cell, despawned at the end of its animation
+///
+/// The cell is revealed in the `GameSession` and removed from the covered cells as soon as it is uncovered,
+/// the animation only shows it.
+#[derive(Component)]
+pub struct Uncovering {
+	/// Seconds to wait before the animation starts, the covers further from the revealed cell waiting longer
+	delay: f32,
+	elapsed: f32,
+	/// Translation of the cover before the animation, its bottom left corner
+	origin: Vec3,
+	/// Bursts the cell once the cover is gone, for the mines revealed at the end of the game
+	burst: bool,
+}
+
+/// A flag dropping in its cell
+#[derive(Component)]
+pub struct FlagDrop {
+	elapsed: f32,
+	/// Translation of the flag once it landed
+	target: Vec3,
+}
+
+/// Turns the animations on or off, for the players who prefer the board to stay still
+#[derive(Debug, Copy, Clone, Event)]
+pub struct ToggleReduceMotionEvent;
+
+pub fn toggle_reduce_motion_event_handler(
+	mut ev_toggle: EventReader<ToggleReduceMotionEvent>,
+	mut cfg: ResMut<GameConfig>,
+) {
+	ev_toggle.clear();
+	cfg.ui_style.reduce_motion = !cfg.ui_style.reduce_motion;
+	match cfg.ui_style.reduce_motion {
+		true => info!("Reduced motion, the animations are off"),
+		false => info!("The animations are on"),
+	}
+	save_settings(&cfg);
+}
+
+
+/// Removes the covers of the revealed cells, starting their animation unless the motion is reduced
+///
+/// The covers of an opening leave one ring after the other, from the cell that was revealed outward
+pub fn remove_covers(
+	cmd: &mut Commands,
+	cfg: &GameConfig,
+	from: Coordinates,
+	covers: impl IntoIterator<Item = (Coordinates, Entity)>,
+) {
+	for (coord, entity) in covers {
+		if cfg.ui_style.reduce_motion {
+			cmd.entity(entity).despawn_recursive();
+			continue
+		}
+		let delay = (ring(from, coord) as f32 * cfg.ui_style.cascade_step).min(cfg.ui_style.cascade_max);
+		uncover(cmd, entity, delay, false);
+	}
+}
+
+/// Removes the covers of the mines left once the game is lost, one ring after the other from the mine that exploded
+///
+/// Every mine bursts when its cover is gone, unless the motion is reduced
+pub fn chain_mines(
+	cmd: &mut Commands,
+	cfg: &GameConfig,
+	from: Coordinates,
+	covers: impl IntoIterator<Item = (Coordinates, Entity)>,
+) {
+	for (coord, entity) in covers {
+		if cfg.ui_style.reduce_motion {
+			cmd.entity(entity).despawn_recursive();
+			continue
+		}
+		uncover(cmd, entity, ring(from, coord) as f32 * cfg.ui_style.mine_chain_step, true);
+	}
+}
+
+/// Returns the ring of a cell around another one, the cells touching it being on the first ring
+fn ring(from: Coordinates, coord: Coordinates) -> u16 {
+	coord.x.abs_diff(from.x).max(coord.y.abs_diff(from.y))
+}
+
+fn uncover(cmd: &mut Commands, cover: Entity, delay: f32, burst: bool) {
+	cmd.entity(cover).add(move |mut entity: EntityWorldMut| {
+		let Some(origin) = entity.get::<Transform>().map(|transform| transform.translation) else { return };
+		entity.insert(Uncovering { delay, elapsed: 0., origin, burst });
+	});
+}
+
+/// Makes a flag drop in its cell, unless the motion is reduced
+pub fn drop_flag(cmd: &mut Commands, cfg: &GameConfig, flag: Entity) {
+	if cfg.ui_style.reduce_motion { return }
+	let height = cfg.ui_style.cell_size as f32 / 2.;
+	cmd.entity(flag).add(move |mut entity: EntityWorldMut| {
+		let Some(mut transform) = entity.get_mut::<Transform>() else { return };
+		let target = transform.translation;
+		// Starting above the cell and a bit larger, as if it was falling toward the board
+		transform.translation.y += height;
+		transform.scale = Vec3::splat(1.4);
+		entity.insert(FlagDrop { elapsed: 0., target });
+	});
+}
+
+/// Eases the end of an animation, `t` going from 0 to 1
+fn ease_out(t: f32) -> f32 {
+	1. - (1. - t).powi(3)
+}
+
+/// Plays the animations of the covers, despawning them once they are gone
+pub fn animate_covers(
+	mut cmd: Commands,
+	time: Res<Time>,
+	cfg: Res<GameConfig>,
+	mut q_covers: Query<(Entity, &mut Uncovering, &mut Transform, &GlobalTransform, Option<&mut Sprite>)>,
+) {
+	let size = (cfg.ui_style.cell_size - cfg.ui_style.cell_padding) as f32;
+	for (entity, mut uncovering, mut transform, global, sprite) in &mut q_covers {
+		uncovering.elapsed += time.delta_seconds();
+		let t = ((uncovering.elapsed - uncovering.delay) / cfg.ui_style.cover_duration.max(f32::EPSILON)).clamp(0., 1.);
+		if t >= 1. {
+			if uncovering.burst {
+				// The center of the cell, wherever the animation moved the cover
+				let center = global.translation() - transform.translation + uncovering.origin + Vec3::splat(size / 2.);
+				spawn_burst(&mut cmd, &cfg, center.truncate(), &[cfg.theme.mistakes, cfg.theme.flags], BURST_PARTICLES / 3);
+			}
+			cmd.entity(entity).despawn_recursive();
+			continue
+		}
+		let left = 1. - ease_out(t);
+		// The covers are anchored in their bottom left corner, so they are moved to stay centered on their cell
+		let scale = match (cfg.ui_style.cover_animation, sprite) {
+			(CoverAnimation::Fade, Some(mut sprite)) => {
+				sprite.color.set_a(left);
+				continue
+			},
+			// The covers of the hexagonal cells share their material, they shrink instead of fading
+			(CoverAnimation::Fade, None) => Vec2::splat(left),
+			(CoverAnimation::Flip, _) => Vec2::new(left, 1.),
+		};
+		transform.scale = scale.extend(1.);
+		transform.translation = uncovering.origin + (size * (1. - scale) / 2.).extend(0.);
+	}
+}
+
+/// Plays the animations of the flags dropping in their cells
+pub fn animate_flags(
+	mut cmd: Commands,
+	time: Res<Time>,
+	cfg: Res<GameConfig>,
+	mut q_flags: Query<(Entity, &mut FlagDrop, &mut Transform)>,
+) {
+	let height = cfg.ui_style.cell_size as f32 / 2.;
+	for (entity, mut drop, mut transform) in &mut q_flags {
+		drop.elapsed += time.delta_seconds();
+		let t = (drop.elapsed / cfg.ui_style.flag_duration.max(f32::EPSILON)).min(1.);
+		let left = 1. - ease_out(t);
+		transform.translation = drop.target + Vec3::Y * height * left;
+		transform.scale = Vec3::splat(1. + 0.4 * left);
+		if t >= 1. {
+			cmd.entity(entity).remove::<FlagDrop>();
+		}
+	}
+}
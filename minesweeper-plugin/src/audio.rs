@@ -0,0 +1,289 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::audio::{AddAudioSource, AudioSink, AudioSourceBundle, Decodable, PlaybackSettings, Source, Volume};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::config::GameConfig;
+use crate::events::ExplosionEvent;
+use crate::resources::{FlagsDuel, SavedSettings, SETTINGS_FILE};
+
+
+/// Samples per second of the sounds
+pub const SAMPLE_RATE: u32 = 44_100;
+/// Number of cells revealed at once from which an opening has its own sound
+pub const BIG_OPENING: usize = 10;
+/// Seconds a sound can wait to be played before the audio device is considered missing
+const PROBE_DELAY: f32 = 1.;
+
+
+/// The shape of the wave of a sound
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Wave {
+	Sine,
+	Square,
+	Triangle,
+	/// Random samples, held for one period of the frequency so the low frequencies rumble
+	Noise,
+}
+
+/// A short sound synthesized by the game, the game has no audio file to load
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Synth {
+	pub wave: Wave,
+	/// Frequencies of the notes, played one after the other
+	pub notes: Vec<f32>,
+	/// Seconds every note lasts
+	pub note_length: f32,
+	/// Ratio between the frequency at the end of a note and at its start, to slide up or down
+	pub slide: f32,
+}
+
+/// Plays a `Synth`, one sample after the other
+pub struct SynthDecoder {
+	synth: Synth,
+	sample: u32,
+	phase: f32,
+	noise_seed: u32,
+	noise: f32,
+}
+
+impl Iterator for SynthDecoder {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<f32> {
+		let note_samples = ((self.synth.note_length * SAMPLE_RATE as f32) as u32).max(1);
+		let frequency = *self.synth.notes.get((self.sample / note_samples) as usize)?;
+		// Progress of the note, from 0 to 1
+		let t = (self.sample % note_samples) as f32 / note_samples as f32;
+		self.phase += frequency * self.synth.slide.powf(t) / SAMPLE_RATE as f32;
+		if self.phase >= 1. {
+			self.phase = self.phase.fract();
+			// Xorshift, to pick the next sample of the noise
+			self.noise_seed ^= self.noise_seed << 13;
+			self.noise_seed ^= self.noise_seed >> 17;
+			self.noise_seed ^= self.noise_seed << 5;
+			self.noise = self.noise_seed as f32 / u32::MAX as f32 * 2. - 1.;
+		}
+		let value = match self.synth.wave {
+			Wave::Sine => (self.phase * TAU).sin(),
+			Wave::Square => if self.phase < 0.5 { 1. } else { -1. },
+			Wave::Triangle => 1. - 4. * (self.phase - 0.5).abs(),
+			Wave::Noise => self.noise,
+		};
+		// A short attack avoids the clicks, then the note fades out
+		let attack = (t * self.synth.note_length / 0.005).min(1.);
+		self.sample += 1;
+		Some(value * attack * (1. - t).powi(2) * 0.5)
+	}
+}
+
+impl Source for SynthDecoder {
+	fn current_frame_len(&self) -> Option<usize> { None }
+	fn channels(&self) -> u16 { 1 }
+	fn sample_rate(&self) -> u32 { SAMPLE_RATE }
+	fn total_duration(&self) -> Option<Duration> {
+		Some(Duration::from_secs_f32(self.synth.notes.len() as f32 * self.synth.note_length))
+	}
+}
+
+impl Decodable for Synth {
+	type DecoderItem = f32;
+	type Decoder = SynthDecoder;
+
+	fn decoder(&self) -> Self::Decoder {
+		SynthDecoder { synth: self.clone(), sample: 0, phase: 0., noise_seed: 0x9e37_79b9, noise: 0. }
+	}
+}
+
+
+/// The sounds of the game
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Sound {
+	Reveal,
+	/// Many cells revealed at once
+	Opening,
+	/// The neighbors of a number revealed by a chord
+	Chord,
+	FlagPlaced,
+	FlagRemoved,
+	Explosion,
+	Victory,
+	ButtonHover,
+	ButtonClick,
+}
+
+impl Sound {
+	pub const ALL: [Sound; 9] = [
+		Sound::Reveal, Sound::Opening, Sound::Chord, Sound::FlagPlaced, Sound::FlagRemoved,
+		Sound::Explosion, Sound::Victory, Sound::ButtonHover, Sound::ButtonClick,
+	];
+
+	/// Returns the synthesized sound
+	pub fn synth(&self) -> Synth {
+		let (wave, notes, note_length, slide) = match self {
+			Sound::Reveal => (Wave::Triangle, vec![660.], 0.05, 0.8),
+			Sound::Opening => (Wave::Sine, vec![330.], 0.3, 3.),
+			Sound::Chord => (Wave::Triangle, vec![660., 880.], 0.04, 0.8),
+			Sound::FlagPlaced => (Wave::Square, vec![520., 780.], 0.04, 1.),
+			Sound::FlagRemoved => (Wave::Square, vec![780., 520.], 0.04, 1.),
+			Sound::Explosion => (Wave::Noise, vec![2000.], 0.6, 0.05),
+			Sound::Victory => (Wave::Triangle, vec![523.25, 659.25, 783.99, 1046.5], 0.12, 1.),
+			Sound::ButtonHover => (Wave::Sine, vec![1200.], 0.02, 1.),
+			Sound::ButtonClick => (Wave::Square, vec![300.], 0.04, 0.5),
+		};
+		Synth { wave, notes, note_length, slide }
+	}
+
+	/// Returns the volume of the sound relative to the others, the square waves sounding louder
+	pub fn loudness(&self) -> f32 {
+		match self {
+			Sound::FlagPlaced | Sound::FlagRemoved | Sound::ButtonClick => 0.4,
+			Sound::ButtonHover => 0.3,
+			_ => 1.,
+		}
+	}
+}
+
+/// Plays a sound, at the volume of the effects
+#[derive(Debug, Copy, Clone, Event)]
+pub struct SoundEvent(pub Sound);
+
+/// The synthesized sounds, added to the assets once
+#[derive(Resource, Default)]
+pub struct SoundBank(HashMap<Sound, Handle<Synth>>);
+
+/// Whether the sounds can be heard, found out from the first sound played
+///
+/// Without an audio device, bevy never plays the sounds and never despawns them, so they are not spawned at all
+#[derive(Resource, Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum AudioDevice {
+	#[default]
+	Unknown,
+	Available,
+	Missing,
+}
+
+/// A sound being played, with the seconds it has been waiting for the audio device
+#[derive(Component)]
+pub struct PlayingSound(f32);
+
+
+/// Plays the sounds of the game, the game stays silent when bevy's audio or the audio device is missing
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_event::<SoundEvent>();
+		// Headless apps, like the tests of a CI, may run without bevy's audio
+		if !app.is_plugin_added::<bevy::audio::AudioPlugin>() {
+			info!("No audio plugin, the game stays silent");
+			return;
+		}
+		app.add_audio_source::<Synth>()
+			.init_resource::<AudioDevice>()
+			.init_resource::<SoundBank>()
+			.add_systems(Startup, load_sounds)
+			.add_systems(Update, (
+				button_sounds,
+				explosion_sounds.run_if(on_event::<ExplosionEvent>()),
+				play_sounds.run_if(on_event::<SoundEvent>()),
+				probe_audio_device.run_if(resource_equals(AudioDevice::Unknown)),
+			));
+	}
+}
+
+fn load_sounds(mut bank: ResMut<SoundBank>, mut assets: ResMut<Assets<Synth>>) {
+	for sound in Sound::ALL {
+		bank.0.insert(sound, assets.add(sound.synth()));
+	}
+}
+
+pub fn play_sounds(
+	mut cmd: Commands,
+	mut ev_sound: EventReader<SoundEvent>,
+	device: Res<AudioDevice>,
+	bank: Res<SoundBank>,
+	cfg: Res<GameConfig>,
+) {
+	let volume = cfg.audio.effects_volume();
+	if *device == AudioDevice::Missing || volume <= 0. {
+		ev_sound.clear();
+		return
+	}
+	// A sound sent several times in a frame, like the reveals of a chord, is played once
+	let mut played = HashSet::new();
+	for ev in ev_sound.read() {
+		if !played.insert(ev.0) { continue }
+		let Some(source) = bank.0.get(&ev.0) else { continue };
+		cmd.spawn(AudioSourceBundle {
+			source: source.clone(),
+			settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(volume * ev.0.loudness())),
+		}).insert(PlayingSound(0.)).insert(Name::new("Sound"));
+	}
+}
+
+/// Finds out if the sounds are played, bevy giving them a sink once they are
+///
+/// If a sound is still waiting after a while, there is no audio device and the sounds waiting are removed
+pub fn probe_audio_device(
+	mut cmd: Commands,
+	time: Res<Time>,
+	mut device: ResMut<AudioDevice>,
+	mut q_sounds: Query<(Entity, &mut PlayingSound, Option<&AudioSink>)>,
+) {
+	if q_sounds.iter().any(|(_, _, sink)| sink.is_some()) {
+		*device = AudioDevice::Available;
+		return
+	}
+	for (_, mut playing, _) in &mut q_sounds {
+		playing.0 += time.delta_seconds();
+	}
+	if q_sounds.iter().any(|(_, playing, _)| playing.0 > PROBE_DELAY) {
+		warn!("No audio device found, the game stays silent");
+		*device = AudioDevice::Missing;
+		for (entity, _, _) in &q_sounds {
+			cmd.entity(entity).despawn();
+		}
+	}
+}
+
+/// Plays the sounds of the buttons of every menu
+pub fn button_sounds(
+	q_interaction: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+	mut ev_sound: EventWriter<SoundEvent>,
+) {
+	for interaction in &q_interaction {
+		match interaction {
+			Interaction::Pressed => { ev_sound.send(SoundEvent(Sound::ButtonClick)); },
+			Interaction::Hovered => { ev_sound.send(SoundEvent(Sound::ButtonHover)); },
+			Interaction::None => {},
+		}
+	}
+}
+
+/// Plays the explosions, the mines claimed in the Flags duel sound like flags
+pub fn explosion_sounds(
+	mut ev_explosion: EventReader<ExplosionEvent>,
+	mut ev_sound: EventWriter<SoundEvent>,
+	duel: Option<Res<FlagsDuel>>,
+) {
+	for _ in ev_explosion.read() {
+		let sound = if duel.is_some() { Sound::FlagPlaced } else { Sound::Explosion };
+		ev_sound.send(SoundEvent(sound));
+	}
+}
+
+
+/// Saves the settings chosen in the settings menu, a failure only losing them for the next games
+pub fn save_settings(cfg: &GameConfig) {
+	let mut settings = SavedSettings::from_config(cfg);
+	// The difficulty saved from the menu is kept while the game plays another one
+	if settings.difficulty.is_none() {
+		settings.difficulty = SavedSettings::load(SETTINGS_FILE).ok().and_then(|saved| saved.difficulty);
+	}
+	if let Err(err) = settings.save(SETTINGS_FILE) {
+		error!("{}", err);
+	}
+}
@@ -0,0 +1,117 @@
+// This module contains what every board has, whichever game it is part of.
+// A board is an entity carrying its session, its covers, its timer, its replay and its metrics,
+// so the solo game and the versus game play on their boards through the same systems.
+
+use std::future::Future;
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, poll_once};
+use bevy::utils::HashMap;
+
+use crate::{
+	update_camera,
+	animations::{animate_covers, animate_flags},
+	audio::SoundPlugin,
+	components::Coordinates,
+	config::GameConfig,
+	effects::{animate_particles, explosion_effects, shake_camera},
+	events::{
+		ExplosionEvent, FlagCellEvent, RestartEvent, TimeoutEvent, UncoverCellEvent,
+		explosion_event_handler, flag_event_handler, reveal_board_on_defeat, timeout_event_handler, uncover_event_handler,
+	},
+	resources::{Clicks, CoveredCells, FlagsDuel, GameSession, Replay, ThreeBv},
+	time::{GameTime, check_timeout, update_timer},
+	ui::{update_generating_ui, update_ghost_cells},
+};
+
+
+/// Everything a board needs to be played, inserted on the entity of the board
+#[derive(Bundle)]
+pub struct BoardBundle {
+	pub session: GameSession,
+	pub covers: CoveredCells,
+	pub time: GameTime,
+	pub replay: Replay,
+	pub three_bv: ThreeBv,
+	pub clicks: Clicks,
+}
+
+impl BoardBundle {
+	/// Starts playing a session on a board, with the covers of its cells and the timer of the difficulty
+	pub fn new(session: GameSession, covers: HashMap<Coordinates, Entity>, cfg: &GameConfig) -> Self {
+		let mut time = GameTime::default();
+		time.set_time_limit(cfg.difficulty.time_limit.map(|secs| secs as f32));
+		Self {
+			three_bv: ThreeBv::new(session.grid()),
+			replay: Replay::from_session(&session),
+			covers: CoveredCells(covers),
+			clicks: Clicks::default(),
+			session,
+			time,
+		}
+	}
+}
+
+/// A session whose grid is generated in the background, the game starting once it is ready
+///
+/// Dropping it cancels the generation
+#[derive(Resource)]
+pub struct PendingSession(Task<GameSession>);
+
+impl PendingSession {
+	/// Generates a session on the async compute pool
+	pub fn spawn(generation: impl Future<Output = GameSession> + Send + 'static) -> Self {
+		Self(AsyncComputeTaskPool::get().spawn(generation))
+	}
+
+	/// Returns the session once it is generated
+	pub fn poll(&mut self) -> Option<GameSession> {
+		block_on(poll_once(&mut self.0))
+	}
+}
+
+/// Marks a board whose mines were revealed after a loss, so they are only revealed once
+#[derive(Component)]
+pub struct Resolved;
+
+/// Returns the entity of a cell of a board, the cells being the children of their board
+pub fn cell_entity<'a, T>(
+	cells: impl IntoIterator<Item = (Entity, &'a Coordinates, &'a Parent, T)>,
+	board: Entity,
+	coordinates: Coordinates,
+) -> Option<(Entity, T)> {
+	cells.into_iter()
+		.find(|(_, coord, parent, _)| parent.get() == board && **coord == coordinates)
+		.map(|(entity, _, _, data)| (entity, data))
+}
+
+
+/// Plays on the boards: the rules, the timers, the animations, the effects and the sounds shared by every game
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_plugins(SoundPlugin)
+			.add_event::<UncoverCellEvent>()
+			.add_event::<FlagCellEvent>()
+			.add_event::<ExplosionEvent>()
+			.add_event::<TimeoutEvent>()
+			.add_event::<RestartEvent>()
+			.add_systems(Update, (
+				update_timer,
+				check_timeout.after(update_timer),
+				uncover_event_handler       .run_if(on_event::<UncoverCellEvent>()),
+				// The flags of the duel are the mines claimed by the players, they cannot be placed by hand
+				flag_event_handler          .run_if(on_event::<FlagCellEvent>()).run_if(not(resource_exists::<FlagsDuel>)),
+				explosion_event_handler     .run_if(on_event::<ExplosionEvent>()).after(uncover_event_handler),
+				timeout_event_handler       .run_if(on_event::<TimeoutEvent>()).after(check_timeout),
+				update_ghost_cells.after(uncover_event_handler).after(flag_event_handler),
+				reveal_board_on_defeat.after(explosion_event_handler).after(timeout_event_handler),
+			))
+			// The animations finish whatever the state of the game, the cells being already revealed
+			.add_systems(Update, (
+				animate_covers, animate_flags, animate_particles, shake_camera.before(update_camera),
+				explosion_effects.run_if(on_event::<ExplosionEvent>()),
+				update_generating_ui,
+			));
+	}
+}
@@ -14,9 +14,6 @@ pub struct EmptyCell;
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Component)]
 pub struct Bomb;
 
-#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Component)]
-pub struct Uncover;
-
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Component)]
 pub struct Flag { pub id: Entity }
 
@@ -25,5 +22,5 @@ pub struct GridContainer;
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Component)]
 pub struct BombNeighbors {
-	pub count: u8
+	pub count: u16
 }
\ No newline at end of file
@@ -1,5 +1,9 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 use bevy::prelude::*;
 use crate::components::Coordinates;
+use crate::resources::{BoardFilter, DailyChallenge, Kernel, Mask, Technique, TopologyKind};
 
 // This file contains the default configuration for the game
 
@@ -17,7 +21,13 @@ pub struct ColorTheme {
 	pub menus_msg: Color,
 	pub menus_btn: Color,
 	pub flags: Color,
-	pub neighbors: [Color; 8],
+	/// Colors of the flags claimed by the players of the Flags duel
+	pub player_one: Color,
+	pub player_two: Color,
+	/// Color of the mine that ended the game and of the crosses over the wrong flags
+	pub mistakes: Color,
+	/// Colors of the numbers, from 1 to as many colors as there are, the numbers above use the last one
+	pub neighbors: Vec<Color>,
 }
 
 impl Default for ColorTheme {
@@ -33,7 +43,10 @@ impl Default for ColorTheme {
 			menus_bg:       Color::hex("7c183c").unwrap(),
 			menus_btn:      Color::hex("df7264").unwrap(),
 			menus_msg:      Color::hex("ff8274").unwrap(),
-			neighbors:  [
+			player_one:     Color::hex("ff8274").unwrap(),
+			player_two:     Color::hex("6cb9c9").unwrap(),
+			mistakes:       Color::hex("e0303c").unwrap(),
+			neighbors:  vec![
 				Color::hex("6d85a5").unwrap(),
 				Color::hex("6cb9c9").unwrap(),
 				Color::hex("9ceded").unwrap(),
@@ -41,44 +54,245 @@ impl Default for ColorTheme {
 				Color::hex("bf1d5c").unwrap(),
 				Color::hex("4f1446").unwrap(),
 				Color::hex("2e0a30").unwrap(),
-				Color::hex("0d001a").unwrap()
+				Color::hex("0d001a").unwrap(),
+				// The numbers above 8, with several mines per cell or bigger kernels
+				Color::hex("f2e07a").unwrap(),
+				Color::hex("e0b05a").unwrap(),
+				Color::hex("d08159").unwrap(),
+				Color::hex("df7264").unwrap(),
+				Color::hex("ff8274").unwrap(),
+				Color::hex("b6d35c").unwrap(),
+				Color::hex("6fae57").unwrap(),
+				Color::hex("2f7d5a").unwrap(),
 			]
 		}
 	}
 }
 
+impl ColorTheme {
+	/// Returns the color of a number, the numbers without a color of their own share the color of the last one
+	pub fn neighbor_color(&self, count: u16) -> Color {
+		let index = (count.max(1) as usize - 1).min(self.neighbors.len().saturating_sub(1));
+		self.neighbors.get(index).copied().unwrap_or(Color::WHITE)
+	}
+
+	/// Returns the color of a player of the Flags duel, starting from 0
+	pub fn player_color(&self, player: usize) -> Color {
+		if player == 0 { self.player_one } else { self.player_two }
+	}
+
+	/// Loads a theme from a text file, see `ColorTheme::from_str` for the format
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		let text = fs::read_to_string(path)
+			.map_err(|err| format!("Cannot read theme file {}: {}", path.display(), err))?;
+		text.parse()
+	}
+}
+
+/// Parses a theme from text
+///
+/// Every line contains a color name and its hex value, like `background = 203c56`.
+/// `neighbors` takes the colors of the numbers separated by commas, starting from 1,
+/// empty lines and lines starting with `#` are ignored.
+/// Missing colors keep their default value.
+impl FromStr for ColorTheme {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut theme = ColorTheme::default();
+		let parse_color = |value: &str| Color::hex(value.trim())
+			.map_err(|_| format!("Invalid color: {}", value.trim()));
+
+		for (number, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') { continue }
+			let (key, value) = line.split_once('=')
+				.ok_or_else(|| format!("Line {}: expected `name = color`", number + 1))?;
+			let color = match key.trim() {
+				"neighbors" => {
+					theme.neighbors = value.split(',').map(parse_color).collect::<Result<Vec<_>, _>>()?;
+					continue
+				},
+				"background"    => &mut theme.background,
+				"border"        => &mut theme.border,
+				"cell"          => &mut theme.cell,
+				"hidden_cell"   => &mut theme.hidden_cell,
+				"flags"         => &mut theme.flags,
+				"main_ui"       => &mut theme.main_ui,
+				"main_ui_hover" => &mut theme.main_ui_hover,
+				"menus_bg"      => &mut theme.menus_bg,
+				"menus_btn"     => &mut theme.menus_btn,
+				"menus_msg"     => &mut theme.menus_msg,
+				"player_one"    => &mut theme.player_one,
+				"player_two"    => &mut theme.player_two,
+				"mistakes"      => &mut theme.mistakes,
+				other => return Err(format!("Line {}: unknown color {}", number + 1, other)),
+			};
+			*color = parse_color(value)?;
+		}
+		Ok(theme)
+	}
+}
+
 // Simple type alias to have the handy Coordinates struct without naming it ^^
 type GridSize = Coordinates;
 
-/// Structure containing the two parameters influencing the difficulty of a minesweeper game,
-/// namely the grid size (width and height) and bomb count
+/// The three standard minesweeper difficulties, the only ones with high scores
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+pub enum StandardDifficulty {
+	Beginner,
+	Intermediate,
+	Expert,
+}
+impl StandardDifficulty {
+	/// Returns the settings of the difficulty, with the classic rules
+	pub fn settings(&self) -> DifficultySettings {
+		match self {
+			StandardDifficulty::Beginner => DifficultySettings::beginner(),
+			StandardDifficulty::Intermediate => DifficultySettings::intermediate(),
+			StandardDifficulty::Expert => DifficultySettings::expert(),
+		}
+	}
+	/// Returns the name of the difficulty, in lowercase
+	pub fn name(&self) -> &'static str {
+		match self {
+			StandardDifficulty::Beginner => "beginner",
+			StandardDifficulty::Intermediate => "intermediate",
+			StandardDifficulty::Expert => "expert",
+		}
+	}
+}
+
+/// Structure containing the parameters influencing the difficulty of a minesweeper game,
+/// namely the grid size (width and height), the bomb count, the shape of the cells and the neighbors they count
 ///
-/// it implements defaults for the three standard minesweeper difficulty settings
-#[derive(Resource, Reflect, Copy, Clone)]
+/// it implements defaults for the three standard minesweeper difficulty settings, on square grids
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
 #[reflect(Resource)]
 pub struct DifficultySettings {
 	pub grid_size: GridSize, // (u16, u16),
 	pub bomb_count: u16,
+	pub topology: TopologyKind,
+	pub kernel: Kernel,
+	/// Shape of the board, the holes of the mask are not part of the grid
+	pub mask: Option<Mask>,
+	/// Maximum number of mines in a single cell, 1 for the classic rules
+	pub mines_per_cell: u8,
+	/// Number of bombs the player can hit, the last one ending the game, 1 for the classic rules
+	pub lives: u8,
+	/// Seconds the player has to clear the grid, counting down in the header, None for the classic stopwatch
+	pub time_limit: Option<u32>,
+	/// Seconds added to the countdown for every safe cell revealed
+	pub time_bonus: f32,
+	/// Range of difficulty the generated boards must be in, None to accept every board
+	pub filter: Option<BoardFilter>,
 }
 impl DifficultySettings {
 	pub fn beginner() -> Self {
 		Self {
 			grid_size: GridSize::new(9, 9),
-			bomb_count: 10
+			bomb_count: 10,
+			topology: TopologyKind::Square,
+			kernel: Kernel::Moore,
+			mask: None,
+			mines_per_cell: 1,
+			lives: 1,
+			time_limit: None,
+			time_bonus: 0.,
+			filter: None,
 		}
 	}
 	pub fn intermediate() -> Self {
 		Self {
 			grid_size: GridSize::new(16, 16),
-			bomb_count: 40
+			bomb_count: 40,
+			topology: TopologyKind::Square,
+			kernel: Kernel::Moore,
+			mask: None,
+			mines_per_cell: 1,
+			lives: 1,
+			time_limit: None,
+			time_bonus: 0.,
+			filter: None,
 		}
 	}
 	pub fn expert() -> Self {
 		Self {
 			grid_size: GridSize::new(30, 16),
-			bomb_count: 99
+			bomb_count: 99,
+			topology: TopologyKind::Square,
+			kernel: Kernel::Moore,
+			mask: None,
+			mines_per_cell: 1,
+			lives: 1,
+			time_limit: None,
+			time_bonus: 0.,
+			filter: None,
 		}
 	}
+	/// Returns the standard difficulty matching these settings, if they follow the classic rules
+	///
+	/// A countdown is allowed, since it can only make the game harder
+	pub fn standard(&self) -> Option<StandardDifficulty> {
+		let classic = self.topology == TopologyKind::Square && self.kernel == Kernel::Moore
+			&& self.mask.is_none() && self.mines_per_cell == 1 && self.lives == 1;
+		if !classic { return None }
+		[StandardDifficulty::Beginner, StandardDifficulty::Intermediate, StandardDifficulty::Expert]
+			.into_iter()
+			.find(|level| {
+				let settings = level.settings();
+				settings.grid_size == self.grid_size && settings.bomb_count == self.bomb_count
+			})
+	}
+	/// Returns the same difficulty, played on another topology
+	pub fn with_topology(self, topology: TopologyKind) -> Self {
+		Self { topology, ..self }
+	}
+	/// Returns the same difficulty, counting other neighbors
+	pub fn with_kernel(self, kernel: Kernel) -> Self {
+		Self { kernel, ..self }
+	}
+	/// Returns the same difficulty, allowing several mines per cell
+	pub fn with_mines_per_cell(self, mines_per_cell: u8) -> Self {
+		Self { mines_per_cell, ..self }
+	}
+	/// Returns the same difficulty, with several lives
+	pub fn with_lives(self, lives: u8) -> Self {
+		Self { lives, ..self }
+	}
+	/// Returns the same difficulty, played against a countdown of `time_limit` seconds
+	pub fn with_countdown(self, time_limit: Option<u32>, time_bonus: f32) -> Self {
+		Self { time_limit, time_bonus, ..self }
+	}
+	/// Returns a time budget fitting the difficulty, a second for every safe cell rounded up to ten seconds
+	pub fn suggested_time_limit(&self) -> u32 {
+		let cells = match &self.mask {
+			Some(mask) => mask.enabled_count(),
+			None => self.grid_size.x as u32 * self.grid_size.y as u32,
+		};
+		let safe_cells = cells.saturating_sub(self.bomb_count as u32).max(1);
+		(safe_cells + 9) / 10 * 10
+	}
+	/// Returns the same difficulty, only generating the boards that pass the filter
+	pub fn with_filter(self, filter: BoardFilter) -> Self {
+		Self { filter: Some(filter), ..self }
+	}
+	/// Returns the same difficulty, only generating the boards needing no technique harder than the given one
+	///
+	/// The 3BV range of the current filter is kept, the filter is removed when it would accept every board
+	pub fn with_hardest_technique(self, hardest: Technique) -> Self {
+		let filter = match self.filter {
+			Some(filter) => filter.with_hardest(hardest),
+			None => BoardFilter::hardest(hardest),
+		};
+		let filter = (filter.has_three_bv_range() || hardest != Technique::Guess).then_some(filter);
+		Self { filter, ..self }
+	}
+	/// Returns the same difficulty, on a board shaped by a mask whose size becomes the size of the grid
+	pub fn with_mask(self, mask: Mask) -> Self {
+		Self { grid_size: GridSize::new(mask.width(), mask.height()), mask: Some(mask), ..self }
+	}
 }
 impl Default for DifficultySettings {
 	fn default() -> Self {
@@ -96,6 +310,22 @@ pub struct UiStyle {
 	pub cell_size: u16,
 	pub font_size: u16,
 	pub margin: u16,
+	/// Width of the panel on the right of the board, only shown during a race
+	pub side_panel: u16,
+	/// How the covers leave the revealed cells
+	pub cover_animation: CoverAnimation,
+	/// Seconds a cover takes to fade or flip away
+	pub cover_duration: f32,
+	/// Seconds between two rings of an opening, which spreads outward from the revealed cell
+	pub cascade_step: f32,
+	/// Longest wait of a cover in an opening, so the large openings don't drag on
+	pub cascade_max: f32,
+	/// Seconds a flag takes to drop in its cell
+	pub flag_duration: f32,
+	/// Seconds between two rings of the mines revealed once the game is lost, from the mine that exploded outward
+	pub mine_chain_step: f32,
+	/// Removes the covers and places the flags at once, without any animation
+	pub reduce_motion: bool,
 }
 impl Default for UiStyle {
 	fn default() -> Self {
@@ -105,15 +335,118 @@ impl Default for UiStyle {
 			font_size: 25,
 			cell_size: 30,
 			margin: 20,
+			side_panel: 240,
+			cover_animation: CoverAnimation::Flip,
+			cover_duration: 0.15,
+			cascade_step: 0.025,
+			cascade_max: 0.4,
+			flag_duration: 0.15,
+			mine_chain_step: 0.06,
+			reduce_motion: false,
 		}
 	}
 }
 
-/// This structure contain the three previous structures to be easily accessed as a single resource
+/// The animations of the covers of the revealed cells
+#[derive(Reflect, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CoverAnimation {
+	/// The cover fades out, the covers of the hexagonal cells shrink instead
+	Fade,
+	/// The cover flips around its vertical axis
+	Flip,
+}
+
+/// Parameters controlling how the grids are generated
+#[derive(Resource, Reflect, Default, Copy, Clone)]
+#[reflect(Resource)]
+pub struct GenerationSettings {
+	/// Seed of the random number generator, the same seed always generates the same sequence of grids
+	pub seed: Option<u64>,
+	/// Only generate grids that can be solved without guessing, the first opening is revealed for the player
+	pub no_guess: bool,
+	/// The daily challenge being played, its board replaces the generation of a new grid
+	pub daily: Option<DailyChallenge>,
+	/// Retrying a board also reveals the first cell the player revealed on it
+	pub retry_first_click: bool,
+}
+
+
+/// How to join a race against another player on the local network
+#[derive(Reflect, Debug, Clone)]
+pub enum RaceSettings {
+	/// Waits for the other player on a port, then sends the board
+	Host { port: u16 },
+	/// Connects to the host, with an address like `192.168.1.12:7878`
+	Join { address: String },
+}
+
+
+/// Volumes of the sounds, from 0 to 1
+#[derive(Reflect, Debug, Copy, Clone, PartialEq)]
+pub struct AudioSettings {
+	/// Volume of everything the game plays
+	pub master: f32,
+	/// Volume of the sound effects, relative to the master volume
+	pub effects: f32,
+	pub muted: bool,
+}
+impl Default for AudioSettings {
+	fn default() -> Self {
+		Self { master: 0.8, effects: 0.8, muted: false }
+	}
+}
+impl AudioSettings {
+	/// Returns the volume of the sound effects, 0 when the game is muted
+	pub fn effects_volume(&self) -> f32 {
+		if self.muted { 0. } else { self.master * self.effects }
+	}
+}
+
+
+/// Who plays against the player in the Flags duel
+#[derive(Reflect, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DuelOpponent {
+	/// Someone else, taking turns on the same computer
+	HotSeat,
+	/// The computer, revealing the cells most likely to hide a mine
+	Computer,
+}
+
+
+/// This structure contain the previous structures to be easily accessed as a single resource
 #[derive(Resource, Reflect, Default)]
 #[reflect(Resource)]
 pub struct GameConfig {
 	pub difficulty: DifficultySettings,
+	/// The difficulty was given on the command line or by a board, the difficulty saved from the settings menu is then
+	/// neither played nor overwritten
+	pub difficulty_given: bool,
+	pub generation: GenerationSettings,
 	pub ui_style: UiStyle,
 	pub theme: ColorTheme,
+	/// The race to host or join, None for a game alone
+	pub race: Option<RaceSettings>,
+	/// Two players on the same computer, each on their own board side by side
+	pub versus: bool,
+	/// The Flags duel to play, None for the classic game
+	pub duel: Option<DuelOpponent>,
+	pub audio: AudioSettings,
+}
+impl GameConfig {
+	/// Size of the board in the world, without its padding
+	pub fn board_size(&self) -> Vec2 {
+		let size = self.difficulty.grid_size;
+		self.difficulty.topology.topology().board_size(size.x, size.y, self.ui_style.cell_size as f32)
+	}
+	/// Size of the window needed to show the boards, their margins, the header and the side panel of the races
+	pub fn window_size(&self) -> Vec2 {
+		let side_panel = if self.race.is_some() { self.ui_style.side_panel as f32 } else { 0. };
+		let board_count = if self.versus { 2. } else { 1. };
+		let margin = self.ui_style.margin as f32;
+		let board_size = self.board_size();
+		Vec2::new(
+			board_size.x * board_count + margin * (board_count + 1.) + side_panel,
+			board_size.y + margin * 2. + self.ui_style.header_size as f32,
+		)
+	}
 }
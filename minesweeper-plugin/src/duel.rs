@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+use crate::AppState;
+use crate::config::{DuelOpponent, GameConfig};
+use crate::events::{RestartEvent, UncoverCellEvent};
+use crate::resources::{FlagsDuel, GameSession, computer_move};
+
+
+/// Switches between the classic game, the Flags duel on the same computer and the Flags duel against the computer
+#[derive(Debug, Copy, Clone, Event)]
+pub struct ToggleDuelEvent;
+
+pub fn toggle_duel_event_handler(
+	mut ev_toggle: EventReader<ToggleDuelEvent>,
+	mut ev_restart: EventWriter<RestartEvent>,
+	mut next_state: ResMut<NextState<AppState>>,
+	mut cfg: ResMut<GameConfig>,
+) {
+	ev_toggle.clear();
+	cfg.duel = match cfg.duel {
+		None => Some(DuelOpponent::HotSeat),
+		Some(DuelOpponent::HotSeat) => Some(DuelOpponent::Computer),
+		Some(DuelOpponent::Computer) => None,
+	};
+	match cfg.duel {
+		Some(DuelOpponent::HotSeat) => info!("Starting a Flags duel, take turns to find the mines"),
+		Some(DuelOpponent::Computer) => info!("Starting a Flags duel against the computer"),
+		None => info!("Back to the classic game"),
+	}
+	// The duel is never played on the board of the day
+	cfg.generation.daily = None;
+	ev_restart.send(RestartEvent);
+	next_state.set(AppState::InGame);
+}
+
+/// Plays the turns of the computer, after letting it think for a moment
+pub fn computer_turn_system(
+	time: Res<Time>,
+	q_boards: Query<(Entity, &GameSession)>,
+	mut duel: ResMut<FlagsDuel>,
+	mut ev_uncover: EventWriter<UncoverCellEvent>,
+) {
+	let Ok((board, session)) = q_boards.get_single() else { return };
+	if !duel.think(time.delta_seconds()) { return }
+	if let Some(coord) = computer_move(session) {
+		debug!("The computer reveals {}", coord);
+		ev_uncover.send(UncoverCellEvent { board, coordinates: coord });
+	}
+}
+
+/// Run condition disabling the board while the computer is playing
+pub fn duel_allows_input(duel: Option<Res<FlagsDuel>>) -> bool {
+	duel.map_or(true, |duel| !duel.is_computer_turn())
+}
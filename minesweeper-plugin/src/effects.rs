@@ -0,0 +1,184 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+	GameCamera,
+	board::cell_entity,
+	components::Coordinates,
+	config::GameConfig,
+	events::ExplosionEvent,
+	resources::{FlagsDuel, GameSession, Outcome},
+};
+
+
+/// Seconds the screen shakes after an explosion
+pub const SHAKE_DURATION: f32 = 0.35;
+/// Largest offset of the camera while the screen shakes, in pixels
+pub const SHAKE_STRENGTH: f32 = 7.;
+/// Number of particles of the burst of a mine exploding
+pub const BURST_PARTICLES: usize = 28;
+/// Number of confetti falling on a victory
+pub const CONFETTI_COUNT: usize = 150;
+
+/// A small square flying away, despawned at the end of its life
+#[derive(Component)]
+pub struct Particle {
+	velocity: Vec2,
+	/// Pull toward the bottom of the window, in pixels per second squared
+	gravity: f32,
+	/// Rotation speed, in radians per second
+	spin: f32,
+	elapsed: f32,
+	lifetime: f32,
+}
+
+/// Shakes the camera, which keeps track of its offset so it can be moved back in place
+#[derive(Component)]
+pub struct ScreenShake {
+	elapsed: f32,
+	offset: Vec2,
+}
+
+
+fn particle_bundle(color: Color, size: f32, position: Vec2) -> SpriteBundle {
+	SpriteBundle {
+		sprite: Sprite {
+			color,
+			custom_size: Some(Vec2::splat(size)),
+			..default()
+		},
+		// Above the board and its flags
+		transform: Transform::from_translation(position.extend(10.)),
+		..default()
+	}
+}
+
+/// Spawns a burst of particles flying away from a point, in the given colors
+pub fn spawn_burst(cmd: &mut Commands, cfg: &GameConfig, center: Vec2, colors: &[Color], count: usize) {
+	if cfg.ui_style.reduce_motion || colors.is_empty() { return }
+	let mut rng = rand::thread_rng();
+	let cell_size = cfg.ui_style.cell_size as f32;
+	for index in 0..count {
+		let angle = rng.gen_range(0. .. TAU);
+		let speed = rng.gen_range(2. .. 6.) * cell_size;
+		cmd.spawn(particle_bundle(colors[index % colors.len()], rng.gen_range(0.1 .. 0.2) * cell_size, center))
+			.insert(Particle {
+				velocity: Vec2::from_angle(angle) * speed,
+				gravity: 4. * cell_size,
+				spin: rng.gen_range(-10. .. 10.),
+				elapsed: 0.,
+				lifetime: rng.gen_range(0.4 .. 0.7),
+			})
+			.insert(Name::new("Particle"));
+	}
+}
+
+/// Returns the center of a cell of the board, in the coordinates of the window
+pub fn cell_center(cfg: &GameConfig, transform: &GlobalTransform) -> Vec2 {
+	let size = (cfg.ui_style.cell_size - cfg.ui_style.cell_padding) as f32;
+	transform.translation().truncate() + Vec2::splat(size / 2.)
+}
+
+/// Bursts the mines found or hit, shaking the screen when they explode
+///
+/// In the Flags duel, the mines claimed burst in the color of the player who found them without shaking the screen
+pub fn explosion_effects(
+	mut cmd: Commands,
+	mut ev_explosion: EventReader<ExplosionEvent>,
+	q_boards: Query<&GameSession>,
+	duel: Option<Res<FlagsDuel>>,
+	cfg: Res<GameConfig>,
+	q_cells: Query<(Entity, &Coordinates, &Parent, &GlobalTransform)>,
+	mut q_camera: Query<(Entity, Option<&mut ScreenShake>), With<GameCamera>>,
+) {
+	if cfg.ui_style.reduce_motion {
+		ev_explosion.clear();
+		return
+	}
+	for ev in ev_explosion.read() {
+		let Ok(session) = q_boards.get(ev.board) else { continue };
+		let Some((_, transform)) = cell_entity(&q_cells, ev.board, ev.coordinates) else { continue };
+		let center = cell_center(&cfg, transform);
+		match &duel {
+			Some(duel) if session.outcome() != Outcome::Defeat => {
+				spawn_burst(&mut cmd, &cfg, center, &[cfg.theme.player_color(duel.turn()), cfg.theme.flags], BURST_PARTICLES);
+			},
+			_ => {
+				spawn_burst(&mut cmd, &cfg, center, &[cfg.theme.mistakes, cfg.theme.flags, cfg.theme.menus_msg], BURST_PARTICLES);
+				// A shake already going on starts over, keeping its offset to move the camera back in place
+				match q_camera.get_single_mut() {
+					Ok((_, Some(mut shake))) => shake.elapsed = 0.,
+					Ok((camera, None)) => { cmd.entity(camera).insert(ScreenShake { elapsed: 0., offset: Vec2::ZERO }); },
+					Err(_) => {},
+				}
+			},
+		}
+	}
+}
+
+/// Drops confetti from the top of the window, in the colors of the numbers and of the flags
+pub fn spawn_confetti(mut cmd: Commands, cfg: Res<GameConfig>) {
+	if cfg.ui_style.reduce_motion { return }
+	let mut rng = rand::thread_rng();
+	let window_size = cfg.window_size();
+	let colors: Vec<Color> = cfg.theme.neighbors.iter().copied()
+		.chain([cfg.theme.flags, cfg.theme.player_one, cfg.theme.player_two])
+		.collect();
+	let cell_size = cfg.ui_style.cell_size as f32;
+	for index in 0..CONFETTI_COUNT {
+		let position = Vec2::new(rng.gen_range(0. ..= window_size.x), window_size.y + rng.gen_range(0. .. 2. * cell_size));
+		cmd.spawn(particle_bundle(colors[index % colors.len()], rng.gen_range(0.15 .. 0.3) * cell_size, position))
+			.insert(Particle {
+				velocity: Vec2::new(rng.gen_range(-1. .. 1.) * cell_size, -rng.gen_range(1. .. 4.) * cell_size),
+				gravity: 2. * cell_size,
+				spin: rng.gen_range(-6. .. 6.),
+				elapsed: 0.,
+				lifetime: rng.gen_range(1.5 .. 3.),
+			})
+			.insert(Name::new("Confetti"));
+	}
+}
+
+/// Moves the particles and fades them out at the end of their life
+pub fn animate_particles(
+	mut cmd: Commands,
+	time: Res<Time>,
+	mut q_particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+	let delta = time.delta_seconds();
+	for (entity, mut particle, mut transform, mut sprite) in &mut q_particles {
+		particle.elapsed += delta;
+		if particle.elapsed >= particle.lifetime {
+			cmd.entity(entity).despawn_recursive();
+			continue
+		}
+		particle.velocity.y -= particle.gravity * delta;
+		transform.translation += (particle.velocity * delta).extend(0.);
+		transform.rotate_z(particle.spin * delta);
+		// Fading out during the last third of its life
+		sprite.color.set_a((3. * (1. - particle.elapsed / particle.lifetime)).min(1.));
+	}
+}
+
+/// Shakes the camera, less and less until it is back in place
+///
+/// A new game puts the camera back in place and stops the shake, so it runs before the camera is updated
+pub fn shake_camera(
+	mut cmd: Commands,
+	time: Res<Time>,
+	mut q_camera: Query<(Entity, &mut ScreenShake, &mut Transform), With<GameCamera>>,
+) {
+	let Ok((entity, mut shake, mut transform)) = q_camera.get_single_mut() else { return };
+	transform.translation -= shake.offset.extend(0.);
+	shake.elapsed += time.delta_seconds();
+	if shake.elapsed >= SHAKE_DURATION {
+		cmd.entity(entity).remove::<ScreenShake>();
+		return
+	}
+	let strength = SHAKE_STRENGTH * (1. - shake.elapsed / SHAKE_DURATION);
+	let mut rng = rand::thread_rng();
+	shake.offset = Vec2::new(rng.gen_range(-1. ..= 1.), rng.gen_range(-1. ..= 1.)) * strength;
+	transform.translation += shake.offset.extend(0.);
+}
@@ -1,25 +1,50 @@
 
+use std::path::PathBuf;
 use bevy::prelude::*;
 use bevy::log::debug;
 
 use crate::{
 	AppState, Coordinates,
+	animations::{chain_mines, drop_flag, remove_covers},
+	audio::{Sound, SoundEvent, BIG_OPENING, save_settings},
+	board::{Resolved, cell_entity},
 	config::GameConfig,
-	resources::{CoveredCells, NEIGHBORS},
-	components::{Bomb, EmptyCell, Flag, Uncover},
+	resources::{
+		BoardFile, BoardToLoad, CoveredCells, FlagOutcome, GameSession, Replay, ReplayAction, ReplayPlayback,
+		DailyChallenge, HighScores, RankedGame, Kernel, Outcome, RevealOutcome, TopologyKind, new_board_path,
+		FlagsDuel, LoadedBoard, RetryCount, Statistics, Clicks, GameMetrics, ThreeBv, HIGH_SCORES_FILE, STATISTICS_FILE, CUSTOM_BUCKET, retry_bucket,
+	},
+	components::Flag,
+	time::GameTime,
 };
 use crate::components::GridContainer;
-use crate::config::DifficultySettings;
+use crate::config::{DifficultySettings, StandardDifficulty};
 
 
+/// Reveals a cell of a board
 #[derive(Debug, Copy, Clone, Event)]
-pub struct UncoverCellEvent(pub Coordinates);
+pub struct UncoverCellEvent {
+	pub board: Entity,
+	pub coordinates: Coordinates,
+}
 
+/// Toggles the flag of a cell of a board
 #[derive(Debug, Copy, Clone, Event)]
-pub struct FlagCellEvent(pub Coordinates);
+pub struct FlagCellEvent {
+	pub board: Entity,
+	pub coordinates: Coordinates,
+}
 
+/// A bomb of a board exploded at the coordinates, costing a life or ending the game, or scoring a point in the Flags duel
 #[derive(Debug, Copy, Clone, Event)]
-pub struct ExplosionEvent;
+pub struct ExplosionEvent {
+	pub board: Entity,
+	pub coordinates: Coordinates,
+}
+
+/// The countdown of a board reached zero, the game on it is lost
+#[derive(Debug, Copy, Clone, Event)]
+pub struct TimeoutEvent(pub Entity);
 
 #[derive(Debug, Copy, Clone, Event)]
 pub struct RestartEvent;
@@ -30,145 +55,512 @@ pub struct RestartEvent;
 #[derive(Event)]
 pub struct ChangeDifficultyEvent(pub DifficultySettings);
 
+#[derive(Debug, Clone, Event)]
+pub struct LoadBoardEvent(pub PathBuf);
+
+#[derive(Debug, Copy, Clone, Event)]
+pub struct ExportBoardEvent;
+
+/// Starts the game over on the same board, to try again after a loss
+#[derive(Debug, Copy, Clone, Event)]
+pub struct RetryBoardEvent;
+
+/// Starts the daily challenge of the current difficulty, or of the intermediate one for custom difficulties
+#[derive(Debug, Copy, Clone, Event)]
+pub struct StartDailyEvent;
+
 pub fn update_difficulty(
 	mut ev_difficulty: EventReader<ChangeDifficultyEvent>,
 	mut ev_restart: EventWriter<RestartEvent>,
 	mut cfg: ResMut<GameConfig>
 ) {
 	for ev in ev_difficulty.read() {
-		cfg.difficulty = ev.0;
+		cfg.difficulty = ev.0.clone();
+		// Changing the difficulty leaves the daily challenge
+		cfg.generation.daily = None;
+		cfg.difficulty_given = false;
+	}
+	save_settings(&cfg);
+	ev_restart.send(RestartEvent);
+}
+
+
+/// Loads a board or a replay file, then restarts the game on it
+///
+/// The difficulty is changed to match the board, so the following restarts generate grids of the same size
+pub fn load_board_event_handler(
+	mut ev_load: EventReader<LoadBoardEvent>,
+	mut ev_restart: EventWriter<RestartEvent>,
+	mut board_to_load: ResMut<BoardToLoad>,
+	mut retry: ResMut<RetryCount>,
+	mut loaded: ResMut<LoadedBoard>,
+	mut next_state: ResMut<NextState<AppState>>,
+	mut cfg: ResMut<GameConfig>,
+) {
+	for ev in ev_load.read() {
+		match BoardFile::load(&ev.0) {
+			Ok(file) => {
+				info!("Loading board {}", ev.0.display());
+				let grid = file.grid();
+				cfg.difficulty.grid_size = Coordinates::new(grid.width(), grid.height());
+				cfg.difficulty.bomb_count = grid.bomb_count();
+				cfg.difficulty.topology = grid.topology();
+				cfg.difficulty.kernel = grid.kernel().clone();
+				cfg.difficulty.mask = grid.mask().cloned();
+				cfg.difficulty.mines_per_cell = grid.mines_per_cell();
+				cfg.difficulty_given = true;
+				cfg.generation.daily = None;
+				retry.0 = 0;
+				loaded.0 = true;
+				board_to_load.0 = Some(file);
+				ev_restart.send(RestartEvent);
+				next_state.set(AppState::InGame);
+			},
+			Err(err) => error!("{}", err),
+		}
 	}
+}
+
+/// Restarts the game on the board that was just played, with the same mines
+///
+/// The board is loaded like a board file, so `game_setup` does not generate a new grid
+pub fn retry_board_event_handler(
+	mut ev_retry: EventReader<RetryBoardEvent>,
+	mut ev_restart: EventWriter<RestartEvent>,
+	mut board_to_load: ResMut<BoardToLoad>,
+	mut retry: ResMut<RetryCount>,
+	q_boards: Query<&Replay>,
+	cfg: Res<GameConfig>,
+) {
+	ev_retry.clear();
+	let Ok(replay) = q_boards.get_single() else { return };
+	retry.0 += 1;
+	info!("Retrying the same board, attempt #{}", retry.0 + 1);
+	board_to_load.0 = Some(BoardFile::Board(replay.restart(cfg.generation.retry_first_click)));
 	ev_restart.send(RestartEvent);
 }
 
+/// Saves the current board and the state of the game in the boards directory
+///
+/// Classic square boards, without kernel, mask nor stacked mines, are also exported in the MBF format and their game in the RawVF format, to be opened by the community tools
+pub fn export_board_event_handler(
+	q_boards: Query<(&GameSession, &Replay)>,
+) {
+	let Ok((session, replay)) = q_boards.get_single() else { return };
+	let board_path = new_board_path("txt");
+	match session.save(&board_path) {
+		Ok(()) => info!("Board exported to {}", board_path.display()),
+		Err(err) => error!("{}", err),
+	}
+	let grid = session.grid();
+	let is_classic = grid.topology() == TopologyKind::Square && *grid.kernel() == Kernel::Moore
+		&& grid.mask().is_none() && grid.mines_per_cell() == 1;
+	if !is_classic { return }
+
+	let mbf_path = board_path.with_extension("mbf");
+	let replay_path = board_path.with_extension("rawvf");
+	let result = session.grid().to_mbf()
+		.and_then(|bytes| std::fs::write(&mbf_path, bytes).map_err(|err| format!("Cannot write board file {}: {}", mbf_path.display(), err)))
+		.and_then(|()| replay.to_rawvf())
+		.and_then(|text| std::fs::write(&replay_path, text).map_err(|err| format!("Cannot write replay file {}: {}", replay_path.display(), err)));
+	match result {
+		Ok(()) => info!("Board exported to {} and {}", mbf_path.display(), replay_path.display()),
+		Err(err) => error!("{}", err),
+	}
+}
+
+/// Sends the actions of the replay being played back, as the game time goes
+pub fn replay_playback_system(
+	q_boards: Query<(Entity, &GameTime)>,
+	mut playback: ResMut<ReplayPlayback>,
+	mut ev_uncover: EventWriter<UncoverCellEvent>,
+	mut ev_flag: EventWriter<FlagCellEvent>,
+) {
+	let Ok((board, time)) = q_boards.get_single() else { return };
+	for ev in playback.advance(time.stopwatch.elapsed_secs()) {
+		let coordinates = ev.coordinates;
+		match ev.action {
+			ReplayAction::Reveal => { ev_uncover.send(UncoverCellEvent { board, coordinates }); },
+			ReplayAction::Flag => { ev_flag.send(FlagCellEvent { board, coordinates }); },
+		}
+	}
+}
 
+/// Run condition disabling the player's inputs while a replay is played back
+pub fn is_replaying(playback: Res<ReplayPlayback>) -> bool {
+	playback.is_playing()
+}
 
-/// Despawns the grid so we can later generate a new one
+/// Despawns the boards so we can later generate new ones
 pub fn despawn_grid(
 	mut cmd: Commands,
 		q_grid: Query<Entity, With<GridContainer>>,
 ) {
 	info!("Despawning the grid to restart the game...");
-	if let Ok(entity) = q_grid.get_single() {
+	for entity in &q_grid {
 		cmd.entity(entity).despawn_recursive()
 	}
 }
 
 /// Handles UncoverCellEvents
 ///
-/// We cannot simply access the cover's Parent so we instead add a `Uncover` marker it
-/// so we can later query all marked Covers and their parents
-/// hence allowing us to check if the cell contains a bomb
+/// The cell is revealed in the `GameSession` of its board, which computes the whole opening if the cell is empty
+/// and tells us if we blew up or won the game. We then only have to despawn the covers of every revealed cell.
+/// The end of the game is left to the game the board is part of, watching the outcome of the session.
 pub fn uncover_event_handler(
-		cfg: Res<GameConfig>,
 	mut cmd: Commands,
-	mut covered_cells: ResMut<CoveredCells>,
-	mut next_state: ResMut<NextState<AppState>>,
-	// mut ev_victory: EventWriter<VictoryEvent>,
+	mut q_boards: Query<(&mut GameSession, &mut Replay, &mut GameTime, &mut CoveredCells)>,
+		cfg: Res<GameConfig>,
+	mut duel: Option<ResMut<FlagsDuel>>,
+	mut ev_explosion: EventWriter<ExplosionEvent>,
+	mut ev_sound: EventWriter<SoundEvent>,
 	mut ev_uncover: EventReader<UncoverCellEvent>,
 ) {
 	for ev in ev_uncover.read() {
-		let cover = covered_cells.0.remove(&ev.0);
-		if covered_cells.0.iter().count() == cfg.difficulty.bomb_count as usize {
-			next_state.set(AppState::Victory);
+		let Ok((mut session, mut replay, mut time, mut covered_cells)) = q_boards.get_mut(ev.board) else { continue };
+		let outcome = session.reveal(ev.coordinates);
+		if outcome != RevealOutcome::Ignored {
+			replay.record(time.stopwatch.elapsed_secs(), ReplayAction::Reveal, ev.coordinates);
+		}
+		// In the Flags duel, the game ends as soon as a player claimed more than half of the mines
+		let mut won = matches!(outcome, RevealOutcome::Victory(_));
+		if let Some(duel) = &mut duel {
+			duel.play(&outcome, &session);
+			won |= duel.result(&session).is_some();
 		}
-		match cover {
-			None => {
-				// debug!("The Cell {} is already uncovered !", ev.0); // Big spammer
+		if won {
+			ev_sound.send(SoundEvent(Sound::Victory));
+		}
+		let revealed = match outcome {
+			RevealOutcome::Ignored => continue, // The Cell is already uncovered, flagged or out of the grid
+			RevealOutcome::Safe(cells) => {
+				// In countdown mode, every safe cell revealed gives the player some more time
+				time.add_bonus(cfg.difficulty.time_bonus * cells.len() as f32);
+				let sound = if cells.len() >= BIG_OPENING { Sound::Opening } else { Sound::Reveal };
+				ev_sound.send(SoundEvent(sound));
+				cells
 			},
-			Some(entity) => {
-				// debug!("Uncovering cell {}", ev.0); // Big spammer
-				cmd.entity(entity).insert(Uncover);
+			RevealOutcome::Explosion(coord) | RevealOutcome::LifeLost(coord) | RevealOutcome::Claimed(coord) => {
+				ev_explosion.send(ExplosionEvent { board: ev.board, coordinates: coord });
+				vec![coord]
 			},
-		}
+			RevealOutcome::Victory(cells) => cells,
+		};
+		let covers = revealed.into_iter()
+			.filter_map(|coord| covered_cells.0.remove(&coord).map(|entity| (coord, entity)));
+		remove_covers(&mut cmd, &cfg, ev.coordinates, covers);
 	}
 }
 
-/// Queries the children all cells with a `Uncover` marker, then delete
-pub fn uncover_cell(
-	mut cmd: Commands,
-	mut ev_explosion: EventWriter<ExplosionEvent>,
-	mut ev_uncover: EventWriter<UncoverCellEvent>,
-		q_cell: Query<(Entity, &Parent), With<Uncover>>,
-		q_parents: Query<(&Coordinates, Option<&Bomb>, Option<&EmptyCell>)>,
-) {
-	for (entity, parent) in q_cell.iter() {
-		cmd.entity(entity).despawn_recursive();
-		if let Ok((coord, is_bomb, is_empty)) = q_parents.get(parent.get()) {
-			if is_bomb.is_some() {
-				ev_explosion.send(ExplosionEvent);
-			}
-			if is_empty.is_some() {
-				// info!("Cell {coord} is empty, propagating event to all adjacent empty cells"); // Big spammer
-				// Iterates over all neigbors of the cell and sends an event for each one
-				NEIGHBORS.iter()
-					.for_each(|neighbor| {
-						let neighbor_coords = *coord + *neighbor;
-						ev_uncover.send(UncoverCellEvent(neighbor_coords));
-					});
-			}
-		}
+/// Switches to the GameOver or the Victory state once the game on the board is over, in the solo game
+///
+/// A board loaded from a file may already be over, it then goes straight to the end of the game
+pub fn update_game_state(
+	q_boards: Query<&GameSession, Changed<GameSession>>,
+	duel: Option<Res<FlagsDuel>>,
+	mut next_state: ResMut<NextState<AppState>>,
+) {
+	let Ok(session) = q_boards.get_single() else { return };
+	if duel.is_some_and(|duel| duel.result(session).is_some()) {
+		next_state.set(AppState::Victory);
+		return
+	}
+	match session.outcome() {
+		Outcome::Victory => next_state.set(AppState::Victory),
+		Outcome::Defeat => next_state.set(AppState::GameOver),
+		Outcome::Playing => {},
+	}
+}
+
+/// Returns the sprite of a flag, placed over the cell's cover
+pub fn flag_bundle(cfg: &GameConfig, texture: Handle<Image>) -> SpriteBundle {
+	SpriteBundle {
+		sprite: Sprite {
+			custom_size: Some(Vec2::splat((cfg.ui_style.cell_size - cfg.ui_style.cell_padding - 2) as f32)),
+			..default()
+		},
+		transform: Transform::from_xyz(
+			cfg.ui_style.cell_size as f32 / 2.,
+			cfg.ui_style.cell_size as f32 / 2.,
+			3.
+		),
+		texture,
+		..default()
+	}
+}
+
+/// Label showing how many mines or flags are stacked in a cell, as a child of the sprite of the mine or the flag
+pub fn stack_count_bundle(cfg: &GameConfig, font: Handle<Font>, count: u8, color: Color) -> Text2dBundle {
+	Text2dBundle {
+		text: Text::from_section(count.to_string(), TextStyle {
+			color,
+			font,
+			font_size: cfg.ui_style.font_size as f32 / 2.,
+		}),
+		// In the bottom right corner of the cell
+		transform: Transform::from_xyz(
+			cfg.ui_style.cell_size as f32 / 4.,
+			-(cfg.ui_style.cell_size as f32) / 4.,
+			// Just over the sprite, the mine sprites and their numbers staying under the cover of their cell
+			0.5
+		),
+		..default()
 	}
 }
 
+/// Places a flag sprite on a cell, with the number of flags if there are several, and marks the cell with a Flag
+///
+/// Returns the entity of the flag sprite
+pub fn place_flag(cmd: &mut Commands, cell: Entity, cfg: &GameConfig, srv: &AssetServer, count: u8) -> Entity {
+	let flag_sprite = srv.load("sprites/flag.png");
+	let mut child_id: Flag = Flag { id: cell }; // Placeholder value, because cargo won't compile without
+	cmd.entity(cell)
+	   .with_children(|parent| {
+		   // Spawn a child containing the Flag sprite
+		   let mut flag = parent.spawn(flag_bundle(cfg, flag_sprite));
+		   flag.insert(bevy::core::Name::new("Flag"));
+		   if count > 1 {
+			   flag.with_children(|parent| {
+				   parent.spawn(stack_count_bundle(cfg, srv.load("fonts/FiraSans-Black.ttf"), count, cfg.theme.flags))
+					   .insert(bevy::core::Name::new("Flag count"));
+			   });
+		   }
+		   // Store the child's ID in a component
+		   child_id = Flag { id: flag.id() };
+	   }).insert(child_id);
+	drop_flag(cmd, cfg, child_id.id);
+	child_id.id
+}
+
 /// Handles FlagCellEvent, toggling the flags on the suspected trapped cells
 ///
-/// This time i tried an other technique than uncover cells.
-/// We query every cell, then check if the coordinates match with the event, is they do match,
-/// we then check if there's a flag already present on the cell, If there is a flag, we remove it,
-/// If there is no flag on the cell, we add a flag_sprite and a Flag marker
+/// The flag is toggled in the `GameSession` first, which ignores revealed cells.
+/// We then query every cell and check if the coordinates match with the event, if they do match,
+/// we either add a flag_sprite and a Flag marker, update the number of stacked flags, or remove them
 pub fn flag_event_handler(
 	mut ev_flags: EventReader<FlagCellEvent>,
 	mut cmd: Commands,
+	mut q_boards: Query<(&mut GameSession, &mut Replay, &GameTime)>,
 		srv: Res<AssetServer>,
 		cfg: Res<GameConfig>,
-		q_cells: Query<(&Coordinates, Entity, Option<&Flag>)>,
+		q_cells: Query<(Entity, &Coordinates, &Parent, Option<&Flag>)>,
+	mut ev_sound: EventWriter<SoundEvent>,
 ) {
 	for ev in ev_flags.read() {
-		debug!("Toggling Flag on cell {}", ev.0);
-		for (coord, entity, flag) in &q_cells {
-			if *coord == ev.0 {
-				match flag {
-					None => {
-						let flag_sprite = srv.load("sprites/flag.png");
-						let mut child_id: Flag = Flag { id: entity }; // Placeholder value, because cargo won't compile without
-						cmd.entity(entity)
-						   .with_children(|parent| {
-							   // Spawn a child containing the Flag sprite
-							   let id = parent.spawn(SpriteBundle {
-								   sprite: Sprite {
-									   custom_size: Some(Vec2::splat((cfg.ui_style.cell_size - cfg.ui_style.cell_padding - 2) as f32)),
-									   ..default()
-								   },
-								   transform: Transform::from_xyz(
-									   cfg.ui_style.cell_size as f32 / 2.,
-									   cfg.ui_style.cell_size as f32 / 2.,
-									   3.
-								   ),
-								   texture: flag_sprite.clone(),
-								   ..default()
-							   }).insert(bevy::core::Name::new("Flag")).id();
-							   // Store the child's ID in a component
-							   child_id = Flag { id };
-						   }).insert(child_id);
-					},
-					Some(&flag_id) => {
-						// Despawn the flag
-						cmd.entity((flag_id).id).despawn();
-						// Remove the component containing the flag's id
-						cmd.entity(entity).remove::<Flag>();
-					}
-				}
-			}
+		let Ok((mut session, mut replay, time)) = q_boards.get_mut(ev.board) else { continue };
+		let outcome = session.toggle_flag(ev.coordinates);
+		if outcome == FlagOutcome::Ignored { continue }
+		replay.record(time.stopwatch.elapsed_secs(), ReplayAction::Flag, ev.coordinates);
+		let sound = if outcome == FlagOutcome::Removed { Sound::FlagRemoved } else { Sound::FlagPlaced };
+		ev_sound.send(SoundEvent(sound));
+		debug!("Toggling Flag on cell {}", ev.coordinates);
+		if let Some((entity, flag)) = cell_entity(&q_cells, ev.board, ev.coordinates) {
+			show_flag_outcome(&mut cmd, entity, flag, outcome, &cfg, &srv);
 		}
 	}
 }
 
-/// Just sets game state to GameOver, everything else is handled by the Game State
+/// Updates the flag sprites of a cell after toggling its flag: a flag is added, its number of flags updated, or it is removed
+pub fn show_flag_outcome(cmd: &mut Commands, cell: Entity, flag: Option<&Flag>, outcome: FlagOutcome, cfg: &GameConfig, srv: &AssetServer) {
+	match (outcome, flag) {
+		(FlagOutcome::Placed, None) => {
+			place_flag(cmd, cell, cfg, srv, 1);
+		},
+		(FlagOutcome::Stacked(count), Some(&flag_id)) => {
+			let font = srv.load("fonts/FiraSans-Black.ttf");
+			cmd.entity(flag_id.id)
+			   .despawn_descendants()
+			   .with_children(|parent| {
+				   parent.spawn(stack_count_bundle(cfg, font, count, cfg.theme.flags))
+					   .insert(bevy::core::Name::new("Flag count"));
+			   });
+		},
+		(FlagOutcome::Removed, Some(&flag_id)) => {
+			// Despawn the flag, with the number of stacked flags
+			cmd.entity((flag_id).id).despawn_recursive();
+			// Remove the component containing the flag's id
+			cmd.entity(cell).remove::<Flag>();
+		},
+		_ => {}
+	}
+}
+
+/// Shows the bombs that exploded, the end of the game being handled by the game the board is part of
+///
+/// While the player has lives left, the bomb that exploded is flagged by the `GameSession` and we only show its flag.
+/// In the Flags duel, the bomb is a point for the player who found it, its flag taking the color of the player
 pub fn explosion_event_handler(
-	ev_explosion: EventReader<ExplosionEvent>,
-	mut next_state: ResMut<NextState<AppState>>,
+	mut ev_explosion: EventReader<ExplosionEvent>,
+	mut cmd: Commands,
+	q_boards: Query<&GameSession>,
+	duel: Option<Res<FlagsDuel>>,
+	srv: Res<AssetServer>,
+	cfg: Res<GameConfig>,
+	q_cells: Query<(Entity, &Coordinates, &Parent), Without<Flag>>,
+) {
+	for ev in ev_explosion.read() {
+		let Ok(session) = q_boards.get(ev.board) else { continue };
+		if session.outcome() == Outcome::Defeat {
+			info!("Tough luck, you just blew up! Try skill next time.");
+			continue
+		}
+		match &duel {
+			Some(duel) => info!("{} found the mine at {}, score: {}", duel.name(duel.turn()), ev.coordinates, duel.score(duel.turn())),
+			None => info!("Boom! The bomb at {} cost a life, {} left", ev.coordinates, session.lives()),
+		}
+		let cells = q_cells.iter().map(|(entity, coord, parent)| (entity, coord, parent, ()));
+		if let Some((entity, ())) = cell_entity(cells, ev.board, ev.coordinates) {
+			let flag = place_flag(&mut cmd, entity, &cfg, &srv, session.flags_at(ev.coordinates));
+			if let Some(duel) = &duel {
+				let color = cfg.theme.player_color(duel.turn());
+				cmd.entity(flag).add(move |mut entity: EntityWorldMut| {
+					if let Some(mut sprite) = entity.get_mut::<Sprite>() { sprite.color = color }
+				});
+			}
+		}
+	}
+}
+/// Resolves the boards once their game is lost, the classic way
+///
+/// Every mine is revealed, except the flagged ones which keep their flag, the mine that was hit is shown in red
+/// and the wrong flags are crossed out
+pub fn reveal_board_on_defeat(
+	mut cmd: Commands,
+	mut q_boards: Query<(Entity, &GameSession, &mut CoveredCells), (Changed<GameSession>, Without<Resolved>)>,
+	cfg: Res<GameConfig>,
+	srv: Res<AssetServer>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+	mut q_cells: Query<(&Coordinates, Entity, &Parent, Option<&mut Sprite>, Option<&mut Handle<ColorMaterial>>)>,
+) {
+	for (board, session, mut covered_cells) in &mut q_boards {
+		if session.outcome() != Outcome::Defeat { continue }
+		cmd.entity(board).insert(Resolved);
+		// A game lost to the countdown has no mine hit, its mines are then revealed from the centre of the board
+		let origin = session.mine_hit()
+			.unwrap_or_else(|| Coordinates::new(session.grid().width() / 2, session.grid().height() / 2));
+		let covers = session.hidden_mines().into_iter()
+			.filter_map(|coord| covered_cells.0.remove(&coord).map(|entity| (coord, entity)));
+		chain_mines(&mut cmd, &cfg, origin, covers);
+		mark_mistakes(&mut cmd, session, board, &cfg, &srv, &mut materials, &mut q_cells);
+	}
+}
+
+/// Shows the mine that was hit in red and crosses out the wrong flags of a board
+fn mark_mistakes(
+	cmd: &mut Commands,
+	session: &GameSession,
+	board: Entity,
+	cfg: &GameConfig,
+	srv: &AssetServer,
+	materials: &mut Assets<ColorMaterial>,
+	q_cells: &mut Query<(&Coordinates, Entity, &Parent, Option<&mut Sprite>, Option<&mut Handle<ColorMaterial>>)>,
+) {
+	let mine_hit = session.mine_hit();
+	let wrong_flags = session.wrong_flags();
+	let font = srv.load("fonts/FiraSans-Black.ttf");
+	for (coord, entity, parent, sprite, material) in q_cells.iter_mut() {
+		if parent.get() != board { continue }
+		if Some(*coord) == mine_hit {
+			// Square cells are sprites, the other shapes are meshes with a material
+			if let Some(mut sprite) = sprite { sprite.color = cfg.theme.mistakes }
+			if let Some(mut material) = material { *material = materials.add(cfg.theme.mistakes) }
+		}
+		if wrong_flags.contains(coord) {
+			cmd.entity(entity).with_children(|parent| {
+				parent.spawn(Text2dBundle {
+					text: Text::from_section("X", TextStyle {
+						color: cfg.theme.mistakes,
+						font: font.clone(),
+						font_size: cfg.ui_style.font_size as f32,
+					}),
+					// Over the flag
+					transform: Transform::from_xyz(
+						cfg.ui_style.cell_size as f32 / 2.,
+						cfg.ui_style.cell_size as f32 / 2.,
+						4.
+					),
+					..default()
+				}).insert(bevy::core::Name::new("Wrong flag"));
+			});
+		}
+	}
+}
+
+/// Ends the game on a board when its countdown reaches zero, like an explosion would
+pub fn timeout_event_handler(
+	mut ev_timeout: EventReader<TimeoutEvent>,
+	mut q_boards: Query<&mut GameSession>,
+) {
+	for ev in ev_timeout.read() {
+		let Ok(mut session) = q_boards.get_mut(ev.0) else { continue };
+		if session.outcome() != Outcome::Playing { continue }
+		info!("Time's up! The clock was faster than you.");
+		session.time_out();
+	}
+}
+
+/// Switches to the board of the day, with the standard settings of the current difficulty
+pub fn start_daily_event_handler(
+	mut ev_daily: EventReader<StartDailyEvent>,
+	mut ev_restart: EventWriter<RestartEvent>,
+	mut cfg: ResMut<GameConfig>,
 ) {
-	info!("Tough luck, you just blew up! Try skill next time.");
-	next_state.set(AppState::GameOver);
-}
\ No newline at end of file
+	ev_daily.clear();
+	let level = cfg.difficulty.standard().unwrap_or(StandardDifficulty::Intermediate);
+	let daily = DailyChallenge::today(level);
+	info!("Starting the {}", daily);
+	cfg.difficulty = daily.difficulty();
+	cfg.generation.daily = Some(daily);
+	ev_restart.send(RestartEvent);
+}
+
+/// Records the time of a ranked victory in the high scores with the metrics of the game, then saves them
+pub fn record_high_score(
+	q_boards: Query<(&GameSession, &GameTime, &ThreeBv, &Clicks)>,
+	ranked: Res<RankedGame>,
+	mut scores: ResMut<HighScores>,
+) {
+	let Some(bucket) = &ranked.0 else { return };
+	let Ok((session, time, three_bv, clicks)) = q_boards.get_single() else { return };
+	let secs = time.stopwatch.elapsed_secs();
+	let metrics = GameMetrics::new(three_bv, session, *clicks, secs);
+	info!("{}", metrics);
+	match scores.record(bucket, secs, Some(metrics)) {
+		Some(0) => info!("New best time for {}: {:.2} secs !", bucket, secs),
+		Some(rank) => info!("Time ranked #{} for {}: {:.2} secs", rank + 1, bucket, secs),
+		None => info!("{:.2} secs, not in the best times for {}", secs, bucket),
+	}
+	if let Err(err) = scores.save(HIGH_SCORES_FILE) {
+		error!("{}", err);
+	}
+}
+
+/// Counts the game that just ended in the statistics, then saves them
+///
+/// The games are counted by difficulty, the retries of a board apart from the new boards.
+/// The replays, the boards loaded from a file and the Flags duels are not counted,
+/// a loaded board being possibly half played or played again and again.
+pub fn record_statistics(
+	q_boards: Query<&GameSession>,
+	cfg: Res<GameConfig>,
+	retry: Res<RetryCount>,
+	loaded: Res<LoadedBoard>,
+	playback: Res<ReplayPlayback>,
+	duel: Option<Res<FlagsDuel>>,
+	mut statistics: ResMut<Statistics>,
+) {
+	if playback.has_replay() || loaded.0 || duel.is_some() { return }
+	let Ok(session) = q_boards.get_single() else { return };
+	let bucket = statistics_bucket(&cfg, &retry);
+	let record = statistics.record(&bucket, session.outcome() == Outcome::Victory);
+	info!("{}: {}", bucket, record);
+	if let Err(err) = statistics.save(STATISTICS_FILE) {
+		error!("{}", err);
+	}
+}
+
+/// Returns the statistics bucket of the game being played
+pub fn statistics_bucket(cfg: &GameConfig, retry: &RetryCount) -> String {
+	let bucket = cfg.difficulty.standard().map_or(CUSTOM_BUCKET, |level| level.name());
+	if retry.0 > 0 { retry_bucket(bucket) } else { bucket.to_string() }
+}
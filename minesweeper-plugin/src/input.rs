@@ -1,51 +1,120 @@
 use bevy::transform::components::GlobalTransform;
 use bevy::input::ButtonInput;
 use bevy::math::Vec2;
-use bevy::prelude::{Camera, EventWriter, MouseButton, Query, Res, Window};
+use bevy::prelude::{Camera, Entity, EventReader, EventWriter, MouseButton, Query, Res, Window, With};
 use bevy::log::debug;
+use bevy::window::FileDragAndDrop;
 
 use crate::{
+	audio::{Sound, SoundEvent},
+	components::{Coordinates, GridContainer},
 	config::GameConfig,
-	events::{FlagCellEvent, UncoverCellEvent}
+	events::{FlagCellEvent, LoadBoardEvent, UncoverCellEvent},
+	resources::{CellState, Click, Clicks, GameSession},
 };
 
 /// Reads mouse events, calculate the cell clicked and send the appropriate events
+///
+/// The left button reveals, or chords on a revealed number, the middle button chords and the right button flags.
+/// There is no chording in the Flags duel, where every safe cell revealed would pass the turn again.
 pub fn mouse_button_events(
 	cfg: Res<GameConfig>,
 	windows_q: Query<&Window>,
 	camera_q: Query<(&Camera, &GlobalTransform)>,
 	mouse_button: Res<ButtonInput<MouseButton>>,
+	mut q_board: Query<(Entity, &GameSession, &mut Clicks), With<GridContainer>>,
 	mut ev_uncover: EventWriter<UncoverCellEvent>,
 	mut ev_flag: EventWriter<FlagCellEvent>,
+	mut ev_sound: EventWriter<SoundEvent>,
 ) {
+	let Ok((board, session, mut clicks)) = q_board.get_single_mut() else { return };
 	let window = windows_q.single();
 	let (camera, camera_transform) = camera_q.single();
 
 	// For every mouse button pressed since last frame
 	for button in mouse_button.get_just_pressed() {
-		// Get the position of the mouse in the 2D World
-		if let Some(world_position) = window.cursor_position()
-		                                    .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)) {
-			// Get the mouse coordinates in the grid
-			let mut grid_coord = (world_position - Vec2::splat(cfg.ui_style.margin as f32)) / Vec2::splat( cfg.ui_style.cell_size as f32);
-
-			// If the position is in the grid bounds
-			if grid_coord.cmpge(Vec2::ZERO).all() &&
-				grid_coord.cmplt(cfg.difficulty.grid_size.into()).all() {
-				grid_coord = grid_coord.trunc(); // Truncate to avoid rounding errors
-
-				match button {
-					MouseButton::Left => {
-						// debug!("Left clicked on cell {} - {}", grid_coord.x, grid_coord.y);
-						ev_uncover.send(UncoverCellEvent(grid_coord.into()));
-					},
-					MouseButton::Right => {
-						// debug!("Right clicked on cell {} - {}", grid_coord.x, grid_coord.y);
-						ev_flag.send(FlagCellEvent(grid_coord.into()));
-					},
-					_ => {}
-				}
+		// If the mouse is on a cell of the grid
+		if let Some(grid_coord) = cursor_cell(&cfg, window, camera, camera_transform) {
+			let click = match button {
+				MouseButton::Left => Click::Left,
+				MouseButton::Middle => Click::Chord,
+				MouseButton::Right => Click::Right,
+				_ => continue,
+			};
+			if cfg.duel.is_some() && (click == Click::Chord || session.state(grid_coord) == Some(CellState::Revealed)) {
+				continue
 			}
+			// debug!("{:?} click on cell {}", click, grid_coord);
+			if play_click(click, board, grid_coord, session, &mut clicks, &mut ev_uncover, &mut ev_flag) {
+				ev_sound.send(SoundEvent(Sound::Chord));
+			}
+		}
+	}
+}
+
+/// Plays a click of the player on a cell of a board, counting it in the clicks of the board
+///
+/// Only the clicks of the player go through here, the replays and the computer sending their events directly.
+/// A left click on a revealed cell is a chord, which reveals the covered neighbors of a number with all its flags around it.
+/// Returns whether the click chorded on some cells, to play the sound of the chord.
+pub fn play_click(
+	click: Click,
+	board: Entity,
+	coordinates: Coordinates,
+	session: &GameSession,
+	clicks: &mut Clicks,
+	ev_uncover: &mut EventWriter<UncoverCellEvent>,
+	ev_flag: &mut EventWriter<FlagCellEvent>,
+) -> bool {
+	let click = match session.state(coordinates) {
+		Some(CellState::Revealed) if click == Click::Left => Click::Chord,
+		_ => click,
+	};
+	clicks.count(click, session, coordinates);
+	match click {
+		Click::Left => { ev_uncover.send(UncoverCellEvent { board, coordinates }); false },
+		Click::Right => { ev_flag.send(FlagCellEvent { board, coordinates }); false },
+		Click::Chord => {
+			debug!("Chording on {}", coordinates);
+			let neighbors = session.chord(coordinates);
+			for &neighbor in &neighbors {
+				ev_uncover.send(UncoverCellEvent { board, coordinates: neighbor });
+			}
+			!neighbors.is_empty()
+		},
+	}
+}
+
+/// Returns the cell under the mouse, if any and if it is not a hole of the board
+pub fn cursor_cell(cfg: &GameConfig, window: &Window, camera: &Camera, camera_transform: &GlobalTransform) -> Option<Coordinates> {
+	let world_position = cursor_position(window, camera, camera_transform)?;
+	board_cell(cfg, world_position - Vec2::splat(cfg.ui_style.margin as f32))
+}
+
+/// Returns the position of the mouse in the 2D World
+pub fn cursor_position(window: &Window, camera: &Camera, camera_transform: &GlobalTransform) -> Option<Vec2> {
+	window.cursor_position()
+	      .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+}
+
+/// Returns the cell at a position relative to the bottom left corner of a board, if it is not a hole of the board
+pub fn board_cell(cfg: &GameConfig, position: Vec2) -> Option<Coordinates> {
+	// The cells are shifted by half of the padding
+	let board_position = position - Vec2::splat(cfg.ui_style.cell_padding as f32 / 2.);
+	let size = cfg.difficulty.grid_size;
+	cfg.difficulty.topology.topology().cell_at(board_position, size.x, size.y, cfg.ui_style.cell_size as f32)
+		// The holes of the mask cannot be played on
+		.filter(|&coord| cfg.difficulty.mask.as_ref().map_or(true, |mask| mask.is_enabled(coord)))
+}
+
+/// Loads the board files dropped on the window
+pub fn file_drop_events(
+	mut ev_drop: EventReader<FileDragAndDrop>,
+	mut ev_load: EventWriter<LoadBoardEvent>,
+) {
+	for ev in ev_drop.read() {
+		if let FileDragAndDrop::DroppedFile { path_buf, .. } = ev {
+			ev_load.send(LoadBoardEvent(path_buf.clone()));
 		}
 	}
-}
\ No newline at end of file
+}
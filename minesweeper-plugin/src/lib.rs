@@ -3,52 +3,92 @@ extern crate core;
 
 use std::ops::DerefMut;
 use bevy::prelude::*;
-use bevy::sprite::Anchor;
+use bevy::ecs::system::EntityCommands;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::sprite::{Anchor, MaterialMesh2dBundle, Mesh2dHandle};
 use bevy::utils::HashMap;
 use bevy::window::WindowResolution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 
-mod config;
-mod components;
-mod resources;
+// The config, components and resources are public so the game's rules can be reused by other frontends
+pub mod config;
+pub mod components;
+pub mod resources;
 mod input;
 mod events;
 mod ui;
 mod time;
+mod race;
+mod duel;
+mod versus;
+mod board;
+mod animations;
+mod effects;
+mod audio;
 
 
 
 
 use crate::{
-	input::mouse_button_events,
+	input::{mouse_button_events, file_drop_events},
 	config::{
-		ColorTheme, GameConfig
+		ColorTheme, DifficultySettings, GameConfig, GenerationSettings
 	},
 	ui::{
-		MainSettings, GoToDifficultySettingsEvent, //GoToThemeSettingsEvent,
+		MainSettings, GoToDifficultySettingsEvent, GoToAudioSettingsEvent, //GoToThemeSettingsEvent,
+		spawn_audio_settings_ui, despawn_audio_settings_ui, audio_settings_interaction,
 		spawn_game_over_ui, spawn_main_game_ui, spawn_settings_ui, spawn_difficulty_settings_ui, spawn_victory_ui,
 		despawn_game_over_ui, despawn_victory_ui, despawn_settings_ui,
-		update_ui_timer, update_ui_flags,
-		settings_button_system, restart_button_system, retry_button_system, quit_button_system,
+		update_ui_timer, update_ui_flags, update_ui_lives, update_ui_duel, spawn_ghost_cells,
+		update_neighbor_preview, despawn_neighbor_preview, spawn_race_panel, update_race_panel,
+		settings_button_system, restart_button_system, retry_button_system, quit_button_system, show_board_button_system,
+		retry_board_button_system,
 		victory_quit_button, victory_restart_button,
 		main_settings_interaction, difficulty_settings_interaction,
 
 	},
 	events::{
-		ExplosionEvent, UncoverCellEvent, FlagCellEvent, RestartEvent, ChangeDifficultyEvent, //VictoryEvent,
-		uncover_cell, despawn_grid, update_difficulty,
-		explosion_event_handler, flag_event_handler, uncover_event_handler,
+		RestartEvent, ChangeDifficultyEvent, //VictoryEvent,
+		LoadBoardEvent, ExportBoardEvent, StartDailyEvent,
+		despawn_grid, update_difficulty, flag_bundle, stack_count_bundle,
+		flag_event_handler, uncover_event_handler, update_game_state,
+		load_board_event_handler, export_board_event_handler, replay_playback_system, is_replaying,
+		start_daily_event_handler, record_high_score,
+		RetryBoardEvent, retry_board_event_handler, record_statistics,
 	},
 	components::{
-		Bomb, BombNeighbors, EmptyCell, Coordinates, GridContainer
+		Bomb, BombNeighbors, EmptyCell, Coordinates, Flag, GridContainer
 	},
 	resources::{
-		CoveredCells, Grid, Cell,
+		BoardFile, BoardRng, BoardToLoad, GameSession, Grid, Cell, CellState, ReplayPlayback,
+		FlagsDuel, HighScores, LoadedBoard, RankedGame, RetryCount, SavedSettings, Statistics, Topology,
+		BoardRating, generate_filtered, GENERATION_BUDGET,
+		HIGH_SCORES_FILE, SETTINGS_FILE, STATISTICS_FILE,
+	},
+	animations::{
+		ToggleReduceMotionEvent,
+		toggle_reduce_motion_event_handler,
+	},
+	effects::{
+		ScreenShake,
+		spawn_confetti,
+	},
+	duel::{
+		ToggleDuelEvent,
+		toggle_duel_event_handler, computer_turn_system, duel_allows_input,
+	},
+	race::{
+		Race,
+		race_network_system, race_progress_system, race_send_actions, race_allows_input, is_racing,
 	},
 	time::{
-		GameTime,
-		update_timer, pause_timer, unpause_timer, restart_timer,
-	}
+		pause_timer, unpause_timer,
+	},
+	board::{BoardBundle, BoardPlugin, PendingSession},
+	versus::VersusPlugin,
 };
 
 
@@ -66,91 +106,409 @@ pub struct MinesweeperPlugin;
 impl Plugin for MinesweeperPlugin {
 	fn build(&self, app: &mut App) {
 		app.init_resource::<ColorTheme>()
-			.init_resource::<GameConfig>()
-			.init_resource::<GameTime>()
-			.init_resource::<CoveredCells>()
-			.add_event::<UncoverCellEvent>()
-			.add_event::<FlagCellEvent>()
-			.add_event::<RestartEvent>()
+			.init_resource::<GameConfig>();
+
+		// The config may have been inserted by the app before adding the plugin, to apply command line options
+		let rng = match app.world.resource::<GameConfig>().generation.seed {
+			Some(seed) => StdRng::seed_from_u64(seed),
+			None => StdRng::from_entropy(),
+		};
+
+		// The settings chosen in the settings menu are kept from one game to the next
+		match SavedSettings::load(SETTINGS_FILE) {
+			Ok(settings) => settings.apply(&mut app.world.resource_mut::<GameConfig>()),
+			Err(err) => warn!("{}", err),
+		}
+
+		// The boards are played the same way in every game
+		app.insert_resource(BoardRng(rng))
+			.add_plugins(BoardPlugin);
+
+		// A versus game has its own boards and inputs, none of the menus of the solo game are added
+		if app.world.resource::<GameConfig>().versus {
+			app.add_plugins(VersusPlugin);
+			return;
+		}
+
+		// Without a readable high scores file, the game is still playable and the scores start over
+		let scores = HighScores::load(HIGH_SCORES_FILE).unwrap_or_else(|err| {
+			warn!("{}", err);
+			HighScores::default()
+		});
+		let statistics = Statistics::load(STATISTICS_FILE).unwrap_or_else(|err| {
+			warn!("{}", err);
+			Statistics::default()
+		});
+
+		// The race connection is opened before the first frame, the game is played alone if it fails
+		let race_settings = app.world.resource::<GameConfig>().race.clone();
+		match race_settings.map(|settings| Race::connect(&settings)) {
+			Some(Ok(race)) => { app.insert_resource(race); },
+			Some(Err(err)) => {
+				error!("{}, playing alone", err);
+				app.world.resource_mut::<GameConfig>().race = None;
+			},
+			None => {},
+		}
+
+		// A board given on the command line is a loaded board
+		let loaded = app.world.get_resource::<BoardToLoad>().is_some_and(|board| board.0.is_some());
+		app.insert_resource(scores)
+			.insert_resource(statistics)
+			.insert_resource(LoadedBoard(loaded))
+			.init_resource::<RankedGame>()
+			.init_resource::<RetryCount>()
+			.init_resource::<BoardToLoad>()
+			.init_resource::<ReplayPlayback>()
 			// .add_event::<VictoryEvent>()
-			.add_event::<ExplosionEvent>()
 			// .add_event::<MainSettings>()
 			.add_event::<GoToDifficultySettingsEvent>()
 			.add_event::<ChangeDifficultyEvent>()
+			.add_event::<LoadBoardEvent>()
+			.add_event::<ExportBoardEvent>()
+			.add_event::<StartDailyEvent>()
+			.add_event::<ToggleDuelEvent>()
+			.add_event::<ToggleReduceMotionEvent>()
+			.add_event::<GoToAudioSettingsEvent>()
+			.add_event::<RetryBoardEvent>()
 			// .add_event::<GoToThemeSettingsEvent>()
 			.insert_state(AppState::InGame)
-			.add_systems(Startup, (update_window, spawn_camera, game_setup, spawn_main_game_ui))
+			.add_systems(Startup, (
+				update_window, spawn_camera, game_setup, spawn_main_game_ui,
+				spawn_race_panel.run_if(resource_exists::<Race>),
+			))
 			// .add_systems(PostStartup, unpause_gametimer)
 			.add_systems(Update, (
-				update_timer,
+				file_drop_events,
 				(
-					uncover_cell, update_ui_timer, update_ui_flags,
-					restart_button_system, settings_button_system,
-					mouse_button_events
+					update_game_state.after(uncover_event_handler),
+					update_ui_timer, update_ui_flags, update_ui_lives, update_ui_duel,
+					(restart_button_system, settings_button_system).run_if(not(is_racing)),
+					mouse_button_events.run_if(not(is_replaying)).run_if(race_allows_input).run_if(duel_allows_input),
+					computer_turn_system.run_if(resource_exists::<FlagsDuel>).run_if(not(is_replaying)),
+					update_neighbor_preview,
+					// After the setup and the handlers, so the new board is spawned before playing a replay
+					replay_playback_system.after(game_setup).after(uncover_event_handler).after(flag_event_handler),
 				).run_if(in_state(AppState::InGame)),
 				(
-					quit_button_system, retry_button_system
+					quit_button_system, show_board_button_system,
+					(retry_button_system, retry_board_button_system).run_if(not(is_racing))
 				).run_if(in_state(AppState::GameOver)),
 				(
-					victory_quit_button, victory_restart_button
+					victory_quit_button, victory_restart_button.run_if(not(is_racing))
 				).run_if(in_state(AppState::Victory)),
+				// The race goes on whatever the state of the game, a player who blew up still sees the opponent's progress
+				(
+					race_network_system,
+					race_progress_system,
+					race_send_actions,
+					update_race_panel,
+				).run_if(resource_exists::<Race>),
 				(
 					main_settings_interaction, difficulty_settings_interaction,
+					toggle_reduce_motion_event_handler.run_if(on_event::<ToggleReduceMotionEvent>()),
+					audio_settings_interaction,
+					spawn_audio_settings_ui.run_if(on_event::<GoToAudioSettingsEvent>()),
 				).run_if(in_state(AppState::Settings)),
+				// Before a restart, so a board finished by then is despawned with the previous game
+				finish_game_setup.run_if(resource_exists::<PendingSession>).before(despawn_grid),
 				(
 					despawn_grid,
 					game_setup.after(despawn_grid),
-					(update_window, update_camera, restart_timer).after(game_setup),
+					(update_window, update_camera).after(game_setup),
 				).run_if(on_event::<RestartEvent>()),
 				// spawn_victory_ui            .run_if(on_event::<VictoryEvent>()),
-				uncover_event_handler       .run_if(on_event::<UncoverCellEvent>()),
-				explosion_event_handler     .run_if(on_event::<ExplosionEvent>()),
-				flag_event_handler          .run_if(on_event::<FlagCellEvent>()),
 				spawn_difficulty_settings_ui.run_if(on_event::<GoToDifficultySettingsEvent>()),
-				update_difficulty           .run_if(on_event::<ChangeDifficultyEvent>())
+				update_difficulty           .run_if(on_event::<ChangeDifficultyEvent>()),
+				load_board_event_handler    .run_if(on_event::<LoadBoardEvent>()),
+				export_board_event_handler  .run_if(on_event::<ExportBoardEvent>()),
+				start_daily_event_handler   .run_if(on_event::<StartDailyEvent>()),
+				toggle_duel_event_handler   .run_if(on_event::<ToggleDuelEvent>()),
+				retry_board_event_handler   .run_if(on_event::<RetryBoardEvent>()),
 
 		   ))
-			.add_systems(OnEnter(AppState::GameOver), spawn_game_over_ui)
+			.add_systems(OnEnter(AppState::GameOver), (record_statistics, spawn_game_over_ui).chain())
 			.add_systems(OnExit(AppState::GameOver), despawn_game_over_ui)
-			.add_systems(OnEnter(AppState::Victory), spawn_victory_ui)
+			.add_systems(OnEnter(AppState::Victory), ((record_high_score, record_statistics, spawn_victory_ui).chain(), spawn_confetti))
 			.add_systems(OnExit(AppState::Victory), despawn_victory_ui)
-			.add_systems(OnExit(AppState::InGame), pause_timer)
+			.add_systems(OnExit(AppState::InGame), (pause_timer, despawn_neighbor_preview))
 			.add_systems(OnEnter(AppState::InGame), unpause_timer)
 			.add_systems(OnEnter(AppState::Settings), spawn_settings_ui)
-			.add_systems(OnExit(AppState::Settings), despawn_settings_ui);
+			.add_systems(OnExit(AppState::Settings), (despawn_settings_ui, despawn_audio_settings_ui));
+	}
+}
+
+
+/// Generates the grid of a new game according to the settings, and starts a session on it
+///
+/// With the no-guess option, the first opening of the grid is already revealed.
+/// With a filter, grids are generated until one passes it.
+/// When no grid was found within the `GENERATION_BUDGET`, a random grid is played instead.
+/// The daily challenge always plays the board of its day, whatever the options.
+fn new_session(difficulty: &DifficultySettings, generation: &GenerationSettings, rng: &mut StdRng) -> GameSession {
+	if let Some(daily) = generation.daily {
+		return GameSession::new(daily.grid());
+	}
+	let (width, height) = (difficulty.grid_size.x, difficulty.grid_size.y);
+	let mut empty = Grid::empty(width, height)
+		.with_topology(difficulty.topology)
+		.with_kernel(difficulty.kernel.clone())
+		.with_mines_per_cell(difficulty.mines_per_cell);
+	if let Some(mask) = &difficulty.mask {
+		empty = empty.with_mask(mask.clone());
+	}
+	let slow = generation.no_guess || difficulty.filter.is_some();
+	let generated = match slow {
+		true => generate_filtered(&empty, difficulty.bomb_count, generation.no_guess, difficulty.filter.as_ref(), rng, GENERATION_BUDGET),
+		false => None,
+	};
+	let (grid, start) = generated.unwrap_or_else(|| {
+		if slow {
+			warn!("Could not generate a grid{}{} in time, falling back to a random one",
+				if generation.no_guess { " solvable without guessing" } else { "" },
+				difficulty.filter.map(|filter| format!(" passing the filter {}", filter.name())).unwrap_or_default());
+		}
+		let mut grid = empty.clone();
+		grid.set_bombs_with_rng(difficulty.bomb_count, rng);
+		(grid, None)
+	});
+	let mut session = GameSession::new(grid).with_lives(difficulty.lives);
+	if let Some(start) = start {
+		session.reveal(start);
+	}
+	session
+}
+
+/// Starts a new session on the config, returning it at once unless its grid is generated in the background
+///
+/// The no-guess and the filtered grids may take a few seconds to find, they are generated on the async compute pool
+/// from a seed drawn from the board's random generator, so a seeded game still plays the same grids.
+/// A `PendingSession` is then inserted, the board being spawned once it is ready.
+pub(crate) fn request_session(cmd: &mut Commands, cfg: &GameConfig, rng: &mut StdRng) -> Option<GameSession> {
+	if cfg.generation.daily.is_some() || !(cfg.generation.no_guess || cfg.difficulty.filter.is_some()) {
+		return Some(new_session(&cfg.difficulty, &cfg.generation, rng));
 	}
+	let (difficulty, generation, seed) = (cfg.difficulty.clone(), cfg.generation, rng.gen());
+	cmd.insert_resource(PendingSession::spawn(async move {
+		new_session(&difficulty, &generation, &mut StdRng::seed_from_u64(seed))
+	}));
+	None
+}
+
+/// Returns the high-score bucket of a new game, using up the ranked attempt of the daily challenge
+///
+/// Only the standard difficulties are ranked, the daily challenges only once per day
+fn ranked_bucket(cfg: &GameConfig, scores: &mut HighScores) -> Option<String> {
+	if cfg.duel.is_some() { return None }
+	if let Some(daily) = cfg.generation.daily {
+		let bucket = daily.bucket();
+		if !scores.use_attempt(&bucket) {
+			info!("The ranked attempt of the {} was already used, this game is not ranked", daily);
+			return None;
+		}
+		if let Err(err) = scores.save(HIGH_SCORES_FILE) {
+			error!("{}", err);
+		}
+		return Some(bucket);
+	}
+	let level = cfg.difficulty.standard()?;
+	// The grids solvable without guessing are easier, they get their own buckets, and so do the filtered grids
+	let mut bucket = level.name().to_string();
+	if cfg.generation.no_guess {
+		bucket.push_str("-no-guess");
+	}
+	if let Some(filter) = &cfg.difficulty.filter {
+		bucket = format!("{}-{}", bucket, filter.name());
+	}
+	Some(bucket)
+}
+
+/// The shape every cell of the board is drawn with
+///
+/// Square cells are sprites, the cells of other shapes are meshes built from the outline given by the topology,
+/// both sharing the same anchor in the bottom left corner
+enum CellShape {
+	Square { size: f32, cell: Color, cover: Color },
+	Polygon { mesh: Mesh2dHandle, cell: Handle<ColorMaterial>, cover: Handle<ColorMaterial> },
 }
 
+impl CellShape {
+	fn new(topology: &dyn Topology, cfg: &GameConfig, meshes: &mut Assets<Mesh>, materials: &mut Assets<ColorMaterial>) -> Self {
+		let size = (cfg.ui_style.cell_size - cfg.ui_style.cell_padding) as f32;
+		match topology.cell_outline(size) {
+			None => CellShape::Square { size, cell: cfg.theme.cell, cover: cfg.theme.hidden_cell },
+			Some(outline) => CellShape::Polygon {
+				mesh: meshes.add(polygon_mesh(&outline, size)).into(),
+				cell: materials.add(cfg.theme.cell),
+				cover: materials.add(cfg.theme.hidden_cell),
+			},
+		}
+	}
+
+	/// Inserts the shape of a cell, or of its cover, in an entity
+	fn insert(&self, cmd: &mut EntityCommands, is_cover: bool, transform: Transform) {
+		match self {
+			CellShape::Square { size, cell, cover } => {
+				cmd.insert(SpriteBundle {
+					sprite: Sprite {
+						color: if is_cover { *cover } else { *cell },
+						custom_size: Some(Vec2::splat(*size)),
+						anchor: Anchor::BottomLeft,
+						..default()
+					},
+					transform,
+					..default()
+				});
+			},
+			CellShape::Polygon { mesh, cell, cover } => {
+				cmd.insert(MaterialMesh2dBundle {
+					mesh: mesh.clone(),
+					material: if is_cover { cover.clone() } else { cell.clone() },
+					transform,
+					..default()
+				});
+			},
+		}
+	}
+}
+
+/// Builds the mesh of a convex polygon, as a fan of triangles around its first corner
+fn polygon_mesh(outline: &[Vec2], size: f32) -> Mesh {
+	let positions: Vec<[f32; 3]> = outline.iter().map(|corner| [corner.x, corner.y, 0.]).collect();
+	let normals = vec![[0., 0., 1.]; outline.len()];
+	let uvs: Vec<[f32; 2]> = outline.iter().map(|corner| [corner.x / size, 1. - corner.y / size]).collect();
+	let indices = (1..outline.len() as u32 - 1).flat_map(|i| [0, i, i + 1]).collect();
+	Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+		.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+		.with_inserted_indices(Indices::U32(indices))
+}
 
 fn game_setup(
 	mut cmd: Commands,
 	cfg: Res<GameConfig>,
 	srv: Res<AssetServer>,
-	mut covers_res: ResMut<CoveredCells>,
+	mut rng: ResMut<BoardRng>,
+	mut board_to_load: ResMut<BoardToLoad>,
+	mut playback: ResMut<ReplayPlayback>,
 	mut next_state: ResMut<NextState<AppState>>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+	mut scores: ResMut<HighScores>,
+	mut ranked: ResMut<RankedGame>,
+	mut retry: ResMut<RetryCount>,
+	mut loaded: ResMut<LoadedBoard>,
+) {
+	next_state.set(AppState::InGame);
+	// A grid still being generated for the previous game is dropped, which cancels its generation
+	cmd.remove_resource::<PendingSession>();
+	// A board loaded from a file replaces the generation of a new grid, a replay starts on a fresh session of its grid
+	*playback = ReplayPlayback::default();
+	// A new grid is never a retry, the retries being loaded like boards
+	ranked.0 = match board_to_load.0 {
+		Some(_) => None,
+		None => {
+			retry.0 = 0;
+			loaded.0 = false;
+			ranked_bucket(&cfg, &mut scores)
+		},
+	};
+	let session = match board_to_load.0.take() {
+		Some(BoardFile::Board(session)) => session.with_lives(cfg.difficulty.lives),
+		Some(BoardFile::Replay(replay)) => {
+			let session = GameSession::new(replay.grid.clone()).with_lives(replay.lives);
+			*playback = ReplayPlayback::play(replay);
+			session
+		},
+		None => match request_session(&mut cmd, &cfg, &mut rng.0) {
+			Some(session) => session,
+			// The board is spawned by `finish_game_setup` once its grid is generated
+			None => return,
+		},
+	};
+	start_game(&mut cmd, session, &cfg, &srv, &mut meshes, &mut materials);
+}
+
+/// Spawns the board of the session generated in the background, once it is ready
+fn finish_game_setup(
+	mut cmd: Commands,
+	cfg: Res<GameConfig>,
+	srv: Res<AssetServer>,
+	mut pending: ResMut<PendingSession>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+	let Some(session) = pending.poll() else { return };
+	cmd.remove_resource::<PendingSession>();
+	start_game(&mut cmd, session, &cfg, &srv, &mut meshes, &mut materials);
+}
 
+/// Spawns the board of a new game and starts playing the session on it
+fn start_game(
+	cmd: &mut Commands,
+	session: GameSession,
+	cfg: &GameConfig,
+	srv: &AssetServer,
+	meshes: &mut Assets<Mesh>,
+	materials: &mut Assets<ColorMaterial>,
 ) {
+	// In the Flags duel, the mines are claimed instead of exploding
+	let session = match cfg.duel {
+		Some(opponent) => {
+			cmd.insert_resource(FlagsDuel::new(opponent));
+			session.with_claimed_mines()
+		},
+		None => {
+			cmd.remove_resource::<FlagsDuel>();
+			session
+		},
+	};
+
+	debug!("{}", session.grid().console_output());
+	// The board is rated from the cell already revealed, if it started with one, only when the debug logs are shown
+	debug!("Board rating: {}", BoardRating::new(
+		session.grid(),
+		session.grid().cells().into_iter().find(|coord| session.state(*coord) == Some(CellState::Revealed)),
+	));
+
+	let (board, cell_covers) = spawn_board(cmd, &session, cfg, srv, meshes, materials, Vec2::splat(cfg.ui_style.margin.into()));
+	cmd.entity(board).insert(BoardBundle::new(session, cell_covers, cfg));
+}
+
+/// Spawns a board showing a session, with its bottom left corner at the given position
+///
+/// Returns the entity of the board, and the covers of the cells that are still covered
+pub(crate) fn spawn_board(
+	cmd: &mut Commands,
+	session: &GameSession,
+	cfg: &GameConfig,
+	srv: &AssetServer,
+	meshes: &mut Assets<Mesh>,
+	materials: &mut Assets<ColorMaterial>,
+	position: Vec2,
+) -> (Entity, HashMap<Coordinates, Entity>) {
 	let font = srv.load("fonts/FiraSans-Black.ttf");
 	let bomb_sprite = srv.load("sprites/mine.png");
+	let flag_sprite = srv.load("sprites/flag.png");
 
 	let mut cell_covers: HashMap<Coordinates, Entity> = HashMap::new();
 
-	let mut grid = Grid::empty(cfg.difficulty.grid_size.x, cfg.difficulty.grid_size.y);
-	grid.set_bombs(cfg.difficulty.bomb_count);
+	let topology = session.grid().shape();
+	let shape = CellShape::new(topology, cfg, meshes, materials);
 
-	debug!("{}", grid.console_output());
+	let grid_size = cfg.board_size() + Vec2::splat(cfg.ui_style.cell_padding.into());
 
-	let grid_size = <Coordinates as Into<Vec2>>::into(cfg.difficulty.grid_size)
-		* Vec2::splat(cfg.ui_style.cell_size.into())
-		+ Vec2::splat(cfg.ui_style.cell_padding.into());
+	let grid_position: Vec3 = position.extend(0.);
 
-	let grid_position: Vec3 = Vec2::splat(cfg.ui_style.margin.into()).extend(0.);
+	// The holes of a mask blend in the window background instead of showing the border color
+	let background = if session.grid().mask().is_some() { cfg.theme.background } else { cfg.theme.border };
 
 	// Spawning the grid background as a parent container
-	cmd.spawn(SpriteBundle {
+	let board = cmd.spawn(SpriteBundle {
 			transform: Transform::from_translation(grid_position),
 			sprite: Sprite {
-				color: cfg.theme.border,
+				color: background,
 				custom_size: Some(grid_size),
 				anchor: Anchor::BottomLeft,
 				..default()
@@ -159,102 +517,108 @@ fn game_setup(
 		}).insert(GridContainer).insert(Name::new("Grid Background"))
 		// Spawning every cells
 		.with_children(|parent| {
-			for (row, line) in grid.iter().enumerate() {
-				for (col, cell) in line.iter().enumerate() {
-					let mut cmd = parent.spawn_empty();
-					// Spawns the cell background
-					cmd.insert(SpriteBundle {
-						sprite: Sprite {
-							color: cfg.theme.cell,
-							custom_size: Some(Vec2::splat(
-								(cfg.ui_style.cell_size - cfg.ui_style.cell_padding) as f32,
-							)),
-							anchor: Anchor::BottomLeft,
-							..default()
-						},
-						transform: Transform::from_xyz(
-							((col * cfg.ui_style.cell_size as usize) + cfg.ui_style.cell_padding as usize) as f32,
-							((row * cfg.ui_style.cell_size as usize) + cfg.ui_style.cell_padding as usize) as f32,
-							1.,
-						),
-						..Default::default()
-					}).insert(Name::new(format!("Cell {col} - {row}")))
-					  .insert(Coordinates { x: col as u16, y: row as u16 });
-
-					// Covering the cell
+			for coord in session.grid().cells() {
+				let Some(cell) = session.grid().get(coord) else { continue };
+				let (col, row) = (coord.x, coord.y);
+				let origin = topology.cell_origin(coord, cfg.ui_style.cell_size as f32)
+					+ Vec2::splat(cfg.ui_style.cell_padding as f32);
+				let mut cmd = parent.spawn_empty();
+				// Spawns the cell background
+				shape.insert(&mut cmd, false, Transform::from_translation(origin.extend(1.)));
+				cmd.insert(Name::new(format!("Cell {col} - {row}")))
+				   .insert(coord);
+
+				// Covering the cell, unless it was already revealed by the no-guess generation
+				if session.state(coord) == Some(CellState::Covered) {
 					cmd.with_children(|parent| {
-						let entity = parent.spawn(SpriteBundle {
-							sprite: Sprite {
-								color: cfg.theme.hidden_cell,
-								custom_size: Some(Vec2::splat(
-									(cfg.ui_style.cell_size - cfg.ui_style.cell_padding) as f32,
-								)),
-								anchor: Anchor::BottomLeft,
-								..default()
-							},
-							transform: Transform::from_xyz( 0.,0.,2.),
-							..Default::default()
-						}).insert(Name::new("Cell Cover")).id();
-						cell_covers.insert(Coordinates {x: col as u16, y: row as u16}, entity);
+						let mut cover = parent.spawn_empty();
+						shape.insert(&mut cover, true, Transform::from_xyz(0., 0., 2.));
+						let entity = cover.insert(Name::new("Cell Cover")).id();
+						cell_covers.insert(coord, entity);
 					});
-
-					// Adding the cell's specific information depending on it's type
-					match cell {
-						Cell::Empty => {
-							cmd.insert(EmptyCell);
-						},
-						Cell::Bomb => {
-							cmd.insert(Bomb);
-							cmd.with_children(|parent| {
-								parent.spawn(SpriteBundle {
-									sprite: Sprite {
-										custom_size: Some(Vec2::splat((cfg.ui_style.cell_size - cfg.ui_style.cell_padding - 2) as f32)),
-										..default()
-									},
-									transform: Transform::from_xyz(
-										cfg.ui_style.cell_size as f32 / 2.,
-										cfg.ui_style.cell_size as f32 / 2.,
-										1.
-									),
-									texture: bomb_sprite.clone(),
-									..default()
-								}).insert(Name::new("Bomb Sprite"));
+				}
+				// Flagging the cell, if the board was loaded with flags
+				if session.state(coord) == Some(CellState::Flagged) {
+					let mut flag = Flag { id: Entity::PLACEHOLDER };
+					cmd.with_children(|parent| {
+						let mut sprite = parent.spawn(flag_bundle(&cfg, flag_sprite.clone()));
+						sprite.insert(Name::new("Flag"));
+						let flags = session.flags_at(coord);
+						if flags > 1 {
+							sprite.with_children(|parent| {
+								parent.spawn(stack_count_bundle(&cfg, font.clone(), flags, cfg.theme.flags))
+									.insert(Name::new("Flag count"));
 							});
-						},
-						Cell::BombNeighbor(count) => {
-							cmd.insert(BombNeighbors { count: *count });
-							cmd.with_children(|parent| {
-								parent.spawn(Text2dBundle {
-									text: Text::from_section((*count).to_string(), TextStyle {
-										color: cfg.theme.neighbors[(*count - 1) as usize],
-										font: font.clone(),
-										font_size: cfg.ui_style.font_size as f32,
-									}),
-									transform: Transform::from_xyz(
-										cfg.ui_style.cell_size as f32 / 2.,
-										cfg.ui_style.cell_size as f32 / 2.,
-										1.
-									),
+						}
+						flag.id = sprite.id();
+					});
+					cmd.insert(flag);
+				}
+
+				// Adding the cell's specific information depending on it's type
+				match &cell {
+					Cell::Empty => {
+						cmd.insert(EmptyCell);
+					},
+					Cell::Bomb(mines) => {
+						cmd.insert(Bomb);
+						cmd.with_children(|parent| {
+							let mut sprite = parent.spawn(SpriteBundle {
+								sprite: Sprite {
+									custom_size: Some(Vec2::splat((cfg.ui_style.cell_size - cfg.ui_style.cell_padding - 2) as f32)),
 									..default()
-								}).insert(Name::new("Neighbor count"));
+								},
+								transform: Transform::from_xyz(
+									cfg.ui_style.cell_size as f32 / 2.,
+									cfg.ui_style.cell_size as f32 / 2.,
+									1.
+								),
+								texture: bomb_sprite.clone(),
+								..default()
 							});
-						}
+							sprite.insert(Name::new("Bomb Sprite"));
+							// The number of mines, when the cell holds several of them
+							if *mines > 1 {
+								sprite.with_children(|parent| {
+									parent.spawn(stack_count_bundle(&cfg, font.clone(), *mines, cfg.theme.menus_msg))
+										.insert(Name::new("Mine count"));
+								});
+							}
+						});
+					},
+					Cell::BombNeighbor(count) => {
+						cmd.insert(BombNeighbors { count: *count });
+						cmd.with_children(|parent| {
+							parent.spawn(Text2dBundle {
+								text: Text::from_section((*count).to_string(), TextStyle {
+									color: cfg.theme.neighbor_color(*count),
+									font: font.clone(),
+									font_size: cfg.ui_style.font_size as f32,
+								}),
+								transform: Transform::from_xyz(
+									cfg.ui_style.cell_size as f32 / 2.,
+									cfg.ui_style.cell_size as f32 / 2.,
+									1.
+								),
+								..default()
+							}).insert(Name::new("Neighbor count"));
+						});
 					}
-
 				}
+
 			}
-		});
+			// The copies of the cells drawn outside of the board, if the topology has any
+			spawn_ghost_cells(parent, session, cfg, font.clone());
+		}).id();
 
-	*covers_res = CoveredCells(cell_covers);
-	next_state.set(AppState::InGame);
+	(board, cell_covers)
 }
 
 #[derive(Component)]
 pub struct GameCamera;
 
 fn spawn_camera(mut cmd: Commands, cfg: Res<GameConfig>) {
-	let window_size = <Coordinates as Into<Vec2>>::into(cfg.difficulty.grid_size) * Vec2::splat(cfg.ui_style.cell_size.into())
-		+ Vec2::splat(cfg.ui_style.margin as f32 * 2.) + Vec2::new(0., cfg.ui_style.header_size.into());
+	let window_size = cfg.window_size();
 	cmd.spawn((GameCamera, Camera2dBundle {
 		transform: Transform::from_translation((window_size/2.).extend(0.)),
 		camera: Camera {
@@ -264,17 +628,16 @@ fn spawn_camera(mut cmd: Commands, cfg: Res<GameConfig>) {
 		..default()
 	}));
 }
-fn update_camera(mut q_camera: Query<(&Camera, &mut Transform), With<GameCamera>>, mut cmd: Commands, cfg: Res<GameConfig>) {
-	let window_size = <Coordinates as Into<Vec2>>::into(cfg.difficulty.grid_size) * Vec2::splat(cfg.ui_style.cell_size.into())
-		+ Vec2::splat(cfg.ui_style.margin as f32 * 2.) + Vec2::new(0., cfg.ui_style.header_size.into());
-	if let Ok((_, mut transform)) = q_camera.get_single_mut() {
+pub(crate) fn update_camera(mut q_camera: Query<(Entity, &mut Transform), With<GameCamera>>, mut cmd: Commands, cfg: Res<GameConfig>) {
+	let window_size = cfg.window_size();
+	if let Ok((entity, mut transform)) = q_camera.get_single_mut() {
 		*transform = Transform::from_translation((window_size/2.).extend(0.));
+		cmd.entity(entity).remove::<ScreenShake>();
 	}
 }
 fn update_window(mut window: Query<&mut Window>, cfg: Res<GameConfig>) {
 	// Resize the window to fit the grid
-	let window_size = <Coordinates as Into<Vec2>>::into(cfg.difficulty.grid_size) * Vec2::splat(cfg.ui_style.cell_size.into())
-		+ Vec2::splat(cfg.ui_style.margin as f32 * 2.) + Vec2::new(0., cfg.ui_style.header_size.into());
+	let window_size = cfg.window_size();
 
 	window.single_mut().resolution = WindowResolution::from(window_size);
 
@@ -0,0 +1,202 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::AppState;
+use crate::components::Coordinates;
+use crate::config::{DifficultySettings, GameConfig, RaceSettings};
+use crate::events::RestartEvent;
+use crate::resources::{
+	BoardFile, BoardRng, BoardToLoad, GameSession, Replay, ReplayAction,
+	RaceConnection, RaceEvent, RaceMessage, RaceProgress, RaceResult, RaceStart,
+};
+
+
+/// A race against another player on the local network
+///
+/// Only inserted if the connection could be opened, the game is played alone otherwise
+#[derive(Resource)]
+pub struct Race {
+	pub connection: RaceConnection,
+	pub settings: RaceSettings,
+	/// The board of the race, None while waiting for the other player
+	pub start: Option<RaceStart>,
+	/// The host's copy of the client's game, replaying its actions to decide the result of the race
+	referee: Option<GameSession>,
+	/// Number of actions of the current replay already sent to the host
+	sent_actions: usize,
+	pub local: RaceProgress,
+	pub opponent: RaceProgress,
+	pub result: Option<RaceResult>,
+}
+
+impl Race {
+	/// Opens the connection, the host waits for the client and the client connects to the host in the background
+	pub fn connect(settings: &RaceSettings) -> Result<Self, String> {
+		let connection = match settings {
+			RaceSettings::Host { port } => RaceConnection::host(*port)?,
+			RaceSettings::Join { address } => RaceConnection::join(address),
+		};
+		Ok(Self {
+			connection,
+			settings: settings.clone(),
+			start: None,
+			referee: None,
+			sent_actions: 0,
+			local: RaceProgress::default(),
+			opponent: RaceProgress::default(),
+			result: None,
+		})
+	}
+
+	pub fn is_host(&self) -> bool {
+		matches!(self.settings, RaceSettings::Host { .. })
+	}
+
+	/// Returns true from the start of the race until its result is known
+	pub fn is_running(&self) -> bool {
+		self.start.is_some() && self.result.is_none()
+	}
+
+	/// Starts the race on the board of the seed, the difficulty following the board so both windows have the same size
+	fn begin(&mut self, start: RaceStart, cfg: &mut GameConfig, board_to_load: &mut BoardToLoad) {
+		info!("The race starts, good luck!");
+		cfg.difficulty = DifficultySettings {
+			grid_size: Coordinates::new(start.width, start.height),
+			bomb_count: start.mines,
+			lives: start.lives,
+			topology: start.topology,
+			kernel: start.kernel.clone(),
+			mines_per_cell: start.mines_per_cell,
+			mask: start.mask.clone(),
+			..DifficultySettings::default()
+		};
+		cfg.difficulty_given = true;
+		cfg.generation.daily = None;
+		board_to_load.0 = Some(BoardFile::Board(start.session()));
+		self.referee = self.is_host().then(|| start.session());
+		self.start = Some(start);
+		self.sent_actions = 0;
+		self.local = RaceProgress::default();
+		self.opponent = RaceProgress::default();
+		self.result = None;
+	}
+
+	/// Decides the result of the race on the host, and tells it to the client
+	fn decide_result(&mut self) {
+		if !self.is_host() || !self.is_running() { return }
+		if let Some(result) = RaceResult::decide(&self.local, &self.opponent) {
+			self.end(result);
+			self.connection.send(RaceMessage::Result(result.opposite()));
+		}
+	}
+
+	fn end(&mut self, result: RaceResult) {
+		match result {
+			RaceResult::Won => info!("You won the race!"),
+			RaceResult::Lost => info!("Your opponent was faster, you lost the race"),
+			RaceResult::Draw => info!("You both blew up, it's a draw"),
+			RaceResult::OpponentLeft => warn!("The connection was lost, the race is over"),
+		}
+		self.result = Some(result);
+	}
+}
+
+
+/// Handles the messages of the other player
+///
+/// The host sends the board as soon as the client connects, then replays the actions of the client.
+/// The client starts the race when it receives the board, then only shows what the host tells it.
+pub fn race_network_system(
+	mut race: ResMut<Race>,
+	mut cfg: ResMut<GameConfig>,
+	mut rng: ResMut<BoardRng>,
+	mut board_to_load: ResMut<BoardToLoad>,
+	mut ev_restart: EventWriter<RestartEvent>,
+	mut next_state: ResMut<NextState<AppState>>,
+) {
+	let race = &mut *race;
+	for event in race.connection.poll() {
+		match event {
+			RaceEvent::Connected if race.is_host() => {
+				let start = RaceStart {
+					seed: rng.0.gen(),
+					width: cfg.difficulty.grid_size.x,
+					height: cfg.difficulty.grid_size.y,
+					mines: cfg.difficulty.bomb_count,
+					lives: cfg.difficulty.lives,
+					topology: cfg.difficulty.topology,
+					kernel: cfg.difficulty.kernel.clone(),
+					mines_per_cell: cfg.difficulty.mines_per_cell,
+					mask: cfg.difficulty.mask.clone(),
+				};
+				race.connection.send(RaceMessage::Start(start.clone()));
+				race.begin(start, &mut cfg, &mut board_to_load);
+				ev_restart.send(RestartEvent);
+				next_state.set(AppState::InGame);
+			},
+			RaceEvent::Connected => info!("Connected to the host, waiting for the board"),
+			RaceEvent::Message(RaceMessage::Start(start)) if !race.is_host() => {
+				race.begin(start, &mut cfg, &mut board_to_load);
+				ev_restart.send(RestartEvent);
+				next_state.set(AppState::InGame);
+			},
+			RaceEvent::Message(RaceMessage::Action(action, coord)) if race.is_host() => {
+				let Some(referee) = &mut race.referee else { continue };
+				match action {
+					ReplayAction::Reveal => { referee.reveal(coord); },
+					ReplayAction::Flag => { referee.toggle_flag(coord); },
+				}
+				race.opponent = RaceProgress::of(referee);
+				race.decide_result();
+			},
+			RaceEvent::Message(RaceMessage::Progress(progress)) if !race.is_host() => {
+				race.opponent = progress;
+			},
+			RaceEvent::Message(RaceMessage::Result(result)) if !race.is_host() => {
+				if race.is_running() { race.end(result) }
+			},
+			RaceEvent::Message(message) => warn!("Ignoring an unexpected race message: {}", message),
+			RaceEvent::Disconnected => {
+				if race.is_running() || race.start.is_none() { race.end(RaceResult::OpponentLeft) }
+			},
+		}
+	}
+}
+
+/// Updates the progress of the player, sent to the client by the host
+pub fn race_progress_system(
+	q_boards: Query<&GameSession, Changed<GameSession>>,
+	mut race: ResMut<Race>,
+) {
+	if !race.is_running() { return }
+	let Ok(session) = q_boards.get_single() else { return };
+	race.local = RaceProgress::of(session);
+	if race.is_host() {
+		let progress = race.local;
+		race.connection.send(RaceMessage::Progress(progress));
+		race.decide_result();
+	}
+}
+
+/// Sends the actions of the client to the host, as recorded in the replay so only the ones changing the game are sent
+pub fn race_send_actions(
+	q_boards: Query<&Replay, Changed<Replay>>,
+	mut race: ResMut<Race>,
+) {
+	if race.is_host() || !race.is_running() { return }
+	let Ok(replay) = q_boards.get_single() else { return };
+	for ev in replay.events.iter().skip(race.sent_actions) {
+		race.connection.send(RaceMessage::Action(ev.action, ev.coordinates));
+	}
+	race.sent_actions = replay.events.len();
+}
+
+/// Run condition disabling the board while waiting for the other player
+pub fn race_allows_input(race: Option<Res<Race>>) -> bool {
+	race.map_or(true, |race| race.start.is_some() || race.result.is_some())
+}
+
+/// Run condition disabling the restarts and the settings until the end of the race
+pub fn is_racing(race: Option<Res<Race>>) -> bool {
+	race.is_some_and(|race| race.is_running())
+}
@@ -0,0 +1,260 @@
+// This module contains the text format used to import and export boards
+//
+// A board is written as one line of text per row of the grid, starting with the top row as it is shown on screen.
+// Every character describes a single cell:
+//
+//   .  a covered safe cell         *  a covered bomb
+//   o  a revealed safe cell        X  a revealed bomb (the player blew up)
+//   f  a flag on a safe cell       F  a flag on a bomb
+//   -  a hole in the board, for boards shaped by a mask
+//
+// Every row must have the same length, empty lines and lines starting with `#` are ignored.
+// Boards that are not square grids start with a line giving their topology, like `topology: hex` or `topology: torus`,
+// and boards counting other neighbors than the 8 around a cell give their kernel, like `kernel: knight`.
+// Boards allowing several mines per cell give the maximum with `mines-per-cell: 3`, then list the cells holding more
+// than one mine and the cells with more than one flag, as `x,y=count` with the coordinates of the grid:
+//
+//   mines-per-cell: 3
+//   mines: 2,0=3 4,1=2
+//   flags: 4,1=2
+//
+// A plain grid only uses `.` and `*`, which is all `Grid` needs, the other characters are used to save a `GameSession`.
+// For example, a 5x3 grid with two bombs, one of them flagged and the bottom left corner opened:
+//
+//   # Exported board
+//   ..*..
+//   oo..F
+//   oo...
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::components::Coordinates;
+use super::{CellState, GameSession, Grid, Kernel, Mask, TopologyKind};
+
+
+/// Returns the character used to describe a cell, a cell without state being a hole
+fn cell_char(is_bomb: bool, state: Option<CellState>) -> char {
+	match (is_bomb, state) {
+		(_, None) => '-',
+		(false, Some(CellState::Covered)) => '.',
+		(true, Some(CellState::Covered)) => '*',
+		(false, Some(CellState::Revealed)) => 'o',
+		(true, Some(CellState::Revealed)) => 'X',
+		(false, Some(CellState::Flagged)) => 'f',
+		(true, Some(CellState::Flagged)) => 'F',
+	}
+}
+
+/// Parses a list of `x,y=count` entries
+fn parse_counts(text: &str) -> Result<Vec<(Coordinates, u8)>, String> {
+	text.split_whitespace()
+		.map(|entry| {
+			let invalid = || format!("Invalid count '{}', expected x,y=count", entry);
+			let (coord, count) = entry.split_once('=').ok_or_else(invalid)?;
+			let (x, y) = coord.split_once(',').ok_or_else(invalid)?;
+			Ok((
+				Coordinates::new(x.parse().map_err(|_| invalid())?, y.parse().map_err(|_| invalid())?),
+				count.parse().map_err(|_| invalid())?,
+			))
+		})
+		.collect()
+}
+
+/// Parses a board, returning the grid, the state of every cell indexed like the grid, and the cells with several flags
+fn parse_board(text: &str) -> Result<(Grid, Vec<Vec<CellState>>, Vec<(Coordinates, u8)>), String> {
+	let mut rows: Vec<(usize, &str)> = text.lines()
+		.enumerate()
+		.map(|(number, line)| (number + 1, line.trim()))
+		.filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+		.collect();
+
+	let mut topology = TopologyKind::Square;
+	let mut kernel = Kernel::Moore;
+	let mut mines_per_cell = 1;
+	let mut mine_counts = Vec::new();
+	let mut flag_counts = Vec::new();
+	while let Some(&(number, line)) = rows.first() {
+		let parsed = if let Some(name) = line.strip_prefix("topology:") {
+			name.trim().parse().map(|parsed| topology = parsed)
+		} else if let Some(name) = line.strip_prefix("kernel:") {
+			name.trim().parse().map(|parsed| kernel = parsed)
+		} else if let Some(value) = line.strip_prefix("mines-per-cell:") {
+			value.trim().parse().map(|parsed| mines_per_cell = parsed).map_err(|_| format!("Invalid mines per cell '{}'", value.trim()))
+		} else if let Some(counts) = line.strip_prefix("mines:") {
+			parse_counts(counts).map(|parsed| mine_counts = parsed)
+		} else if let Some(counts) = line.strip_prefix("flags:") {
+			parse_counts(counts).map(|parsed| flag_counts = parsed)
+		} else {
+			break
+		};
+		parsed.map_err(|err| format!("Line {}: {}", number, err))?;
+		rows.remove(0);
+	}
+	topology.check_kernel(&kernel)?;
+
+	let width = rows.first().ok_or("The board is empty")?.1.chars().count();
+	let height = rows.len();
+	if width > u16::MAX as usize || height > u16::MAX as usize {
+		return Err(format!("The board is too big ({}x{})", width, height));
+	}
+
+	let mut bombs = Vec::new();
+	let mut enabled: Vec<Vec<bool>> = Vec::with_capacity(height);
+	let mut states = vec![Vec::with_capacity(width); height];
+	for (row, (number, line)) in rows.iter().enumerate() {
+		if line.chars().count() != width {
+			return Err(format!("Line {}: expected {} cells, found {}", number, width, line.chars().count()));
+		}
+		// The first row of the text is the top of the grid
+		let y = height - 1 - row;
+		enabled.push(line.chars().map(|c| c != '-').collect());
+		for (x, c) in line.chars().enumerate() {
+			let (is_bomb, state) = match c {
+				'-' => (false, CellState::Covered),
+				'.' => (false, CellState::Covered),
+				'*' => (true, CellState::Covered),
+				'o' => (false, CellState::Revealed),
+				'X' => (true, CellState::Revealed),
+				'f' => (false, CellState::Flagged),
+				'F' => (true, CellState::Flagged),
+				other => return Err(format!("Line {}: unknown cell '{}'", number, other)),
+			};
+			if is_bomb {
+				let coord = Coordinates::new(x as u16, y as u16);
+				let mines = mine_counts.iter().find(|(stacked, _)| *stacked == coord).map_or(1, |&(_, mines)| mines);
+				bombs.extend(std::iter::repeat(coord).take(mines as usize));
+			}
+			states[y].push(state);
+		}
+	}
+	let mut grid = Grid::from_bombs(width as u16, height as u16, &bombs)
+		.with_topology(topology)
+		.with_kernel(kernel);
+	// The maximum can be higher than what the cells hold
+	let mines_per_cell = mines_per_cell.max(grid.mines_per_cell());
+	grid = grid.with_mines_per_cell(mines_per_cell);
+	if enabled.iter().flatten().any(|&cell| !cell) {
+		grid = grid.with_mask(Mask::from_rows(enabled)?);
+	}
+	Ok((grid, states, flag_counts))
+}
+
+
+/// Writes a list of `x,y=count` entries, for the counts above 1
+fn write_counts(f: &mut fmt::Formatter<'_>, key: &str, counts: impl Iterator<Item = (Coordinates, u8)>) -> fmt::Result {
+	let entries: Vec<String> = counts
+		.filter(|&(_, count)| count > 1)
+		.map(|(coord, count)| format!("{},{}={}", coord.x, coord.y, count))
+		.collect();
+	if entries.is_empty() { return Ok(()) }
+	writeln!(f, "{}: {}", key, entries.join(" "))
+}
+
+/// Writes the header lines, for the boards that are not classic square grids
+fn write_header(f: &mut fmt::Formatter<'_>, grid: &Grid) -> fmt::Result {
+	if grid.topology() != TopologyKind::Square {
+		writeln!(f, "topology: {}", grid.topology())?;
+	}
+	if *grid.kernel() != Kernel::Moore {
+		writeln!(f, "kernel: {}", grid.kernel())?;
+	}
+	if grid.mines_per_cell() > 1 {
+		writeln!(f, "mines-per-cell: {}", grid.mines_per_cell())?;
+		write_counts(f, "mines", grid.cells().into_iter().map(|coord| (coord, grid.mines_at(coord))))?;
+	}
+	Ok(())
+}
+
+/// Writes the bombs of the grid, every cell being covered
+impl fmt::Display for Grid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write_header(f, self)?;
+		for y in (0..self.height()).rev() {
+			let row: String = (0..self.width())
+				.map(|x| Coordinates::new(x, y))
+				.map(|coord| cell_char(self.is_bomb_at(coord), self.contains(coord).then_some(CellState::Covered)))
+				.collect();
+			writeln!(f, "{}", row)?;
+		}
+		Ok(())
+	}
+}
+
+/// Reads the bombs of a board, the state of the cells is ignored
+impl FromStr for Grid {
+	type Err = String;
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		parse_board(text).map(|(grid, _, _)| grid)
+	}
+}
+
+/// Writes the bombs of the grid and the state of every cell
+impl fmt::Display for GameSession {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write_header(f, self.grid())?;
+		write_counts(f, "flags", self.grid().cells().into_iter().map(|coord| (coord, self.flags_at(coord))))?;
+		for y in (0..self.grid().height()).rev() {
+			let row: String = (0..self.grid().width())
+				.map(|x| Coordinates::new(x, y))
+				.map(|coord| cell_char(self.grid().is_bomb_at(coord), self.state(coord)))
+				.collect();
+			writeln!(f, "{}", row)?;
+		}
+		Ok(())
+	}
+}
+
+/// Reads a board and resumes the game where it was saved
+impl FromStr for GameSession {
+	type Err = String;
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let (grid, states, flags) = parse_board(text)?;
+		Ok(GameSession::with_states(grid, states)?.with_flag_counts(&flags))
+	}
+}
+
+impl GameSession {
+	/// Loads a board file, see the `board_format` module for the format
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		fs::read_to_string(path)
+			.map_err(|err| format!("Cannot read board file {}: {}", path.display(), err))?
+			.parse()
+	}
+
+	/// Saves the board and the state of the game in a file, creating its directory if needed
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+		let path = path.as_ref();
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir).map_err(|err| format!("Cannot create directory {}: {}", dir.display(), err))?;
+		}
+		let text = format!(
+			"# Minesweeper board, {}x{} with {} bombs\n{}",
+			self.grid().width(), self.grid().height(), self.grid().bomb_count(), self
+		);
+		fs::write(path, text).map_err(|err| format!("Cannot write board file {}: {}", path.display(), err))
+	}
+}
+
+
+/// Directory where the boards are exported, and imported from the settings menu
+pub const BOARDS_DIR: &str = "boards";
+
+/// Returns a new file name in the boards directory, based on the current time
+pub fn new_board_path(extension: &str) -> PathBuf {
+	let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+	Path::new(BOARDS_DIR).join(format!("board-{}.{}", secs, extension))
+}
+
+/// Returns the most recently modified board file of the boards directory
+pub fn latest_board_path() -> Option<PathBuf> {
+	fs::read_dir(BOARDS_DIR).ok()?
+		.filter_map(|entry| entry.ok())
+		.filter(|entry| entry.path().extension().is_some_and(|ext| ext == "txt"))
+		.max_by_key(|entry| entry.metadata().and_then(|meta| meta.modified()).ok())
+		.map(|entry| entry.path())
+}
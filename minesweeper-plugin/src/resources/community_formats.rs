@@ -0,0 +1,218 @@
+// This module contains the board and replay formats used by the community tools, like Minesweeper Arbiter or ViennaSweeper
+//
+// MBF is Arbiter's binary board format, every value is an unsigned byte except the mine count:
+//
+//   width, height, mine count (2 bytes, big endian), then the column and row of every mine
+//
+// RawVF is the text layout the AVF and RMV replays are converted to by the community tools (avf2rawvf, rmv2rawvf).
+// It starts with `Key: Value` lines, followed by a `Board:` section drawn with `*` for mines and `0` for safe cells,
+// and an `Events:` section with one mouse event per line:
+//
+//   RawVF_Version: Rev2
+//   Width: 8
+//   Height: 8
+//   Mines: 10
+//   Board:
+//   0000*000
+//   ...
+//   Events:
+//   0.00 start
+//   1.52 lc 3 4 (40 56) (l)
+//   1.60 lr 3 4 (40 56)
+//
+// Columns and rows are counted from 1, starting from the top left corner.
+// We only read the releases of the left button (`lr`) as reveals and the presses of the right button (`rc`) as flags,
+// the other events (mouse moves, chords, ...) are ignored since our games have no equivalent.
+// In both formats the first row is the top of the board, while our grids start from the bottom.
+// Both formats only know about full square boards with the classic neighbors, the other boards cannot be exported.
+
+use std::fs;
+use std::path::Path;
+
+use crate::components::Coordinates;
+use super::{GameSession, Grid, Kernel, Replay, ReplayAction, TopologyKind};
+
+
+/// Size of a cell in the pixel positions written in RawVF events, the size used by Arbiter
+const RAWVF_CELL_SIZE: u32 = 16;
+
+
+impl Grid {
+	/// Reads a board in the MBF format
+	pub fn from_mbf(bytes: &[u8]) -> Result<Self, String> {
+		let [width, height, high, low, mines @ ..] = bytes else {
+			return Err("The MBF board is too short".to_string());
+		};
+		let (width, height) = (*width as u16, *height as u16);
+		let mine_count = u16::from_be_bytes([*high, *low]) as usize;
+		if mines.len() != mine_count * 2 {
+			return Err(format!("The MBF board should contain {} mines, found {} bytes of mines", mine_count, mines.len()));
+		}
+		let mut bombs = Vec::with_capacity(mine_count);
+		for mine in mines.chunks(2) {
+			let (x, row) = (mine[0] as u16, mine[1] as u16);
+			if x >= width || row >= height {
+				return Err(format!("The MBF board has a mine outside of the grid at ({}, {})", x, row));
+			}
+			bombs.push(Coordinates::new(x, height - 1 - row));
+		}
+		Ok(Grid::from_bombs(width, height, &bombs))
+	}
+
+	/// Writes the board in the MBF format, which only holds square grids up to 255x255
+	pub fn to_mbf(&self) -> Result<Vec<u8>, String> {
+		if self.topology() != TopologyKind::Square {
+			return Err(format!("The MBF format only holds square grids, not {} grids", self.topology()));
+		}
+		if *self.kernel() != Kernel::Moore {
+			return Err(format!("The MBF format only holds grids with the moore kernel, not {}", self.kernel()));
+		}
+		if self.mask().is_some() {
+			return Err("The MBF format cannot hold the holes of a mask".to_string());
+		}
+		if self.mines_per_cell() > 1 {
+			return Err("The MBF format only holds one mine per cell".to_string());
+		}
+		if self.width() > u8::MAX as u16 || self.height() > u8::MAX as u16 {
+			return Err(format!("The grid is too big for the MBF format ({}x{})", self.width(), self.height()));
+		}
+		let mut bytes = vec![self.width() as u8, self.height() as u8];
+		bytes.extend(self.bomb_count().to_be_bytes());
+		for row in 0..self.height() {
+			for x in 0..self.width() {
+				if self.is_bomb_at(Coordinates::new(x, self.height() - 1 - row)) {
+					bytes.extend([x as u8, row as u8]);
+				}
+			}
+		}
+		Ok(bytes)
+	}
+}
+
+
+impl Replay {
+	/// Reads a replay in the RawVF layout
+	pub fn from_rawvf(text: &str) -> Result<Self, String> {
+		let mut lines = text.lines().map(str::trim).enumerate();
+		let mut width = None;
+		let mut height = None;
+
+		// Header, until the board
+		for (_, line) in lines.by_ref() {
+			if line == "Board:" { break }
+			match line.split_once(':').map(|(key, value)| (key.trim(), value.trim())) {
+				Some(("Width", value)) => width = value.parse::<u16>().ok(),
+				Some(("Height", value)) => height = value.parse::<u16>().ok(),
+				_ => {},
+			}
+		}
+		let (Some(width), Some(height)) = (width, height) else {
+			return Err("The replay does not give the size of the board".to_string());
+		};
+
+		// Board, one line per row starting from the top
+		let mut bombs = Vec::new();
+		for row in 0..height {
+			let (number, line) = lines.next().ok_or("The board of the replay is incomplete")?;
+			if line.chars().count() != width as usize {
+				return Err(format!("Line {}: expected {} cells, found {}", number + 1, width, line.chars().count()));
+			}
+			for (x, c) in line.chars().enumerate() {
+				if c == '*' { bombs.push(Coordinates::new(x as u16, height - 1 - row)) }
+			}
+		}
+		let mut replay = Replay::new(Grid::from_bombs(width, height, &bombs));
+
+		// Events, the lines we don't understand are skipped
+		for (_, line) in lines.skip_while(|(_, line)| *line != "Events:").skip(1) {
+			let mut fields = line.split_whitespace();
+			let (Some(time), Some(kind), Some(col), Some(row)) = (fields.next(), fields.next(), fields.next(), fields.next()) else { continue };
+			let action = match kind {
+				"lr" => ReplayAction::Reveal,
+				"rc" => ReplayAction::Flag,
+				_ => continue,
+			};
+			let (Ok(time), Ok(col), Ok(row)) = (time.parse::<f32>(), col.parse::<u16>(), row.parse::<u16>()) else { continue };
+			if col == 0 || row == 0 || col > width || row > height { continue }
+			replay.record(time, action, Coordinates::new(col - 1, height - row));
+		}
+		Ok(replay)
+	}
+
+	/// Writes the replay in the RawVF layout, which only holds games on square grids
+	pub fn to_rawvf(&self) -> Result<String, String> {
+		if self.grid.topology() != TopologyKind::Square {
+			return Err(format!("The RawVF layout only holds games on square grids, not {} grids", self.grid.topology()));
+		}
+		if *self.grid.kernel() != Kernel::Moore {
+			return Err(format!("The RawVF layout only holds games with the moore kernel, not {}", self.grid.kernel()));
+		}
+		if self.grid.mask().is_some() {
+			return Err("The RawVF layout cannot hold the holes of a mask".to_string());
+		}
+		if self.grid.mines_per_cell() > 1 {
+			return Err("The RawVF layout only holds games with one mine per cell".to_string());
+		}
+		let (width, height) = (self.grid.width(), self.grid.height());
+		let mut text = format!(
+			"RawVF_Version: Rev2\nProgram: bevy-minesweeper\nWidth: {}\nHeight: {}\nMines: {}\nBoard:\n",
+			width, height, self.grid.bomb_count()
+		);
+		for y in (0..height).rev() {
+			text.extend((0..width).map(|x| if self.grid.is_bomb_at(Coordinates::new(x, y)) { '*' } else { '0' }));
+			text.push('\n');
+		}
+
+		text.push_str("Events:\n0.00 start\n");
+		for ev in &self.events {
+			let (col, row) = (ev.coordinates.x as u32 + 1, (height - ev.coordinates.y) as u32);
+			// The position of the mouse, in the middle of the cell
+			let (px, py) = (col * RAWVF_CELL_SIZE - RAWVF_CELL_SIZE / 2, row * RAWVF_CELL_SIZE - RAWVF_CELL_SIZE / 2);
+			let (press, release, button) = match ev.action {
+				ReplayAction::Reveal => ("lc", "lr", "l"),
+				ReplayAction::Flag => ("rc", "rr", "r"),
+			};
+			text.push_str(&format!("{:.2} {} {} {} ({} {}) ({})\n", ev.time, press, col, row, px, py, button));
+			text.push_str(&format!("{:.2} {} {} {} ({} {})\n", ev.time, release, col, row, px, py));
+		}
+		Ok(text)
+	}
+}
+
+
+/// The content of a file that can be loaded in the game
+pub enum BoardFile {
+	/// A board, possibly with a game in progress
+	Board(GameSession),
+	/// The replay of a game, to be played back
+	Replay(Replay),
+}
+
+impl BoardFile {
+	/// Loads a file, choosing its format from the extension
+	///
+	/// `.mbf` files are MBF boards, `.rawvf` files are RawVF replays, every other file uses our own board format
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		let read_error = |err| format!("Cannot read board file {}: {}", path.display(), err);
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("mbf") => {
+				let bytes = fs::read(path).map_err(read_error)?;
+				Ok(BoardFile::Board(GameSession::new(Grid::from_mbf(&bytes)?)))
+			},
+			Some("rawvf") => {
+				let text = fs::read_to_string(path).map_err(read_error)?;
+				Ok(BoardFile::Replay(Replay::from_rawvf(&text)?))
+			},
+			_ => GameSession::load(path).map(BoardFile::Board),
+		}
+	}
+
+	/// Returns the grid the file is played on
+	pub fn grid(&self) -> &Grid {
+		match self {
+			BoardFile::Board(session) => session.grid(),
+			BoardFile::Replay(replay) => &replay.grid,
+		}
+	}
+}
@@ -0,0 +1,67 @@
+// This module contains the daily challenge: a single board per calendar day and per difficulty
+// The board only depends on the local date, so every player gets the same one without needing a server
+
+use core::fmt;
+use bevy::prelude::Reflect;
+use chrono::{Datelike, Local, NaiveDate};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::config::{DifficultySettings, StandardDifficulty};
+use super::Grid;
+
+
+/// The board of a given day, for one of the standard difficulties
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+pub struct DailyChallenge {
+	pub year: i32,
+	pub month: u32,
+	pub day: u32,
+	pub level: StandardDifficulty,
+}
+
+impl DailyChallenge {
+	/// Returns the challenge of the given date
+	pub fn new(date: NaiveDate, level: StandardDifficulty) -> Self {
+		Self { year: date.year(), month: date.month(), day: date.day(), level }
+	}
+
+	/// Returns the challenge of the current day, according to the local time
+	pub fn today(level: StandardDifficulty) -> Self {
+		Self::new(Local::now().date_naive(), level)
+	}
+
+	/// Returns the seed generating the board, hashing the date and the difficulty with FNV-1a
+	///
+	/// The hash is written by hand so the seed never changes between versions of the standard library
+	pub fn seed(&self) -> u64 {
+		self.bucket().bytes().fold(0xcbf29ce484222325, |hash, byte| {
+			(hash ^ byte as u64).wrapping_mul(0x100000001b3)
+		})
+	}
+
+	/// Returns the settings of the challenge, always following the classic rules
+	pub fn difficulty(&self) -> DifficultySettings {
+		self.level.settings()
+	}
+
+	/// Generates the board of the challenge, the same one every time
+	pub fn grid(&self) -> Grid {
+		let difficulty = self.difficulty();
+		let mut rng = StdRng::seed_from_u64(self.seed());
+		let mut grid = Grid::empty(difficulty.grid_size.x, difficulty.grid_size.y);
+		grid.set_bombs_with_rng(difficulty.bomb_count, &mut rng);
+		grid
+	}
+
+	/// Returns the name of the high-score bucket of the challenge, like `daily-2024-05-17-expert`
+	pub fn bucket(&self) -> String {
+		format!("daily-{:04}-{:02}-{:02}-{}", self.year, self.month, self.day, self.level.name())
+	}
+}
+
+impl fmt::Display for DailyChallenge {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Daily {:04}-{:02}-{:02} ({})", self.year, self.month, self.day, self.level.name())
+	}
+}
@@ -0,0 +1,197 @@
+// This module contains the Flags duel, a turn-based game for two players on a single board, independently of bevy's ECS
+// Finding a mine scores a point and grants another turn, revealing a safe cell passes the turn to the other player.
+// The first player to claim more than half of the mines wins, so the players look for the mines instead of avoiding them.
+
+use crate::components::Coordinates;
+use crate::config::DuelOpponent;
+use super::{Cell, CellState, GameSession, Outcome, RevealOutcome, Solver};
+
+
+/// Seconds the computer waits before playing, so its moves can be followed
+pub const COMPUTER_DELAY: f32 = 0.8;
+
+/// The result of a duel
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DuelResult {
+	/// The player with this index claimed more than half of the mines
+	Won(usize),
+	/// Both players claimed half of the mines
+	Draw,
+}
+
+/// The scores and the turns of a Flags duel, the second player being the computer or someone on the same computer
+#[derive(Debug, Clone)]
+pub struct FlagsDuel {
+	pub opponent: DuelOpponent,
+	scores: [u16; 2],
+	/// The index of the player whose turn it is
+	turn: usize,
+	/// Seconds the computer has been thinking during its turn
+	thinking: f32,
+}
+
+impl FlagsDuel {
+	pub fn new(opponent: DuelOpponent) -> Self {
+		Self { opponent, scores: [0; 2], turn: 0, thinking: 0. }
+	}
+
+	/// Returns the name of a player
+	pub fn name(&self, player: usize) -> &'static str {
+		match (self.opponent, player) {
+			(DuelOpponent::Computer, 0) => "You",
+			(DuelOpponent::Computer, _) => "The computer",
+			(DuelOpponent::HotSeat, 0) => "Player 1",
+			(DuelOpponent::HotSeat, _) => "Player 2",
+		}
+	}
+
+	pub fn turn(&self) -> usize { self.turn }
+	pub fn score(&self, player: usize) -> u16 { self.scores[player] }
+
+	/// Returns true while the computer is playing, the other player having to wait
+	pub fn is_computer_turn(&self) -> bool {
+		self.opponent == DuelOpponent::Computer && self.turn == 1
+	}
+
+	/// Counts a move of the current player: a mine scores and grants another turn, a safe cell passes the turn
+	pub fn play(&mut self, outcome: &RevealOutcome, session: &GameSession) {
+		match outcome {
+			RevealOutcome::Claimed(coord) => self.scores[self.turn] += session.grid().mines_at(*coord) as u16,
+			RevealOutcome::Safe(_) | RevealOutcome::Victory(_) => self.turn = 1 - self.turn,
+			_ => {},
+		}
+	}
+
+	/// Returns the scores of both players at the end of the game
+	///
+	/// Once every safe cell is revealed, the player whose turn it is can claim all the mines left
+	pub fn final_scores(&self, session: &GameSession) -> [u16; 2] {
+		let mut scores = self.scores;
+		if session.outcome() == Outcome::Victory {
+			scores[self.turn] += session.grid().bomb_count() - scores[0] - scores[1];
+		}
+		scores
+	}
+
+	/// Returns the result of the duel, None while it goes on
+	pub fn result(&self, session: &GameSession) -> Option<DuelResult> {
+		let total = session.grid().bomb_count();
+		match self.final_scores(session) {
+			[first, _] if first * 2 > total => Some(DuelResult::Won(0)),
+			[_, second] if second * 2 > total => Some(DuelResult::Won(1)),
+			[first, second] if first + second == total => Some(DuelResult::Draw),
+			_ => None,
+		}
+	}
+
+	/// Lets the computer think, returning true when it is time for it to play
+	pub fn think(&mut self, secs: f32) -> bool {
+		if !self.is_computer_turn() { return false }
+		self.thinking += secs;
+		if self.thinking < COMPUTER_DELAY { return false }
+		self.thinking = 0.;
+		true
+	}
+}
+
+/// Estimates the probability of every covered cell to hide a mine, from what the players can see
+///
+/// The cells proven safe or proven to be mines by the solver are certain. Any other cell next to a number
+/// gets the highest density of mines among the numbers around it, the mines left over being spread evenly
+/// on the cells that are not next to any number.
+pub fn mine_probabilities(session: &GameSession) -> Vec<(Coordinates, f32)> {
+	let grid = session.grid();
+	let mut solver = Solver::new();
+	let safe = solver.deduce(session);
+	let covered: Vec<Coordinates> = grid.cells().into_iter()
+		.filter(|&coord| session.state(coord) == Some(CellState::Covered))
+		.collect();
+	if covered.is_empty() { return Vec::new() }
+
+	// The mines claimed are flagged and known to everyone
+	let claimed = |coord: Coordinates| match session.state(coord) {
+		Some(CellState::Flagged) => session.flags_at(coord) as f32,
+		_ => 0.,
+	};
+	let mut densities: Vec<Option<f32>> = vec![None; covered.len()];
+	for coord in grid.cells() {
+		if session.state(coord) != Some(CellState::Revealed) { continue }
+		let Some(Cell::BombNeighbor(count)) = grid.get(coord) else { continue };
+		let neighbors: Vec<Coordinates> = grid.get_neighbors(coord).collect();
+		let unknown = neighbors.iter().filter(|&&c| session.state(c) == Some(CellState::Covered)).count();
+		if unknown == 0 { continue }
+		let left = count as f32 - neighbors.iter().map(|&c| claimed(c)).sum::<f32>();
+		let density = (left / unknown as f32).clamp(0., 1.);
+		for (index, cell) in covered.iter().enumerate() {
+			if neighbors.contains(cell) {
+				densities[index] = Some(densities[index].map_or(density, |d: f32| d.max(density)));
+			}
+		}
+	}
+
+	let claimed_total: f32 = grid.cells().into_iter().map(claimed).sum();
+	let expected_near_numbers: f32 = densities.iter().flatten().sum();
+	let far_cells = densities.iter().filter(|density| density.is_none()).count();
+	let far_density = match far_cells {
+		0 => 0.,
+		_ => ((grid.bomb_count() as f32 - claimed_total - expected_near_numbers) / far_cells as f32).clamp(0., 1.),
+	};
+
+	covered.into_iter().zip(densities).map(|(coord, density)| {
+		let probability = if safe.contains(&coord) { 0. }
+			else if solver.bombs().contains(&coord) { 1. }
+			else { density.unwrap_or(far_density) };
+		(coord, probability)
+	}).collect()
+}
+
+/// Returns the cell the computer reveals: the one most likely to hide a mine
+pub fn computer_move(session: &GameSession) -> Option<Coordinates> {
+	mine_probabilities(session).into_iter()
+		.max_by(|(_, a), (_, b)| a.total_cmp(b))
+		.map(|(coord, _)| coord)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::resources::Grid;
+
+	/// A 5x3 grid cut in two by a wall of mines in its middle column, the mines being claimed
+	fn duel_session() -> GameSession {
+		let grid = Grid::from_bombs(5, 3, &[Coordinates::new(2, 0), Coordinates::new(2, 1), Coordinates::new(2, 2)]);
+		GameSession::new(grid).with_claimed_mines()
+	}
+
+	#[test]
+	fn a_safe_cell_passes_the_turn_and_a_mine_scores() {
+		let mut session = duel_session();
+		let mut duel = FlagsDuel::new(DuelOpponent::HotSeat);
+		let outcome = session.reveal(Coordinates::new(0, 1));
+		assert!(matches!(outcome, RevealOutcome::Safe(_)));
+		duel.play(&outcome, &session);
+		assert_eq!(duel.turn(), 1);
+
+		let mine = Coordinates::new(2, 1);
+		let outcome = session.reveal(mine);
+		assert_eq!(outcome, RevealOutcome::Claimed(mine));
+		duel.play(&outcome, &session);
+		assert_eq!((duel.turn(), duel.score(0), duel.score(1)), (1, 0, 1));
+		assert_eq!(duel.result(&session), None);
+	}
+
+	#[test]
+	fn the_mines_left_go_to_the_player_who_clears_the_board() {
+		let mut session = duel_session();
+		let mut duel = FlagsDuel::new(DuelOpponent::Computer);
+		for coord in [Coordinates::new(0, 1), Coordinates::new(2, 0), Coordinates::new(4, 1)] {
+			let outcome = session.reveal(coord);
+			duel.play(&outcome, &session);
+		}
+		// The computer claimed a mine then cleared the board, its turn passing back to the player
+		assert_eq!(session.outcome(), Outcome::Victory);
+		assert_eq!(duel.final_scores(&session), [2, 1]);
+		assert_eq!(duel.result(&session), Some(DuelResult::Won(0)));
+	}
+}
@@ -5,37 +5,40 @@
 use std::ops::{Deref, DerefMut};
 use bevy::log::debug;
 use rand::Rng;
+use rand::seq::SliceRandom;
 
 use crate::components::Coordinates;
-
-/// Slice containing the position of all neighbors of a cell
-pub const NEIGHBORS: [(i8, i8); 8] = [
-	(-1,  1), (0,  1), (1,  1),
-	(-1,  0),          (1,  0),
-	(-1, -1), (0, -1), (1, -1),
-];
+use super::{Kernel, Mask, Topology, TopologyKind};
 
 
 /// A enum that describes a single cell in the gane grid
 ///
-/// It can either be a Bomb, an empty cell or a Bomb neighbor (in which case it stores the number of neigboring bombs)
+/// It can either be a Bomb (storing how many mines the cell holds, 1 unless the grid allows several mines per cell),
+/// an empty cell or a Bomb neighbor (in which case it stores the number of neigboring bombs)
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Cell {
-	Bomb,
-	BombNeighbor(u8),
+	Bomb(u8),
+	BombNeighbor(u16),
 	Empty,
 }
 
 impl Cell {
 	/// Returns true if the cell is a bomb
 	pub fn is_bomb(&self) -> bool {
-		matches!(self, Self::Bomb)
+		matches!(self, Self::Bomb(_))
+	}
+	/// Returns the number of mines in the cell
+	pub fn mines(&self) -> u8 {
+		match self {
+			Cell::Bomb(mines) => *mines,
+			_ => 0,
+		}
 	}
 	/// Returns an ASCII representation of the cell
 	pub fn console_output(&self) -> String {
 		format!("{}", match self {
 			Cell::Empty => " ".to_string(),
-			Cell::Bomb => "*".to_string(),
+			Cell::Bomb(_) => "*".to_string(),
 			Cell::BombNeighbor(v) => v.to_string(),
 		})
 	}
@@ -46,10 +49,15 @@ impl Cell {
 ///
 /// The grid is stored as a 2D Vector of Cells
 /// Every field is private, but there is getters for everything needed for the game
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Grid {
 	bomb_count: u16,
 	height: u16,
 	width: u16,
+	topology: TopologyKind,
+	kernel: Kernel,
+	mask: Option<Mask>,
+	mines_per_cell: u8,
 	grid: Vec<Vec<Cell>>,
 }
 /// Allows easy access to the contained grid by dereferencing the structure
@@ -72,68 +80,200 @@ impl Grid {
 			bomb_count: 0,
 			height,
 			width,
+			topology: TopologyKind::Square,
+			kernel: Kernel::Moore,
+			mask: None,
+			mines_per_cell: 1,
 			grid: map,
 		}
 	}
 
+	/// Changes the topology of the grid, updating the bomb count of every cell
+	pub fn with_topology(mut self, topology: TopologyKind) -> Self {
+		self.topology = topology;
+		self.reset_neighbors();
+		self
+	}
+
+	/// Changes the cells counted as neighbors, updating the bomb count of every cell
+	pub fn with_kernel(mut self, kernel: Kernel) -> Self {
+		self.kernel = kernel;
+		self.reset_neighbors();
+		self
+	}
+
+	/// Disables the cells that are holes in the mask, removing their bombs and updating the bomb count of every cell
+	///
+	/// The cells outside of the mask are disabled too, so the mask should have the size of the grid
+	pub fn with_mask(mut self, mask: Mask) -> Self {
+		for (y, line) in self.grid.iter_mut().enumerate() {
+			for (x, cell) in line.iter_mut().enumerate() {
+				if cell.is_bomb() && !mask.is_enabled(Coordinates::new(x as u16, y as u16)) {
+					self.bomb_count -= cell.mines() as u16;
+					*cell = Cell::Empty;
+				}
+			}
+		}
+		self.mask = Some(mask);
+		self.reset_neighbors();
+		self
+	}
+
+	/// Allows the generation to stack up to `mines` mines in a single cell, the numbers counting every mine
+	pub fn with_mines_per_cell(mut self, mines: u8) -> Self {
+		self.mines_per_cell = mines.max(1);
+		self
+	}
+
+	/// Clears then computes again the bomb count of every cell
+	fn reset_neighbors(&mut self) {
+		for line in self.grid.iter_mut() {
+			for cell in line.iter_mut().filter(|cell| !cell.is_bomb()) {
+				*cell = Cell::Empty;
+			}
+		}
+		self.update_neighbors();
+	}
+
+	/// Create a grid with bombs at the given coordinates, coordinates outside of the grid are ignored
+	///
+	/// Coordinates given several times hold several mines
+	pub fn from_bombs(width: u16, height: u16, bombs: &[Coordinates]) -> Self {
+		let mut grid = Self::empty(width, height);
+		for &coord in bombs {
+			if !grid.contains(coord) { continue }
+			let cell = &mut grid.grid[coord.y as usize][coord.x as usize];
+			if cell.mines() == u8::MAX { continue }
+			*cell = Cell::Bomb(cell.mines() + 1);
+			grid.bomb_count += 1;
+		}
+		grid.mines_per_cell = grid.iter().flatten().map(Cell::mines).max().unwrap_or(1).max(1);
+		grid.update_neighbors();
+		grid
+	}
+
 	/// Spawns an abritrary number of bombs in the grid
 	pub fn set_bombs(&mut self, count: u16) {
-		let mut rng = rand::thread_rng();
-		let mut remaining = count;
-		while remaining > 0 {
-			let (x, y) = (
-				rng.gen_range(0..self.width) as usize,
-				rng.gen_range(0..self.height) as usize,
-			);
-			if let Cell::Empty = self[y][x] {
-				self.grid[y][x] = Cell::Bomb;
-				remaining -= 1;
-			}
+		self.set_bombs_with_rng(count, &mut rand::thread_rng());
+	}
+
+	/// Spawns an abritrary number of bombs in the grid, using the given random number generator
+	///
+	/// Using a seeded generator always produces the same grid for the same seed
+	pub fn set_bombs_with_rng(&mut self, count: u16, rng: &mut impl Rng) {
+		self.set_bombs_avoiding(count, rng, &[]);
+	}
+
+	/// Returns how many more bombs the cells out of the safe zone can hold
+	pub fn bomb_room(&self, safe_zone: &[Coordinates]) -> usize {
+		self.cells().into_iter()
+			.filter(|coord| !safe_zone.contains(coord))
+			.map(|coord| self.mines_per_cell.saturating_sub(self.mines_at(coord)) as usize)
+			.sum()
+	}
+
+	/// Spawns an abritrary number of bombs in the grid, leaving the cells of the safe zone empty
+	///
+	/// A cell receives several bombs if the grid allows more than one mine per cell.
+	/// The bombs already in the grid are kept, and counted with the new ones in the bomb count of the grid.
+	///
+	/// # Panics
+	/// If the cells out of the safe zone have no room for all the bombs
+	pub fn set_bombs_avoiding(&mut self, count: u16, rng: &mut impl Rng, safe_zone: &[Coordinates]) {
+		// Every mine a cell can still hold is a slot, the bombs go to slots drawn at random
+		let mut slots: Vec<Coordinates> = self.cells().into_iter()
+			.filter(|coord| !safe_zone.contains(coord))
+			.flat_map(|coord| std::iter::repeat(coord).take(self.mines_per_cell.saturating_sub(self.mines_at(coord)) as usize))
+			.collect();
+		assert!(count as usize <= slots.len(), "Cannot place {} bombs, the grid only has room for {}", count, slots.len());
+		let (drawn, _) = slots.partial_shuffle(rng, count as usize);
+		for coord in drawn.iter() {
+			let cell = &mut self.grid[coord.y as usize][coord.x as usize];
+			*cell = Cell::Bomb(cell.mines() + 1);
 		}
 		self.update_neighbors();
-		self.bomb_count = count;
+		self.bomb_count = self.cells().into_iter().map(|coord| self.mines_at(coord) as u16).sum();
 	}
 
 	/// Updates every cell in the grid to contain the count of bomb among the cell's neighbors
 	fn update_neighbors(&mut self) {
-		for y in 0..self.height {
-			for x in 0..self.width {
-				let coords = Coordinates { x, y };
-				if self.is_bomb_at(coords) { continue }
-				let bombs = self.count_bomb_neighbors(coords);
-				if bombs > 0 {
-					let cell = &mut self[y as usize][x as usize];
-					*cell = Cell::BombNeighbor(bombs)
-				}
+		for coords in self.cells() {
+			if self.is_bomb_at(coords) { continue }
+			let bombs = self.count_bomb_neighbors(coords);
+			if bombs > 0 {
+				let cell = &mut self[coords.y as usize][coords.x as usize];
+				*cell = Cell::BombNeighbor(bombs)
 			}
 		}
 	}
 
+	/// Returns the implementation of the grid's topology
+	pub fn shape(&self) -> &'static dyn Topology {
+		self.topology.topology()
+	}
+
+	/// Returns every cell of the grid, leaving out the holes of the mask
+	pub fn cells(&self) -> Vec<Coordinates> {
+		self.shape().cells(self.width, self.height)
+			.into_iter()
+			.filter(|&coord| self.mask.as_ref().map_or(true, |mask| mask.is_enabled(coord)))
+			.collect()
+	}
+
+	/// Returns true if the coordinates are inside the grid and not a hole of the mask
+	pub fn contains(&self, coordinates: Coordinates) -> bool {
+		self.shape().contains(coordinates, self.width, self.height)
+			&& self.mask.as_ref().map_or(true, |mask| mask.is_enabled(coordinates))
+	}
+
+	/// Returns the cell at coordinates, or None if the coordinates are out of the grid
+	pub fn get(&self, coordinates: Coordinates) -> Option<Cell> {
+		if !self.contains(coordinates) { return None }
+		Some(self.grid[coordinates.y as usize][coordinates.x as usize])
+	}
+
 	/// Returns true is the cell at coordinates contains a bomb
 	pub fn is_bomb_at(&self, coordinates: Coordinates) -> bool {
-		if coordinates.x >= self.width || coordinates.y >= self.height { return false; };
+		if !self.contains(coordinates) { return false; };
 		self.grid[coordinates.y as usize][coordinates.x as usize].is_bomb()
 	}
 
-	/// Returns an iterator of the neighbors of the cell at the coordinates specified
+	/// Returns the number of mines in the cell at coordinates
+	pub fn mines_at(&self, coordinates: Coordinates) -> u8 {
+		self.get(coordinates).map_or(0, |cell| cell.mines())
+	}
+
+	/// Returns the number of cells containing at least one bomb
+	pub fn bomb_cell_count(&self) -> u32 {
+		self.cells().into_iter().filter(|&coord| self.is_bomb_at(coord)).count() as u32
+	}
+
+	/// Returns an iterator of the neighbors of the cell at the coordinates specified, according to the grid's topology and kernel
 	pub fn get_neighbors(&self, coordinates: Coordinates) -> impl Iterator<Item = Coordinates> {
-		NEIGHBORS.iter()
-			.copied()
-			.map(move |neighbor_coordinates| coordinates + neighbor_coordinates)
+		let mut neighbors = self.shape().neighbors(coordinates, self.width, self.height, &self.kernel);
+		// The holes of the mask are never counted
+		neighbors.retain(|&coord| self.contains(coord));
+		neighbors.into_iter()
 	}
 
-	/// Returns the number of neighbors of a cell at specified coordinates that contains a bomb
-	pub fn count_bomb_neighbors(&self, coordinates: Coordinates) -> u8 {
+	/// Returns the number of bombs in the neighbors of a cell at specified coordinates
+	///
+	/// A neighbor holding several mines counts for all of them, so the number can exceed the number of neighbors
+	pub fn count_bomb_neighbors(&self, coordinates: Coordinates) -> u16 {
 		if self.is_bomb_at(coordinates) { return 0 }
 		self.get_neighbors(coordinates)
-			.filter(|&coord| self.is_bomb_at(coord))
-			.count() as u8
+			.map(|coord| self.mines_at(coord) as u16)
+			.sum()
 	}
 
 	// Some getters that might or might not be useful
 	pub fn width(&self) -> u16 { self.width }
 	pub fn height(&self) -> u16 { self.height }
 	pub fn bomb_count(&self) -> u16 { self.bomb_count }
+	pub fn topology(&self) -> TopologyKind { self.topology }
+	pub fn kernel(&self) -> &Kernel { &self.kernel }
+	pub fn mask(&self) -> Option<&Mask> { self.mask.as_ref() }
+	pub fn mines_per_cell(&self) -> u8 { self.mines_per_cell }
 
 	/// Returns a String containing the grids ASCII representation, for logging/debugging purposes
 	pub fn console_output(&self) -> String {
@@ -153,3 +293,37 @@ impl Grid {
 		format!("{}{}", buffer, line)
 	}
 }
+
+
+#[cfg(test)]
+mod tests {
+	use rand::SeedableRng;
+	use rand::rngs::StdRng;
+	use super::*;
+
+	#[test]
+	fn a_full_grid_is_filled_at_once() {
+		let mut rng = StdRng::seed_from_u64(1);
+		let safe_zone = [Coordinates::new(0, 0)];
+		let mut grid = Grid::empty(4, 4).with_mines_per_cell(3);
+		grid.set_bombs_avoiding(45, &mut rng, &safe_zone);
+		assert_eq!(grid.mines_at(Coordinates::new(0, 0)), 0);
+		assert!(grid.cells().into_iter().filter(|coord| !safe_zone.contains(coord)).all(|coord| grid.mines_at(coord) == 3));
+	}
+
+	#[test]
+	fn the_bombs_already_placed_are_counted() {
+		let mut rng = StdRng::seed_from_u64(1);
+		let mut grid = Grid::empty(4, 4).with_mines_per_cell(2);
+		grid.set_bombs_with_rng(10, &mut rng);
+		grid.set_bombs_with_rng(5, &mut rng);
+		assert_eq!(grid.bomb_count(), 15);
+		assert_eq!(grid.cells().into_iter().map(|coord| grid.mines_at(coord) as u16).sum::<u16>(), 15);
+	}
+
+	#[test]
+	#[should_panic(expected = "only has room for 15")]
+	fn too_many_bombs_are_rejected() {
+		Grid::empty(4, 4).set_bombs_avoiding(16, &mut StdRng::seed_from_u64(1), &[Coordinates::new(3, 3)]);
+	}
+}
@@ -0,0 +1,184 @@
+// This module contains the best times of the player, saved in a small text file
+// The times are sorted in buckets: one per standard difficulty, and one per daily challenge
+// Every time keeps the 3BV and the clicks of its game, to judge it beyond the time
+
+use core::fmt;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{Clicks, GameMetrics};
+
+/// File where the high scores are saved, next to the boards directory
+pub const HIGH_SCORES_FILE: &str = "high_scores.txt";
+
+/// How many times are kept in every bucket
+pub const SCORES_PER_BUCKET: usize = 10;
+
+/// The key of the line listing the daily challenges already attempted
+const ATTEMPTED_KEY: &str = "attempted";
+
+
+/// A time kept in the high scores, with the metrics of its game
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Score {
+	pub secs: f32,
+	/// The metrics of the game, missing from the times saved before they were kept
+	pub metrics: Option<GameMetrics>,
+}
+
+/// Shows the score like `93.20 3bv:120 left:130 right:20 chord:12 useful:150`, or only its time without its metrics
+impl fmt::Display for Score {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:.2}", self.secs)?;
+		if let Some(metrics) = &self.metrics {
+			let clicks = metrics.clicks;
+			write!(f, " 3bv:{} left:{} right:{} chord:{} useful:{}", metrics.three_bv, clicks.left, clicks.right, clicks.chord, clicks.useful)?;
+		}
+		Ok(())
+	}
+}
+
+/// Parses a score shown by `Score::fmt`, the chords missing from the scores saved before they were counted
+impl FromStr for Score {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut words = text.split_whitespace();
+		let time = words.next().unwrap_or_default();
+		let secs = time.parse::<f32>().map_err(|_| format!("invalid time {}", time))?;
+		let mut three_bv = None;
+		let mut clicks = Clicks::default();
+		for word in words {
+			let (key, value) = word.split_once(':').ok_or_else(|| format!("expected `metric:value`, found {}", word))?;
+			let value = value.parse::<u32>().map_err(|_| format!("invalid {} {}", key, value))?;
+			match key {
+				"3bv" => three_bv = Some(value),
+				"left" => clicks.left = value,
+				"right" => clicks.right = value,
+				"chord" => clicks.chord = value,
+				"useful" => clicks.useful = value,
+				_ => return Err(format!("unknown metric {}", key)),
+			}
+		}
+		// The times are victories, the whole 3BV was solved
+		let metrics = three_bv.map(|three_bv| GameMetrics { three_bv, solved: three_bv, clicks, secs });
+		Ok(Score { secs, metrics })
+	}
+}
+
+
+/// The best times of every bucket, in seconds, and the daily challenges already attempted
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HighScores {
+	buckets: BTreeMap<String, Vec<Score>>,
+	attempted: BTreeSet<String>,
+}
+
+impl HighScores {
+	/// Loads the high scores from a file, see `HighScores::from_str` for the format
+	///
+	/// A missing file gives empty high scores, since nobody won a game yet
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		match fs::read_to_string(path) {
+			Ok(text) => text.parse(),
+			Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+			Err(err) => Err(format!("Cannot read high scores file {}: {}", path.display(), err)),
+		}
+	}
+
+	/// Saves the high scores to a file
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+		let path = path.as_ref();
+		fs::write(path, self.to_string())
+			.map_err(|err| format!("Cannot write high scores file {}: {}", path.display(), err))
+	}
+
+	/// Records the time of a victory with the metrics of its game, returning its rank in the bucket starting from 0
+	///
+	/// Returns None if the time is not good enough to be kept
+	pub fn record(&mut self, bucket: &str, secs: f32, metrics: Option<GameMetrics>) -> Option<usize> {
+		let scores = self.buckets.entry(bucket.to_string()).or_default();
+		let rank = scores.partition_point(|score| score.secs <= secs);
+		if rank >= SCORES_PER_BUCKET { return None }
+		scores.insert(rank, Score { secs, metrics });
+		scores.truncate(SCORES_PER_BUCKET);
+		Some(rank)
+	}
+
+	/// Returns the best scores of a bucket, the fastest first
+	pub fn scores(&self, bucket: &str) -> &[Score] {
+		self.buckets.get(bucket).map_or(&[], |scores| scores.as_slice())
+	}
+
+	/// Returns the best times of a bucket, the fastest first
+	pub fn times(&self, bucket: &str) -> Vec<f32> {
+		self.scores(bucket).iter().map(|score| score.secs).collect()
+	}
+
+	/// Returns the best time of a bucket
+	pub fn best(&self, bucket: &str) -> Option<f32> {
+		self.scores(bucket).first().map(|score| score.secs)
+	}
+
+	/// Returns true if the ranked attempt of the bucket was already used
+	pub fn has_attempted(&self, bucket: &str) -> bool {
+		self.attempted.contains(bucket)
+	}
+
+	/// Uses the only ranked attempt of a bucket, returning false if it was already used
+	pub fn use_attempt(&mut self, bucket: &str) -> bool {
+		self.attempted.insert(bucket.to_string())
+	}
+}
+
+/// Parses the high scores from text
+///
+/// Every line contains a bucket and its times in seconds separated by commas, like `expert = 93.2, 101.7`,
+/// every time being optionally followed by its metrics, see `Score::from_str`.
+/// The line `attempted` lists the daily challenges already attempted.
+/// Empty lines and lines starting with `#` are ignored.
+impl FromStr for HighScores {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut scores = HighScores::default();
+		for (number, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') { continue }
+			let (key, value) = line.split_once('=')
+				.ok_or_else(|| format!("Line {}: expected `bucket = times`", number + 1))?;
+			let values = value.split(',').map(str::trim).filter(|value| !value.is_empty());
+			if key.trim() == ATTEMPTED_KEY {
+				scores.attempted.extend(values.map(str::to_string));
+				continue
+			}
+			let mut times = values
+				.map(|score| score.parse::<Score>().map_err(|err| format!("Line {}: {}", number + 1, err)))
+				.collect::<Result<Vec<_>, _>>()?;
+			times.sort_by(|a, b| a.secs.total_cmp(&b.secs));
+			times.truncate(SCORES_PER_BUCKET);
+			scores.buckets.insert(key.trim().to_string(), times);
+		}
+		Ok(scores)
+	}
+}
+
+impl fmt::Display for HighScores {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "# Best times in seconds")?;
+		for (bucket, times) in &self.buckets {
+			let times: Vec<String> = times.iter().map(Score::to_string).collect();
+			writeln!(f, "{} = {}", bucket, times.join(", "))?;
+		}
+		if !self.attempted.is_empty() {
+			writeln!(f, "# Daily challenges already attempted")?;
+			let attempted: Vec<&str> = self.attempted.iter().map(String::as_str).collect();
+			writeln!(f, "{} = {}", ATTEMPTED_KEY, attempted.join(", "))?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,206 @@
+// This module contains the neighborhood kernels, deciding which cells are counted by the number of a cell
+// A kernel is a list of offsets from a cell, the topology of the board then decides how they are applied
+//
+// Custom kernels can be drawn in a text file, with `X` for the cell, `o` for the counted cells and `.` for the others:
+//
+//   .o.o.
+//   o...o
+//   ..X..
+//   o...o
+//   .o.o.
+//
+// Empty lines and lines starting with `#` are ignored, the drawing does not need to be centered on the cell.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use bevy::reflect::Reflect;
+
+use super::NEIGHBORS;
+
+
+/// The four cells sharing a side with the cell
+pub const ORTHOGONAL_NEIGHBORS: [(i8, i8); 4] = [
+	          (0,  1),
+	(-1,  0),          (1,  0),
+	          (0, -1),
+];
+
+/// The eight cells a chess knight could jump to
+pub const KNIGHT_NEIGHBORS: [(i8, i8); 8] = [
+	(-1,  2), (1,  2),
+	(-2,  1), (2,  1),
+	(-2, -1), (2, -1),
+	(-1, -2), (1, -2),
+];
+
+/// Every cell of the 5x5 square around the cell
+pub const RADIUS_2_NEIGHBORS: [(i8, i8); 24] = [
+	(-2,  2), (-1,  2), (0,  2), (1,  2), (2,  2),
+	(-2,  1), (-1,  1), (0,  1), (1,  1), (2,  1),
+	(-2,  0), (-1,  0),          (1,  0), (2,  0),
+	(-2, -1), (-1, -1), (0, -1), (1, -1), (2, -1),
+	(-2, -2), (-1, -2), (0, -2), (1, -2), (2, -2),
+];
+
+
+/// The cells counted as neighbors of a cell, as offsets from it
+///
+/// Kernels apply to the square and torus topologies, hexagonal cells always count the 6 cells touching them
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Reflect)]
+pub enum Kernel {
+	/// The 8 cells around the cell, the classic minesweeper rule
+	#[default]
+	Moore,
+	Orthogonal,
+	Knight,
+	Radius2,
+	Custom(Vec<(i8, i8)>),
+}
+
+impl Kernel {
+	/// Returns the offsets of the neighbors
+	pub fn offsets(&self) -> &[(i8, i8)] {
+		match self {
+			Kernel::Moore => &NEIGHBORS,
+			Kernel::Orthogonal => &ORTHOGONAL_NEIGHBORS,
+			Kernel::Knight => &KNIGHT_NEIGHBORS,
+			Kernel::Radius2 => &RADIUS_2_NEIGHBORS,
+			Kernel::Custom(offsets) => offsets,
+		}
+	}
+
+	/// Returns the next kernel among the predefined ones, to cycle through them
+	pub fn next(&self) -> Self {
+		match self {
+			Kernel::Moore => Kernel::Orthogonal,
+			Kernel::Orthogonal => Kernel::Knight,
+			Kernel::Knight => Kernel::Radius2,
+			Kernel::Radius2 | Kernel::Custom(_) => Kernel::Moore,
+		}
+	}
+
+	/// Loads a custom kernel drawn in a text file, see the `kernel` module for the format
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		let text = fs::read_to_string(path)
+			.map_err(|err| format!("Cannot read kernel file {}: {}", path.display(), err))?;
+		Self::from_drawing(&text)
+	}
+
+	/// Reads a custom kernel from its drawing
+	pub fn from_drawing(text: &str) -> Result<Self, String> {
+		let rows: Vec<&str> = text.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty() && !line.starts_with('#'))
+			.collect();
+
+		let mut center = None;
+		let mut counted = Vec::new();
+		for (row, line) in rows.iter().enumerate() {
+			for (column, c) in line.chars().enumerate() {
+				match c {
+					'X' if center.is_some() => return Err("The kernel has more than one center".to_string()),
+					'X' => center = Some((column as i32, row as i32)),
+					'o' => counted.push((column as i32, row as i32)),
+					'.' => {},
+					other => return Err(format!("Unknown character '{}' in the kernel", other)),
+				}
+			}
+		}
+		let (cx, cy) = center.ok_or("The kernel has no center, mark it with an X")?;
+		// The first row of the drawing is the top, while our rows go up
+		let offsets: Vec<(i8, i8)> = counted.into_iter()
+			.map(|(x, y)| Ok((i8::try_from(x - cx)?, i8::try_from(cy - y)?)))
+			.collect::<Result<_, std::num::TryFromIntError>>()
+			.map_err(|_| "The kernel is too big".to_string())?;
+		if offsets.is_empty() {
+			return Err("The kernel counts no cell, mark them with an o".to_string());
+		}
+		Ok(Kernel::Custom(offsets))
+	}
+}
+
+/// Writes the name of the kernel, followed by the offsets of a custom kernel like `custom 1,2 -1,2`
+impl fmt::Display for Kernel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Kernel::Moore => write!(f, "moore"),
+			Kernel::Orthogonal => write!(f, "orthogonal"),
+			Kernel::Knight => write!(f, "knight"),
+			Kernel::Radius2 => write!(f, "radius2"),
+			Kernel::Custom(offsets) => {
+				write!(f, "custom")?;
+				for (x, y) in offsets {
+					write!(f, " {},{}", x, y)?;
+				}
+				Ok(())
+			},
+		}
+	}
+}
+
+impl FromStr for Kernel {
+	type Err = String;
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut words = text.split_whitespace();
+		match words.next() {
+			Some("moore") => Ok(Kernel::Moore),
+			Some("orthogonal") => Ok(Kernel::Orthogonal),
+			Some("knight") => Ok(Kernel::Knight),
+			Some("radius2") => Ok(Kernel::Radius2),
+			Some("custom") => {
+				let offsets = words
+					.map(|offset| {
+						let (x, y) = offset.split_once(',').ok_or(format!("Invalid offset '{}'", offset))?;
+						let parse = |value: &str| value.parse::<i8>().map_err(|_| format!("Invalid offset '{}'", offset));
+						match (parse(x)?, parse(y)?) {
+							(0, 0) => Err("A cell cannot be its own neighbor".to_string()),
+							offset => Ok(offset),
+						}
+					})
+					.collect::<Result<Vec<_>, String>>()?;
+				if offsets.is_empty() { return Err("The custom kernel counts no cell".to_string()) }
+				// A neighbor counted twice would only be counted once on a torus
+				let mut seen = HashSet::new();
+				if let Some((x, y)) = offsets.iter().find(|&&offset| !seen.insert(offset)) {
+					return Err(format!("The offset {},{} is counted twice", x, y));
+				}
+				Ok(Kernel::Custom(offsets))
+			},
+			_ => Err(format!("Unknown kernel '{}'", text)),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn kernels_are_read_back_from_their_names() {
+		for kernel in [Kernel::Moore, Kernel::Orthogonal, Kernel::Knight, Kernel::Radius2, Kernel::Custom(vec![(1, 2), (-1, 2)])] {
+			assert_eq!(kernel.to_string().parse::<Kernel>().unwrap(), kernel);
+		}
+		assert_eq!("custom 0,1  -3,0".parse::<Kernel>().unwrap(), Kernel::Custom(vec![(0, 1), (-3, 0)]));
+	}
+
+	#[test]
+	fn invalid_custom_kernels_are_rejected() {
+		for text in ["custom", "custom 0,0", "custom 1,2 1,2", "custom 1,2 0,1 1,2", "custom 1;2", "custom 1,200", "king"] {
+			assert!(text.parse::<Kernel>().is_err(), "{}", text);
+		}
+	}
+
+	#[test]
+	fn drawings_are_read_from_their_center() {
+		let kernel = Kernel::from_drawing("# Above and right\n.o\nX.o\n").unwrap();
+		assert_eq!(kernel, Kernel::Custom(vec![(1, 1), (2, 0)]));
+		assert!(Kernel::from_drawing("oXo\n.X.\n").is_err());
+		assert!(Kernel::from_drawing("ooo\n").is_err());
+		assert!(Kernel::from_drawing("X..\n").is_err());
+	}
+}
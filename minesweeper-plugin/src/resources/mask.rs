@@ -0,0 +1,127 @@
+// This module contains the masks, giving irregular shapes to the boards by disabling some of their cells
+// A disabled cell is a hole in the board: it is never mined, never counted as a neighbor and never shown.
+//
+// A mask can be drawn in a text file, with `X` for the cells of the board and `.` or spaces for the holes:
+//
+//   .XX.XX.
+//   XXXXXXX
+//   .XXXXX.
+//   ..XXX..
+//   ...X...
+//
+// Empty lines and lines starting with `#` are ignored, shorter lines are completed with holes.
+// A mask can also be a small PNG image, with one pixel per cell: the dark opaque pixels are the cells of the board,
+// the light or transparent ones are holes.
+
+use std::fs;
+use std::path::Path;
+use bevy::reflect::Reflect;
+
+use crate::components::Coordinates;
+
+
+/// Pixels darker than this are cells of the board, on a scale from 0 to 255
+const PNG_THRESHOLD: u8 = 128;
+
+
+/// The cells of a board that are enabled, the others being holes
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Reflect)]
+pub struct Mask {
+	width: u16,
+	height: u16,
+	/// Indexed like the grid, the first row being the bottom of the board
+	enabled: Vec<Vec<bool>>,
+}
+
+impl Mask {
+	/// Creates a mask from the enabled cells of every row, starting from the top row
+	///
+	/// Shorter rows are completed with holes
+	pub fn from_rows(rows: Vec<Vec<bool>>) -> Result<Self, String> {
+		let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+		let height = rows.len();
+		if width > u16::MAX as usize || height > u16::MAX as usize {
+			return Err(format!("The mask is too big ({}x{})", width, height));
+		}
+		let enabled: Vec<Vec<bool>> = rows.into_iter()
+			.rev()
+			.map(|mut row| { row.resize(width, false); row })
+			.collect();
+		if !enabled.iter().flatten().any(|&cell| cell) {
+			return Err("The mask has no cell, mark them with an X".to_string());
+		}
+		Ok(Self { width: width as u16, height: height as u16, enabled })
+	}
+
+	/// Loads a mask, reading PNG images (`.png`) as pixels and every other file as a drawing
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		let read_error = |err| format!("Cannot read mask file {}: {}", path.display(), err);
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("png") => Self::from_png(&fs::read(path).map_err(read_error)?),
+			_ => Self::from_drawing(&fs::read_to_string(path).map_err(read_error)?),
+		}
+	}
+
+	/// Reads a mask from its drawing, see the `mask` module for the format
+	pub fn from_drawing(text: &str) -> Result<Self, String> {
+		let rows = text.lines()
+			.map(str::trim_end)
+			.filter(|line| !line.is_empty() && !line.starts_with('#'))
+			.map(|line| line.chars()
+				.map(|c| match c {
+					'X' => Ok(true),
+					'.' | ' ' => Ok(false),
+					other => Err(format!("Unknown character '{}' in the mask", other)),
+				})
+				.collect::<Result<Vec<bool>, String>>())
+			.collect::<Result<Vec<_>, String>>()?;
+		Self::from_rows(rows)
+	}
+
+	/// Reads a mask from a PNG image, with one pixel per cell
+	pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+		let mut decoder = png::Decoder::new(bytes);
+		decoder.set_transformations(png::Transformations::normalize_to_color8());
+		let mut reader = decoder.read_info().map_err(|err| format!("Invalid PNG mask: {}", err))?;
+		let mut pixels = vec![0; reader.output_buffer_size()];
+		let info = reader.next_frame(&mut pixels).map_err(|err| format!("Invalid PNG mask: {}", err))?;
+
+		let channels = info.color_type.samples();
+		let rows = pixels.chunks(info.line_size)
+			.take(info.height as usize)
+			.map(|line| line.chunks(channels)
+				.take(info.width as usize)
+				.map(|pixel| {
+					// Gray images have a single color channel, the alpha channel comes last when there is one
+					let (color, alpha) = match pixel {
+						[gray] => (*gray as u32, 255),
+						[gray, alpha] => (*gray as u32, *alpha),
+						[r, g, b] => ((*r as u32 + *g as u32 + *b as u32) / 3, 255),
+						[r, g, b, alpha, ..] => ((*r as u32 + *g as u32 + *b as u32) / 3, *alpha),
+						[] => (255, 0),
+					};
+					alpha >= PNG_THRESHOLD && color < PNG_THRESHOLD as u32
+				})
+				.collect())
+			.collect();
+		Self::from_rows(rows)
+	}
+
+	/// Returns true if the cell is part of the board, and false for a hole or a cell outside of the mask
+	pub fn is_enabled(&self, coordinates: Coordinates) -> bool {
+		self.enabled
+			.get(coordinates.y as usize)
+			.and_then(|line| line.get(coordinates.x as usize))
+			.copied()
+			.unwrap_or(false)
+	}
+
+	/// Returns the number of cells of the board
+	pub fn enabled_count(&self) -> u32 {
+		self.enabled.iter().flatten().filter(|&&cell| cell).count() as u32
+	}
+
+	pub fn width(&self) -> u16 { self.width }
+	pub fn height(&self) -> u16 { self.height }
+}
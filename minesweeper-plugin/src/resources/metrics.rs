@@ -0,0 +1,249 @@
+// This module contains the metrics used to judge a game beyond its time, independently of bevy's ECS
+// The 3BV (Bechtel's Board Benchmark Value) of a board is the least number of left clicks needed to clear it:
+// one click per opening, plus one click per number that no opening reveals.
+// The other metrics compare the 3BV of the board, or the part of it solved on a loss, to the time and the clicks.
+
+use core::fmt;
+use std::collections::HashSet;
+
+use crate::components::Coordinates;
+use super::{Cell, CellState, GameSession, Grid};
+
+
+/// The clicks needed to clear a board, computed once when the board is generated
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ThreeBv {
+	/// A cell of every opening, revealing it reveals the whole opening
+	openings: Vec<Coordinates>,
+	/// The numbers that no opening reveals, each needing a click of its own
+	isolated: Vec<Coordinates>,
+}
+
+impl ThreeBv {
+	pub fn new(grid: &Grid) -> Self {
+		let mut openings = Vec::new();
+		let mut in_opening: HashSet<Coordinates> = HashSet::new();
+		for coord in grid.cells() {
+			if grid.get(coord) != Some(Cell::Empty) || in_opening.contains(&coord) { continue }
+			openings.push(coord);
+			// The opening spreads through the empty cells, the numbers around it being revealed with it
+			let mut stack = vec![coord];
+			in_opening.insert(coord);
+			while let Some(cell) = stack.pop() {
+				if grid.get(cell) != Some(Cell::Empty) { continue }
+				for neighbor in grid.get_neighbors(cell) {
+					if in_opening.insert(neighbor) {
+						stack.push(neighbor);
+					}
+				}
+			}
+		}
+		let isolated = grid.cells().into_iter()
+			.filter(|coord| matches!(grid.get(*coord), Some(Cell::BombNeighbor(_))) && !in_opening.contains(coord))
+			.collect();
+		Self { openings, isolated }
+	}
+
+	/// Returns the 3BV of the board
+	pub fn total(&self) -> u32 {
+		(self.openings.len() + self.isolated.len()) as u32
+	}
+
+	/// Returns a cell of every opening of the board
+	pub fn openings(&self) -> &[Coordinates] {
+		&self.openings
+	}
+
+	/// Returns the number of islands, the groups of touching numbers that no opening reveals
+	pub fn islands(&self, grid: &Grid) -> u32 {
+		let isolated: HashSet<Coordinates> = self.isolated.iter().copied().collect();
+		let mut visited: HashSet<Coordinates> = HashSet::new();
+		let mut islands = 0;
+		for &coord in &self.isolated {
+			if !visited.insert(coord) { continue }
+			islands += 1;
+			let mut stack = vec![coord];
+			while let Some(cell) = stack.pop() {
+				for neighbor in grid.get_neighbors(cell) {
+					if isolated.contains(&neighbor) && visited.insert(neighbor) {
+						stack.push(neighbor);
+					}
+				}
+			}
+		}
+		islands
+	}
+
+	/// Returns the part of the 3BV already revealed in a session
+	pub fn solved(&self, session: &GameSession) -> u32 {
+		self.openings.iter().chain(&self.isolated)
+			.filter(|&&coord| session.state(coord) == Some(CellState::Revealed))
+			.count() as u32
+	}
+}
+
+
+/// A click of the player on a cell
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Click {
+	/// Reveals a covered cell
+	Left,
+	/// Toggles the flag of a cell
+	Right,
+	/// Reveals the covered neighbors of a number with all its flags around it
+	Chord,
+}
+
+/// The clicks of the player on the board, counted as the player clicks so the replays and the computer are not counted
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Clicks {
+	/// The cells revealed, or clicked to be revealed
+	pub left: u32,
+	/// The flags placed or removed, or clicked to be
+	pub right: u32,
+	/// The chords on the numbers, useful or not
+	pub chord: u32,
+	/// The clicks that changed the board, the others being wasted on revealed or flagged cells
+	pub useful: u32,
+}
+
+impl Clicks {
+	pub fn total(&self) -> u32 {
+		self.left + self.right + self.chord
+	}
+
+	/// Counts a click on a cell of the session, before it is played
+	pub fn count(&mut self, click: Click, session: &GameSession, coordinates: Coordinates) {
+		let useful = match click {
+			Click::Left => { self.left += 1; session.can_reveal(coordinates) },
+			Click::Right => { self.right += 1; session.can_toggle_flag(coordinates) },
+			Click::Chord => { self.chord += 1; !session.chord(coordinates).is_empty() },
+		};
+		if useful {
+			self.useful += 1;
+		}
+	}
+}
+
+
+/// The metrics of a game, once it ended
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GameMetrics {
+	/// The 3BV of the board
+	pub three_bv: u32,
+	/// The part of the 3BV revealed by the player, the whole 3BV for a victory
+	pub solved: u32,
+	pub clicks: Clicks,
+	pub secs: f32,
+}
+
+impl GameMetrics {
+	pub fn new(three_bv: &ThreeBv, session: &GameSession, clicks: Clicks, secs: f32) -> Self {
+		Self { three_bv: three_bv.total(), solved: three_bv.solved(session), clicks, secs }
+	}
+
+	/// Returns the 3BV solved per second
+	pub fn three_bv_per_sec(&self) -> f32 {
+		if self.secs <= 0. { return 0. }
+		self.solved as f32 / self.secs
+	}
+
+	/// Returns the 3BV solved per click, as a percentage: 100% is a board cleared without a single extra click
+	pub fn efficiency(&self) -> f32 {
+		if self.clicks.total() == 0 { return 0. }
+		self.solved as f32 * 100. / self.clicks.total() as f32
+	}
+
+	/// Returns the index of efficiency, the 3BV solved per useful click
+	pub fn ioe(&self) -> f32 {
+		if self.clicks.useful == 0 { return 0. }
+		self.solved as f32 / self.clicks.useful as f32
+	}
+
+	/// Returns the rapport qualité prix, the time divided by the 3BV/s: the lower, the better
+	pub fn rqp(&self) -> Option<f32> {
+		let speed = self.three_bv_per_sec();
+		(speed > 0.).then(|| (self.secs + 1.) / speed)
+	}
+}
+
+/// Shows the metrics like `3BV 95/120, 1.52 3BV/s, efficiency 87%, IOE 0.93, RQP 41.6`
+impl fmt::Display for GameMetrics {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "3BV {}/{}, {:.2} 3BV/s, efficiency {:.0}%, IOE {:.2}",
+			self.solved, self.three_bv, self.three_bv_per_sec(), self.efficiency(), self.ioe())?;
+		match self.rqp() {
+			Some(rqp) => write!(f, ", RQP {:.1}", rqp),
+			None => write!(f, ", RQP -"),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn grid(text: &str) -> Grid {
+		text.parse().unwrap()
+	}
+
+	#[test]
+	fn an_opening_reveals_its_numbers() {
+		// The bottom row opens up to the middle row, the number between the two top mines needs a click of its own
+		let grid = grid("*.*\n...\n...\n");
+		let three_bv = ThreeBv::new(&grid);
+		assert_eq!((three_bv.openings().len(), three_bv.total(), three_bv.islands(&grid)), (1, 2, 1));
+	}
+
+	#[test]
+	fn numbers_without_opening_count_one_click_each() {
+		let grid = grid(".*.\n*.*\n.*.\n");
+		let three_bv = ThreeBv::new(&grid);
+		// The four corners and the centre touch each other, they make a single island
+		assert_eq!((three_bv.openings().len(), three_bv.total(), three_bv.islands(&grid)), (0, 5, 1));
+	}
+
+	#[test]
+	fn a_hole_splits_the_openings() {
+		let grid = grid(".-..*\n.-...\n");
+		let three_bv = ThreeBv::new(&grid);
+		// The left column and the cells right of the hole open separately, the corner next to the mine is isolated
+		assert_eq!((three_bv.openings().len(), three_bv.total()), (2, 3));
+		assert!(three_bv.openings().iter().all(|coord| coord.x != 1));
+	}
+
+	#[test]
+	fn a_cell_with_several_mines_is_a_single_bomb_cell() {
+		let grid = Grid::from_bombs(4, 1, &[Coordinates::new(0, 0), Coordinates::new(0, 0), Coordinates::new(3, 0)]);
+		assert_eq!((grid.bomb_count(), grid.bomb_cell_count()), (3, 2));
+		let three_bv = ThreeBv::new(&grid);
+		assert_eq!((three_bv.openings().len(), three_bv.total(), three_bv.islands(&grid)), (0, 2, 1));
+	}
+
+	#[test]
+	fn a_lost_game_only_solves_what_was_revealed() {
+		let grid = grid(".-..*\n.-...\n");
+		let three_bv = ThreeBv::new(&grid);
+		let mut session = GameSession::new(grid);
+		let mut clicks = Clicks::default();
+		for coord in [Coordinates::new(0, 0), Coordinates::new(0, 0), Coordinates::new(4, 1)] {
+			clicks.count(Click::Left, &session, coord);
+			session.reveal(coord);
+		}
+		// The second click on the same cell is wasted
+		assert_eq!((clicks.total(), clicks.useful), (3, 2));
+		let metrics = GameMetrics::new(&three_bv, &session, clicks, 3.);
+		assert_eq!((metrics.three_bv, metrics.solved), (3, 1));
+	}
+
+	#[test]
+	fn the_metrics_compare_the_3bv_to_the_time_and_the_clicks() {
+		let clicks = Clicks { left: 8, right: 2, chord: 0, useful: 8 };
+		let metrics = GameMetrics { three_bv: 10, solved: 8, clicks, secs: 4. };
+		assert_eq!((metrics.three_bv_per_sec(), metrics.efficiency(), metrics.ioe()), (2., 80., 1.));
+		assert_eq!(metrics.rqp(), Some(2.5));
+		assert_eq!(GameMetrics { secs: 0., ..metrics }.rqp(), None);
+		assert_eq!(metrics.to_string(), "3BV 8/10, 2.00 3BV/s, efficiency 80%, IOE 1.00, RQP 2.5");
+	}
+}
@@ -1,15 +1,49 @@
-use bevy::prelude::{Entity, Resource};
+use bevy::ecs::component::TableStorage;
+use bevy::prelude::{Component, Entity, Resource};
 use bevy::utils::HashMap;
+use rand::rngs::StdRng;
 
 use crate::components::Coordinates;
 
+mod board_format;
+mod community_formats;
+mod daily;
+mod duel;
 mod grid;
+mod high_scores;
+mod kernel;
+mod mask;
+mod metrics;
+mod race;
+mod rating;
+mod replay;
+mod saved_settings;
+mod session;
+mod solver;
+mod statistics;
+mod topology;
+pub use board_format::{BOARDS_DIR, new_board_path, latest_board_path};
+pub use community_formats::BoardFile;
+pub use daily::*;
+pub use duel::*;
 pub use grid::*;
+pub use high_scores::*;
+pub use kernel::*;
+pub use mask::*;
+pub use metrics::*;
+pub use race::*;
+pub use rating::*;
+pub use replay::*;
+pub use saved_settings::*;
+pub use session::*;
+pub use solver::*;
+pub use statistics::*;
+pub use topology::*;
 
 
-/// Hashmap that maps grid coordinates with the "Cover" entity
+/// Hashmap that maps grid coordinates with the "Cover" entity, on the entity of a board
 /// So we can easily uncover them in the `uncover_event_handler`
-#[derive(Resource)]
+#[derive(Component)]
 pub struct CoveredCells(pub HashMap<Coordinates, Entity>);
 impl Default for CoveredCells {
 	fn default() -> Self {
@@ -17,3 +51,55 @@ impl Default for CoveredCells {
 	}
 }
 
+/// The session of the game played on a board, on the entity of the board
+impl Component for GameSession {
+	type Storage = TableStorage;
+}
+
+/// The recording of the game played on a board, on the entity of the board
+impl Component for Replay {
+	type Storage = TableStorage;
+}
+
+/// The replay being played back, if a replay file was loaded
+impl Resource for ReplayPlayback {}
+
+/// The Flags duel being played, only inserted by `game_setup` when the config asks for a duel
+impl Resource for FlagsDuel {}
+
+/// The 3BV of a board, on the entity of the board
+impl Component for ThreeBv {
+	type Storage = TableStorage;
+}
+
+/// The clicks of the player on a board, on the entity of the board
+impl Component for Clicks {
+	type Storage = TableStorage;
+}
+
+/// The best times of the player, loaded when the game starts and saved after every ranked victory
+impl Resource for HighScores {}
+
+/// The games played, loaded when the game starts and saved at the end of every game
+impl Resource for Statistics {}
+
+
+/// Random number generator used to generate every grid, seeded from the `GenerationSettings`
+#[derive(Resource)]
+pub struct BoardRng(pub StdRng);
+
+/// A board or a replay waiting to be loaded by `game_setup` instead of generating a new grid
+#[derive(Resource, Default)]
+pub struct BoardToLoad(pub Option<BoardFile>);
+
+/// The high-score bucket the game being played counts for, None if its time is not ranked
+#[derive(Resource, Default)]
+pub struct RankedGame(pub Option<String>);
+
+/// How many times the board being played was retried after a loss, 0 for a new board
+#[derive(Resource, Default)]
+pub struct RetryCount(pub u32);
+
+/// Whether the board being played was loaded from a file, its retries included, until a new grid is generated
+#[derive(Resource, Default)]
+pub struct LoadedBoard(pub bool);
@@ -0,0 +1,491 @@
+// This module contains the head-to-head races on the local network, independently of bevy's ECS
+// The host generates the board from a seed and sends the seed to the client, so both players race on the same board.
+// The client sends every action to the host, which replays them on its own copy of the client's game to decide the winner.
+// The messages are lines of text sent over a TCP connection, read by a background thread.
+
+use core::fmt;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+use bevy::log::warn;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::components::Coordinates;
+use super::{GameSession, Grid, Kernel, Mask, Outcome, ReplayAction, TopologyKind};
+
+
+/// Port used by the races when none is given
+pub const RACE_PORT: u16 = 7878;
+/// Time given to the host to answer the client
+pub const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+/// Widest and tallest board a client accepts to race on
+pub const MAX_RACE_SIDE: u16 = 500;
+
+/// Everything needed to generate the board of a race
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RaceStart {
+	pub seed: u64,
+	pub width: u16,
+	pub height: u16,
+	pub mines: u16,
+	pub lives: u8,
+	pub topology: TopologyKind,
+	pub kernel: Kernel,
+	pub mines_per_cell: u8,
+	/// Shape of the board, None for the whole rectangle
+	pub mask: Option<Mask>,
+}
+
+impl RaceStart {
+	/// Checks that the board can be generated, since it comes from the network
+	pub fn check(&self) -> Result<(), String> {
+		if !(2..=MAX_RACE_SIDE).contains(&self.width) || !(2..=MAX_RACE_SIDE).contains(&self.height) {
+			return Err(format!("Invalid race board size {}x{}, at most {}x{}", self.width, self.height, MAX_RACE_SIDE, MAX_RACE_SIDE));
+		}
+		if self.mines_per_cell == 0 {
+			return Err("A race board needs at least one mine per cell".to_string());
+		}
+		self.topology.check_kernel(&self.kernel)?;
+		let cells = match &self.mask {
+			Some(mask) if (mask.width(), mask.height()) != (self.width, self.height) => {
+				return Err(format!("The mask is {}x{} but the race board is {}x{}", mask.width(), mask.height(), self.width, self.height));
+			},
+			Some(mask) => mask.enabled_count(),
+			None => self.width as u32 * self.height as u32,
+		};
+		// The first cell revealed is always safe
+		let capacity = cells.saturating_sub(1) * self.mines_per_cell as u32;
+		if self.mines == 0 || self.mines as u32 > capacity {
+			return Err(format!("Invalid race board: {} mines for a room of {}", self.mines, capacity));
+		}
+		Ok(())
+	}
+
+	/// Generates the board of the race, the same one on both sides
+	pub fn grid(&self) -> Grid {
+		let mut grid = Grid::empty(self.width, self.height)
+			.with_topology(self.topology)
+			.with_kernel(self.kernel.clone())
+			.with_mines_per_cell(self.mines_per_cell);
+		if let Some(mask) = &self.mask {
+			grid = grid.with_mask(mask.clone());
+		}
+		grid.set_bombs_with_rng(self.mines, &mut StdRng::seed_from_u64(self.seed));
+		grid
+	}
+
+	/// Starts a game on the board of the race
+	pub fn session(&self) -> GameSession {
+		GameSession::new(self.grid()).with_lives(self.lives)
+	}
+}
+
+/// How far a player is in the race
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct RaceProgress {
+	pub revealed: u32,
+	pub safe_cells: u32,
+	pub mines_hit: u32,
+	pub outcome: Outcome,
+}
+
+impl RaceProgress {
+	/// Returns the progress of a player's game
+	pub fn of(session: &GameSession) -> Self {
+		let safe_cells = session.grid().cells().len() as u32 - session.grid().bomb_cell_count();
+		// The last bomb ends the game without being flagged, unless the game was lost some other way
+		let last_bomb = session.outcome() == Outcome::Defeat && session.lives() == 0;
+		Self {
+			revealed: safe_cells - session.covered_safe_cells(),
+			safe_cells,
+			mines_hit: session.exploded().len() as u32 + last_bomb as u32,
+			outcome: session.outcome(),
+		}
+	}
+
+	/// Returns the percentage of safe cells revealed
+	pub fn percent(&self) -> u32 {
+		if self.safe_cells == 0 { return 0 }
+		self.revealed * 100 / self.safe_cells
+	}
+}
+
+/// The result of a race, for one of the players
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RaceResult {
+	Won,
+	Lost,
+	/// Both players blew up
+	Draw,
+	/// The connection was lost before the end of the race
+	OpponentLeft,
+}
+
+impl RaceResult {
+	/// Returns the result of the other player
+	pub fn opposite(&self) -> Self {
+		match self {
+			RaceResult::Won => RaceResult::Lost,
+			RaceResult::Lost => RaceResult::Won,
+			RaceResult::Draw => RaceResult::Draw,
+			RaceResult::OpponentLeft => RaceResult::OpponentLeft,
+		}
+	}
+
+	/// Decides the result of the race for the host, None while it goes on
+	///
+	/// The first player to clear the board wins, a player who blew up can only hope for a draw
+	pub fn decide(host: &RaceProgress, client: &RaceProgress) -> Option<Self> {
+		match (host.outcome, client.outcome) {
+			(Outcome::Victory, _) => Some(RaceResult::Won),
+			(_, Outcome::Victory) => Some(RaceResult::Lost),
+			(Outcome::Defeat, Outcome::Defeat) => Some(RaceResult::Draw),
+			_ => None,
+		}
+	}
+}
+
+/// A message sent between the players
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RaceMessage {
+	/// Sent by the host when the client connects, the race starts on the board of the seed
+	Start(RaceStart),
+	/// An action of the client, replayed by the host
+	Action(ReplayAction, Coordinates),
+	/// The progress of the host, shown to the client
+	Progress(RaceProgress),
+	/// The result of the race for the client, decided by the host
+	Result(RaceResult),
+}
+
+/// Formats a message as a single line, like `reveal 3 4` or `progress 12 71 0 playing`
+///
+/// The board of the race is sent like `start 42 9 9 10 1 hex 1 none moore`, the kernel coming last since a custom kernel
+/// lists its offsets. A mask is drawn like in its file, with its rows separated by `/`.
+impl fmt::Display for RaceMessage {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RaceMessage::Start(start) => {
+				write!(f, "start {} {} {} {} {} {} {} ",
+					start.seed, start.width, start.height, start.mines, start.lives, start.topology, start.mines_per_cell)?;
+				match &start.mask {
+					Some(mask) => {
+						let rows: Vec<String> = (0..mask.height()).rev()
+							.map(|y| (0..mask.width())
+								.map(|x| if mask.is_enabled(Coordinates::new(x, y)) { 'X' } else { '.' })
+								.collect())
+							.collect();
+						write!(f, "{}", rows.join("/"))?;
+					},
+					None => write!(f, "none")?,
+				}
+				write!(f, " {}", start.kernel)
+			},
+			RaceMessage::Action(ReplayAction::Reveal, coord) => write!(f, "reveal {} {}", coord.x, coord.y),
+			RaceMessage::Action(ReplayAction::Flag, coord) => write!(f, "flag {} {}", coord.x, coord.y),
+			RaceMessage::Progress(progress) => {
+				let outcome = match progress.outcome {
+					Outcome::Playing => "playing",
+					Outcome::Victory => "victory",
+					Outcome::Defeat => "defeat",
+				};
+				write!(f, "progress {} {} {} {}", progress.revealed, progress.safe_cells, progress.mines_hit, outcome)
+			},
+			RaceMessage::Result(result) => write!(f, "result {}", match result {
+				RaceResult::Won => "won",
+				RaceResult::Lost => "lost",
+				RaceResult::Draw => "draw",
+				RaceResult::OpponentLeft => "left",
+			}),
+		}
+	}
+}
+
+impl FromStr for RaceMessage {
+	type Err = String;
+
+	fn from_str(line: &str) -> Result<Self, Self::Err> {
+		let mut words = line.split_whitespace();
+		let kind = words.next().ok_or_else(|| "Empty message".to_string())?;
+		let args: Vec<&str> = words.collect();
+		fn number<T: FromStr>(args: &[&str], index: usize) -> Result<T, String> {
+			let arg = args.get(index).ok_or_else(|| format!("Missing argument {}", index + 1))?;
+			arg.parse().map_err(|_| format!("Invalid number: {}", arg))
+		}
+		let coordinates = |args: &[&str]| -> Result<Coordinates, String> {
+			Ok(Coordinates::new(number(args, 0)?, number(args, 1)?))
+		};
+
+		match kind {
+			"start" => {
+				let start = RaceStart {
+					seed: number(&args, 0)?,
+					width: number(&args, 1)?,
+					height: number(&args, 2)?,
+					mines: number(&args, 3)?,
+					lives: number(&args, 4)?,
+					topology: args.get(5).ok_or("Missing topology")?.parse()?,
+					mines_per_cell: number(&args, 6)?,
+					mask: match *args.get(7).ok_or("Missing mask")? {
+						"none" => None,
+						drawing => Some(Mask::from_drawing(&drawing.replace('/', "\n"))?),
+					},
+					kernel: args.get(8..).filter(|kernel| !kernel.is_empty()).ok_or("Missing kernel")?.join(" ").parse()?,
+				};
+				// The board comes from the network, it must be checked before being generated
+				start.check()?;
+				Ok(RaceMessage::Start(start))
+			},
+			"reveal" => Ok(RaceMessage::Action(ReplayAction::Reveal, coordinates(&args)?)),
+			"flag" => Ok(RaceMessage::Action(ReplayAction::Flag, coordinates(&args)?)),
+			"progress" => Ok(RaceMessage::Progress(RaceProgress {
+				revealed: number(&args, 0)?,
+				safe_cells: number(&args, 1)?,
+				mines_hit: number(&args, 2)?,
+				outcome: match args.get(3) {
+					Some(&"playing") => Outcome::Playing,
+					Some(&"victory") => Outcome::Victory,
+					Some(&"defeat") => Outcome::Defeat,
+					other => return Err(format!("Invalid outcome: {:?}", other)),
+				},
+			})),
+			"result" => Ok(RaceMessage::Result(match args.first() {
+				Some(&"won") => RaceResult::Won,
+				Some(&"lost") => RaceResult::Lost,
+				Some(&"draw") => RaceResult::Draw,
+				Some(&"left") => RaceResult::OpponentLeft,
+				other => return Err(format!("Invalid result: {:?}", other)),
+			})),
+			other => Err(format!("Unknown message: {}", other)),
+		}
+	}
+}
+
+/// What happened on the connection since it was last polled
+#[derive(Debug, Clone, PartialEq)]
+pub enum RaceEvent {
+	Connected,
+	Message(RaceMessage),
+	Disconnected,
+}
+
+/// What the background thread sends to the game
+enum Incoming {
+	Connected(TcpStream),
+	Message(RaceMessage),
+	Closed,
+}
+
+/// The connection to the other player
+///
+/// Reading is done by a background thread, so polling the connection never blocks the game
+pub struct RaceConnection {
+	// Behind a mutex so the connection can be shared between threads, like any bevy resource
+	incoming: Mutex<Receiver<Incoming>>,
+	stream: Option<TcpStream>,
+}
+
+impl RaceConnection {
+	/// Waits for a client on the given port, in the background
+	pub fn host(port: u16) -> Result<Self, String> {
+		let listener = TcpListener::bind(("0.0.0.0", port))
+			.map_err(|err| format!("Cannot listen on port {}: {}", port, err))?;
+		let (sender, incoming) = channel();
+		thread::spawn(move || {
+			match listener.accept() {
+				Ok((stream, _)) => read_messages(stream, sender),
+				Err(_) => { let _ = sender.send(Incoming::Closed); },
+			}
+		});
+		Ok(Self { incoming: Mutex::new(incoming), stream: None })
+	}
+
+	/// Connects to a host, like `192.168.1.12:7878`, in the background
+	///
+	/// The connection is lost if the host does not answer within the `JOIN_TIMEOUT`
+	pub fn join(address: &str) -> Self {
+		let (sender, incoming) = channel();
+		let address = address.to_string();
+		thread::spawn(move || match connect(&address) {
+			Ok(stream) => read_messages(stream, sender),
+			Err(err) => {
+				warn!("Cannot connect to {}: {}", address, err);
+				let _ = sender.send(Incoming::Closed);
+			},
+		});
+		Self { incoming: Mutex::new(incoming), stream: None }
+	}
+
+	/// Sends a message to the other player, returns false if it could not be sent
+	pub fn send(&mut self, message: RaceMessage) -> bool {
+		let Some(stream) = &mut self.stream else { return false };
+		writeln!(stream, "{}", message).is_ok()
+	}
+
+	/// Returns everything that happened since the last call, without blocking
+	pub fn poll(&mut self) -> Vec<RaceEvent> {
+		let mut events = Vec::new();
+		let Ok(receiver) = self.incoming.get_mut() else { return events };
+		while let Ok(incoming) = receiver.try_recv() {
+			events.push(match incoming {
+				Incoming::Connected(stream) => {
+					self.stream = Some(stream);
+					RaceEvent::Connected
+				},
+				Incoming::Message(message) => RaceEvent::Message(message),
+				Incoming::Closed => {
+					self.stream = None;
+					RaceEvent::Disconnected
+				},
+			});
+		}
+		events
+	}
+
+	/// Returns true once the other player is connected, until the connection is lost
+	pub fn is_connected(&self) -> bool {
+		self.stream.is_some()
+	}
+}
+
+/// Closes the connection, so the other player knows the race is over and the background thread stops
+impl Drop for RaceConnection {
+	fn drop(&mut self) {
+		if let Some(stream) = &self.stream {
+			let _ = stream.shutdown(Shutdown::Both);
+		}
+	}
+}
+
+/// Connects to the first address of the host answering in time
+fn connect(address: &str) -> io::Result<TcpStream> {
+	let mut error = io::Error::new(ErrorKind::InvalidInput, "no address found");
+	for address in address.to_socket_addrs()? {
+		match TcpStream::connect_timeout(&address, JOIN_TIMEOUT) {
+			Ok(stream) => return Ok(stream),
+			Err(err) => error = err,
+		}
+	}
+	Err(error)
+}
+
+/// Reads the messages of the other player until the connection is closed, ignoring the invalid ones
+///
+/// An invalid board closes the connection, since the race cannot start without it
+fn read_messages(stream: TcpStream, sender: Sender<Incoming>) {
+	let _ = stream.set_nodelay(true);
+	let Ok(writer) = stream.try_clone() else {
+		let _ = sender.send(Incoming::Closed);
+		return
+	};
+	if sender.send(Incoming::Connected(writer)).is_err() { return }
+	for line in BufReader::new(stream).lines() {
+		let Ok(line) = line else { break };
+		match line.parse() {
+			Ok(message) => if sender.send(Incoming::Message(message)).is_err() { return },
+			Err(err) if line.starts_with("start") => {
+				warn!("Leaving the race, the board of the host is invalid: {}", err);
+				break
+			},
+			Err(err) => warn!("Ignoring an invalid race message: {}", err),
+		}
+	}
+	let _ = sender.send(Incoming::Closed);
+}
+
+
+#[cfg(test)]
+mod tests {
+	use std::time::Instant;
+	use super::*;
+
+	/// Polls the connection until something happens, for a few seconds at most
+	fn wait(connection: &mut RaceConnection) -> Vec<RaceEvent> {
+		let deadline = Instant::now() + Duration::from_secs(5);
+		while Instant::now() < deadline {
+			let events = connection.poll();
+			if !events.is_empty() { return events }
+			thread::sleep(Duration::from_millis(10));
+		}
+		Vec::new()
+	}
+
+	#[test]
+	fn race_over_localhost() {
+		// A free port, found by the system
+		let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+		let mut host = RaceConnection::host(port).unwrap();
+		let mut client = RaceConnection::join(&format!("127.0.0.1:{}", port));
+		assert_eq!(wait(&mut client), vec![RaceEvent::Connected]);
+		assert_eq!(wait(&mut host), vec![RaceEvent::Connected]);
+
+		let start = RaceStart {
+			seed: 42, width: 5, height: 4, mines: 6, lives: 2,
+			topology: TopologyKind::Torus,
+			kernel: Kernel::Custom(vec![(1, 2), (-1, 2)]),
+			mines_per_cell: 2,
+			mask: Some(Mask::from_drawing(".XXX.\nXXXXX\nXXXXX\n.XXX.").unwrap()),
+		};
+		assert!(host.send(RaceMessage::Start(start.clone())));
+		assert_eq!(wait(&mut client), vec![RaceEvent::Message(RaceMessage::Start(start.clone()))]);
+		// Both players play on the shape of the host, not on a square board
+		let grid = start.grid();
+		assert_eq!((grid.topology(), grid.kernel(), grid.mask(), grid.mines_per_cell()), (TopologyKind::Torus, &start.kernel, start.mask.as_ref(), 2));
+
+		let action = RaceMessage::Action(ReplayAction::Reveal, Coordinates::new(2, 1));
+		assert!(client.send(action.clone()));
+		assert_eq!(wait(&mut host), vec![RaceEvent::Message(action)]);
+		drop(client);
+		assert_eq!(wait(&mut host), vec![RaceEvent::Disconnected]);
+	}
+
+	#[test]
+	fn malformed_boards_are_rejected() {
+		assert!("start 42 9 9 10 1 square 1 none moore".parse::<RaceMessage>().is_ok());
+		for line in [
+			// More mines than the room left by the first cell
+			"start 42 3 3 9 1 square 1 none moore",
+			"start 42 3 3 0 1 square 1 none moore",
+			// A mask of another size than the board
+			"start 42 9 9 10 1 square 1 XX/XX moore",
+			"start 42 60000 60000 10 1 square 1 none moore",
+			"start 42 9 9 10 1 square 0 none moore",
+			"start 42 9 9 10 1 hex 1 none knight",
+			"start 42 9 9",
+		] {
+			assert!(line.parse::<RaceMessage>().is_err(), "{}", line);
+		}
+	}
+
+	#[test]
+	fn an_invalid_board_closes_the_connection() {
+		let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+		let mut host = RaceConnection::host(port).unwrap();
+		let mut client = RaceConnection::join(&format!("127.0.0.1:{}", port));
+		assert_eq!(wait(&mut client), vec![RaceEvent::Connected]);
+		assert_eq!(wait(&mut host), vec![RaceEvent::Connected]);
+
+		let start = RaceStart {
+			seed: 42, width: 3, height: 3, mines: 50, lives: 1,
+			topology: TopologyKind::Square,
+			kernel: Kernel::Moore,
+			mines_per_cell: 1,
+			mask: None,
+		};
+		assert!(host.send(RaceMessage::Start(start)));
+		assert_eq!(wait(&mut client), vec![RaceEvent::Disconnected]);
+		assert_eq!(wait(&mut host), vec![RaceEvent::Disconnected]);
+	}
+
+	#[test]
+	fn joining_nobody_disconnects() {
+		let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+		let mut client = RaceConnection::join(&format!("127.0.0.1:{}", port));
+		assert_eq!(wait(&mut client), vec![RaceEvent::Disconnected]);
+	}
+}
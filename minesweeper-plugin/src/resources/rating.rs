@@ -0,0 +1,233 @@
+// This module contains the rating of the difficulty of a board, independently of bevy's ECS
+// A board is rated by its 3BV, its openings, its islands and the hardest technique the solver needs to clear it.
+// The rating can filter the generated boards, so the ranked games are played on boards of comparable difficulty.
+
+use core::fmt;
+use std::time::{Duration, Instant};
+use bevy::reflect::Reflect;
+use rand::Rng;
+
+use crate::components::Coordinates;
+use super::{CellState, GameSession, Grid, Outcome, Solver, Technique, ThreeBv, is_solvable_from, no_guess_candidate};
+
+
+/// How many grids are generated before giving up on finding one that passes a filter
+pub const FILTER_ATTEMPTS: u32 = 100_000;
+/// How long the grids may be generated before giving up on the filter and the no-guess option
+pub const GENERATION_BUDGET: Duration = Duration::from_secs(10);
+
+
+/// The difficulty of a board
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BoardRating {
+	/// The least number of clicks needed to clear the board
+	pub three_bv: u32,
+	pub openings: u32,
+	/// The groups of touching numbers that no opening reveals
+	pub islands: u32,
+	/// The hardest technique needed to clear the board, `Guess` if the solver gets stuck somewhere
+	pub hardest: Technique,
+}
+
+impl BoardRating {
+	/// Rates a board, played from the given starting cell
+	///
+	/// Without a starting cell, the game starts in an opening like a player would hope to,
+	/// and a board without any opening needs a guess from the first click.
+	pub fn new(grid: &Grid, start: Option<Coordinates>) -> Self {
+		let three_bv = ThreeBv::new(grid);
+		Self {
+			three_bv: three_bv.total(),
+			openings: three_bv.openings().len() as u32,
+			islands: three_bv.islands(grid),
+			hardest: hardest_technique(grid, &three_bv, start),
+		}
+	}
+}
+
+/// Shows the rating like `3BV 120, 14 openings, 6 islands, hardest technique: subset`
+impl fmt::Display for BoardRating {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "3BV {}, {} openings, {} islands, hardest technique: {}",
+			self.three_bv, self.openings, self.islands, self.hardest.name())
+	}
+}
+
+/// Solves the board with the simplest techniques possible, returning the hardest one it had to use
+///
+/// When the solver is stuck, the guess is always lucky: the next opening, or any safe cell, is revealed
+fn hardest_technique(grid: &Grid, three_bv: &ThreeBv, start: Option<Coordinates>) -> Technique {
+	let mut session = GameSession::new(grid.clone());
+	let mut hardest = Technique::Single;
+	match start.or_else(|| three_bv.openings().first().copied()) {
+		Some(start) => { session.reveal(start); },
+		None => hardest = Technique::Guess,
+	}
+	let mut solver = Solver::new();
+	while session.outcome() == Outcome::Playing {
+		let deduced = Technique::LOGICAL.into_iter()
+			.map(|technique| (technique, solver.deduce_with(&session, technique)))
+			.find(|(_, safe)| !safe.is_empty());
+		let safe = match deduced {
+			Some((technique, safe)) => {
+				hardest = hardest.max(technique);
+				safe
+			},
+			None => {
+				hardest = Technique::Guess;
+				let covered = |coord: &Coordinates| session.state(*coord) == Some(CellState::Covered);
+				let guess = three_bv.openings().iter().copied().find(covered)
+					.or_else(|| grid.cells().into_iter().find(|coord| covered(coord) && !grid.is_bomb_at(*coord)));
+				match guess {
+					Some(guess) => vec![guess],
+					None => break,
+				}
+			},
+		};
+		for coord in safe {
+			session.reveal(coord);
+		}
+	}
+	hardest
+}
+
+
+/// The range of difficulty a generated board must be in
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Reflect)]
+pub struct BoardFilter {
+	pub min_three_bv: u32,
+	pub max_three_bv: u32,
+	/// The hardest technique a board may need, `Guess` accepting every board
+	pub hardest: Technique,
+}
+
+impl BoardFilter {
+	/// Accepts the boards whose 3BV is between `min` and `max`, both included
+	pub fn three_bv(min: u32, max: u32) -> Self {
+		Self { min_three_bv: min, max_three_bv: max, hardest: Technique::Guess }
+	}
+
+	/// Accepts the boards that need no technique harder than the given one, whatever their 3BV
+	pub fn hardest(hardest: Technique) -> Self {
+		Self { min_three_bv: 0, max_three_bv: u32::MAX, hardest }
+	}
+
+	/// Returns the same filter, only accepting the boards that need no technique harder than the given one
+	pub fn with_hardest(self, hardest: Technique) -> Self {
+		Self { hardest, ..self }
+	}
+
+	/// Returns true if the board, played from the given starting cell, passes the filter
+	///
+	/// The 3BV is checked first, since solving the board takes much longer
+	pub fn accepts(&self, grid: &Grid, start: Option<Coordinates>) -> bool {
+		let three_bv = ThreeBv::new(grid);
+		self.accepts_three_bv(&three_bv)
+			&& (self.hardest == Technique::Guess || hardest_technique(grid, &three_bv, start) <= self.hardest)
+	}
+
+	fn accepts_three_bv(&self, three_bv: &ThreeBv) -> bool {
+		(self.min_three_bv..=self.max_three_bv).contains(&three_bv.total())
+	}
+
+	/// Returns true if the filter checks the 3BV of the boards, a range only making sense for a given grid size
+	pub fn has_three_bv_range(&self) -> bool {
+		self.min_three_bv > 0 || self.max_three_bv < u32::MAX
+	}
+
+	/// Returns the name of the filter, added to the high-score buckets like `expert-3bv-150-180` or `expert-subset`
+	pub fn name(&self) -> String {
+		match (self.has_three_bv_range(), self.hardest) {
+			(false, hardest) => hardest.name().to_string(),
+			(true, Technique::Guess) => format!("3bv-{}-{}", self.min_three_bv, self.max_three_bv),
+			(true, hardest) => format!("3bv-{}-{}-{}", self.min_three_bv, self.max_three_bv, hardest.name()),
+		}
+	}
+}
+
+/// Generates boards until one passes the filter and, with `no_guess`, can be solved without guessing from its starting cell
+///
+/// The bombs are placed on copies of the empty grid given. Every board is solved at most once:
+/// the cheap 3BV check comes first, then the solver finds the hardest technique needed, which also tells if a guess is needed.
+/// Returns None if no board was found after `FILTER_ATTEMPTS` tries or once the time budget is spent.
+pub fn generate_filtered<R: Rng>(
+	empty: &Grid,
+	bomb_count: u16,
+	no_guess: bool,
+	filter: Option<&BoardFilter>,
+	rng: &mut R,
+	budget: Duration,
+) -> Option<(Grid, Option<Coordinates>)> {
+	let started = Instant::now();
+	for _ in 0..FILTER_ATTEMPTS {
+		if started.elapsed() > budget { break }
+		let (grid, start) = match no_guess {
+			true => match no_guess_candidate(empty, bomb_count, rng) {
+				Some((grid, start)) => (grid, Some(start)),
+				None => continue,
+			},
+			false => {
+				let mut grid = empty.clone();
+				grid.set_bombs_with_rng(bomb_count, rng);
+				(grid, None)
+			},
+		};
+		let accepted = match filter {
+			Some(filter) => {
+				let three_bv = ThreeBv::new(&grid);
+				filter.accepts_three_bv(&three_bv) && match filter.hardest {
+					// Without a technique to check, only the no-guess option needs the solver
+					Technique::Guess => start.map_or(true, |start| is_solvable_from(&grid, start)),
+					// A board needing a guess is rated `Guess`, harder than any technique of a filter
+					hardest => hardest_technique(&grid, &three_bv, start) <= hardest,
+				}
+			},
+			None => start.map_or(true, |start| is_solvable_from(&grid, start)),
+		};
+		if accepted {
+			return Some((grid, start));
+		}
+	}
+	None
+}
+
+
+#[cfg(test)]
+mod tests {
+	use rand::SeedableRng;
+	use rand::rngs::StdRng;
+	use super::*;
+
+	fn rating(text: &str) -> BoardRating {
+		BoardRating::new(&text.parse().unwrap(), Some(Coordinates::new(0, 0)))
+	}
+
+	#[test]
+	fn boards_are_rated_by_their_hardest_deduction() {
+		assert_eq!(rating(".**\n...\n...\n").hardest, Technique::Single);
+		assert_eq!(rating("*.*\n...\n...\n").hardest, Technique::Subset);
+		assert_eq!(rating("*.\n..\n..\n").hardest, Technique::Guess);
+		// Without a starting cell nor an opening, the first click is a guess
+		assert_eq!(BoardRating::new(&".*.\n*.*\n.*.\n".parse().unwrap(), None).hardest, Technique::Guess);
+	}
+
+	#[test]
+	fn the_filtered_boards_pass_their_filter() {
+		let mut rng = StdRng::seed_from_u64(1);
+		let filter = BoardFilter::hardest(Technique::Single);
+		let (grid, start) = generate_filtered(&Grid::empty(9, 9), 10, true, Some(&filter), &mut rng, GENERATION_BUDGET).unwrap();
+		assert!(start.is_some_and(|start| filter.accepts(&grid, Some(start))));
+		assert!(BoardRating::new(&grid, start).hardest <= Technique::Single);
+	}
+
+	#[test]
+	fn an_impossible_filter_gives_up_within_the_budget() {
+		let mut rng = StdRng::seed_from_u64(1);
+		// No 9x9 board with 10 mines needs a thousand clicks
+		let filter = BoardFilter::three_bv(1000, 2000);
+		let budget = Duration::from_millis(100);
+		let started = Instant::now();
+		assert!(generate_filtered(&Grid::empty(9, 9), 10, false, Some(&filter), &mut rng, budget).is_none());
+		assert!(started.elapsed() < budget * 10);
+	}
+}
@@ -0,0 +1,132 @@
+// This module contains the replays of the games, recording every action of the player with its time
+// A replay only needs the grid and the list of actions, the whole game is rebuilt by applying them to a `GameSession`
+
+use crate::components::Coordinates;
+use super::{CellState, GameSession, Grid};
+
+
+/// An action of the player on a cell
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReplayAction {
+	Reveal,
+	Flag,
+}
+
+/// A single action of the player, with the time in seconds since the start of the game
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ReplayEvent {
+	pub time: f32,
+	pub action: ReplayAction,
+	pub coordinates: Coordinates,
+}
+
+/// The recording of a whole game
+///
+/// Only the actions that changed the game are recorded, clicks on revealed cells are not
+#[derive(Clone)]
+pub struct Replay {
+	pub grid: Grid,
+	/// Lives of the player at the start of the game, 1 for the classic rules
+	pub lives: u8,
+	pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+	/// Starts recording a game on the given grid
+	pub fn new(grid: Grid) -> Self {
+		Self { grid, lives: 1, events: Vec::new() }
+	}
+
+	/// Starts recording a game that may have already started, like a loaded board or a no-guess grid
+	///
+	/// The current state of the session is recorded as actions happening at the start of the game
+	pub fn from_session(session: &GameSession) -> Self {
+		let mut replay = Self::new(session.grid().clone());
+		replay.lives = session.lives();
+		for y in 0..session.grid().height() {
+			for x in 0..session.grid().width() {
+				let coord = Coordinates::new(x, y);
+				match session.state(coord) {
+					Some(CellState::Revealed) => replay.record(0., ReplayAction::Reveal, coord),
+					Some(CellState::Flagged) => for _ in 0..session.flags_at(coord) {
+						replay.record(0., ReplayAction::Flag, coord)
+					},
+					_ => {}
+				}
+			}
+		}
+		replay
+	}
+
+	/// Records an action, the actions must be recorded in chronological order
+	pub fn record(&mut self, time: f32, action: ReplayAction, coordinates: Coordinates) {
+		self.events.push(ReplayEvent { time, action, coordinates });
+	}
+
+	/// Time of the last action of the replay, in seconds
+	pub fn duration(&self) -> f32 {
+		self.events.last().map_or(0., |ev| ev.time)
+	}
+
+	/// Rebuilds the game as it was at a given time of the replay
+	pub fn session_at(&self, time: f32) -> GameSession {
+		let mut session = GameSession::new(self.grid.clone()).with_lives(self.lives);
+		for ev in self.events.iter().take_while(|ev| ev.time <= time) {
+			match ev.action {
+				ReplayAction::Reveal => { session.reveal(ev.coordinates); },
+				ReplayAction::Flag => { session.toggle_flag(ev.coordinates); },
+			}
+		}
+		session
+	}
+
+	/// Starts the game over on the same grid, from its state at the start of the game
+	///
+	/// With `first_click`, the first cell the player revealed is revealed again, unless it was a mine
+	pub fn restart(&self, first_click: bool) -> GameSession {
+		let mut session = self.session_at(0.);
+		let first_reveal = self.events.iter()
+			.find(|ev| ev.time > 0. && ev.action == ReplayAction::Reveal)
+			.filter(|ev| !self.grid.is_bomb_at(ev.coordinates));
+		if let (true, Some(ev)) = (first_click, first_reveal) {
+			session.reveal(ev.coordinates);
+		}
+		session
+	}
+}
+
+
+/// Plays a replay back, handing out its actions as the time goes
+#[derive(Default)]
+pub struct ReplayPlayback {
+	replay: Option<Replay>,
+	next_event: usize,
+}
+
+impl ReplayPlayback {
+	/// Starts playing a replay from its beginning
+	pub fn play(replay: Replay) -> Self {
+		Self { replay: Some(replay), next_event: 0 }
+	}
+
+	/// Returns true if a replay was loaded, even after all of its actions were played
+	pub fn has_replay(&self) -> bool {
+		self.replay.is_some()
+	}
+
+	/// Returns true while some actions of the replay have not been played yet
+	pub fn is_playing(&self) -> bool {
+		self.replay.as_ref().is_some_and(|replay| self.next_event < replay.events.len())
+	}
+
+	/// Returns every action happening before the given time that was not played yet
+	pub fn advance(&mut self, time: f32) -> Vec<ReplayEvent> {
+		let Some(replay) = &self.replay else { return Vec::new() };
+		let due: Vec<ReplayEvent> = replay.events[self.next_event..].iter()
+			.take_while(|ev| ev.time <= time)
+			.copied()
+			.collect();
+		self.next_event += due.len();
+		due
+	}
+}
@@ -0,0 +1,216 @@
+// This module contains the settings chosen in the settings menu, saved in a small text file next to the high scores
+// The options given on the command line are not saved, they only last for the game they were given to
+// The difficulty chosen in the menu is saved as well, except the shape of a mask which only comes from the command line
+
+use core::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::config::{AudioSettings, DifficultySettings, GameConfig};
+use super::{BoardFilter, Technique};
+
+
+/// File where the settings are saved, next to the high scores
+pub const SETTINGS_FILE: &str = "settings.txt";
+
+
+/// The settings kept from one game to the next
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SavedSettings {
+	pub audio: AudioSettings,
+	pub reduce_motion: bool,
+	/// The difficulty chosen in the settings menu, None until one is chosen
+	pub difficulty: Option<DifficultySettings>,
+}
+
+impl SavedSettings {
+	/// Takes the settings to save from the config
+	///
+	/// The difficulty is only taken when it was chosen in the menu, on a board without mask
+	pub fn from_config(cfg: &GameConfig) -> Self {
+		let difficulty = (!cfg.difficulty_given && cfg.difficulty.mask.is_none()).then(|| cfg.difficulty.clone());
+		Self { audio: cfg.audio, reduce_motion: cfg.ui_style.reduce_motion, difficulty }
+	}
+
+	/// Applies the saved settings to the config
+	///
+	/// The command line can mute the game and reduce the motion for a single game, so it turns them on but never off
+	pub fn apply(&self, cfg: &mut GameConfig) {
+		cfg.audio = AudioSettings { muted: self.audio.muted || cfg.audio.muted, ..self.audio };
+		cfg.ui_style.reduce_motion |= self.reduce_motion;
+		// A difficulty given on the command line is played instead of the saved one
+		if let Some(difficulty) = self.difficulty.as_ref().filter(|_| !cfg.difficulty_given) {
+			cfg.difficulty = difficulty.clone();
+		}
+	}
+
+	/// Loads the settings from a file, see `SavedSettings::from_str` for the format
+	///
+	/// A missing file gives the default settings, since none were changed yet
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		match fs::read_to_string(path) {
+			Ok(text) => text.parse(),
+			Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+			Err(err) => Err(format!("Cannot read settings file {}: {}", path.display(), err)),
+		}
+	}
+
+	/// Saves the settings to a file
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+		let path = path.as_ref();
+		fs::write(path, self.to_string())
+			.map_err(|err| format!("Cannot write settings file {}: {}", path.display(), err))
+	}
+}
+
+/// Parses the settings from text
+///
+/// Every line contains a setting and its value, like `master_volume = 0.8` or `muted = true`.
+/// The volumes go from 0 to 1, the settings missing from the text keep their default value.
+/// The difficulty is saved like `width = 16`, `topology = hex` or `time_limit = none`, any of its settings
+/// starting from the intermediate difficulty.
+/// Empty lines and lines starting with `#` are ignored.
+impl FromStr for SavedSettings {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut settings = SavedSettings::default();
+		let mut difficulty = None;
+		for (number, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') { continue }
+			let (key, value) = line.split_once('=')
+				.ok_or_else(|| format!("Line {}: expected `setting = value`", number + 1))?;
+			let value = value.trim();
+			let volume = || value.parse::<f32>().ok()
+				.filter(|volume| (0. ..=1.).contains(volume))
+				.ok_or_else(|| format!("Line {}: invalid volume {}, expected a number from 0 to 1", number + 1, value));
+			let switch = || value.parse::<bool>()
+				.map_err(|_| format!("Line {}: invalid value {}, expected true or false", number + 1, value));
+			match key.trim() {
+				"master_volume" => settings.audio.master = volume()?,
+				"effects_volume" => settings.audio.effects = volume()?,
+				"muted" => settings.audio.muted = switch()?,
+				"reduce_motion" => settings.reduce_motion = switch()?,
+				key => set_difficulty(difficulty.get_or_insert_with(DifficultySettings::default), key, value)
+					.map_err(|err| format!("Line {}: {}", number + 1, err))?,
+			}
+		}
+		// The first cell revealed is always safe, like on the command line
+		if let Some(difficulty) = &difficulty {
+			if difficulty.grid_size.x < 2 || difficulty.grid_size.y < 2 {
+				return Err(format!("The saved grid must be at least 2x2, got {}x{}", difficulty.grid_size.x, difficulty.grid_size.y));
+			}
+			difficulty.topology.check_kernel(&difficulty.kernel)?;
+			let cells = difficulty.grid_size.x as u32 * difficulty.grid_size.y as u32;
+			if difficulty.bomb_count as u32 > (cells - 1) * difficulty.mines_per_cell as u32 {
+				return Err(format!("Too many mines for the saved difficulty: {} on a {}x{} grid",
+					difficulty.bomb_count, difficulty.grid_size.x, difficulty.grid_size.y));
+			}
+		}
+		settings.difficulty = difficulty;
+		Ok(settings)
+	}
+}
+
+/// Sets a setting of the saved difficulty
+fn set_difficulty(difficulty: &mut DifficultySettings, key: &str, value: &str) -> Result<(), String> {
+	let number = |min: u32| value.parse::<u32>().ok()
+		.filter(|number| *number >= min)
+		.ok_or_else(|| format!("invalid value {}, expected a whole number from {}", value, min));
+	let too_big = |_| format!("value {} is too big", value);
+	let hardest = difficulty.filter.map_or(Technique::Guess, |filter| filter.hardest);
+	match key {
+		"width" => difficulty.grid_size.x = u16::try_from(number(2)?).map_err(too_big)?,
+		"height" => difficulty.grid_size.y = u16::try_from(number(2)?).map_err(too_big)?,
+		"mines" => difficulty.bomb_count = u16::try_from(number(1)?).map_err(too_big)?,
+		"topology" => difficulty.topology = value.parse()?,
+		"kernel" => difficulty.kernel = value.parse()?,
+		"mines_per_cell" => difficulty.mines_per_cell = u8::try_from(number(1)?).map_err(too_big)?,
+		"lives" => difficulty.lives = u8::try_from(number(1)?).map_err(too_big)?,
+		"time_limit" => difficulty.time_limit = match value {
+			"none" => None,
+			_ => Some(number(1)?),
+		},
+		"time_bonus" => difficulty.time_bonus = value.parse::<f32>().ok()
+			.filter(|bonus| *bonus >= 0.)
+			.ok_or_else(|| format!("invalid time bonus {}, expected a positive number", value))?,
+		"three_bv" => {
+			let filter = match value {
+				"any" => BoardFilter::hardest(hardest),
+				range => range.split_once('-')
+					.and_then(|(min, max)| Some(BoardFilter::three_bv(min.parse().ok()?, max.parse().ok()?)))
+					.filter(|filter| filter.min_three_bv <= filter.max_three_bv)
+					.ok_or_else(|| format!("invalid 3BV range {}, expected a range like 150-180 or any", range))?
+					.with_hardest(hardest),
+			};
+			difficulty.filter = (filter.has_three_bv_range() || hardest != Technique::Guess).then_some(filter);
+		},
+		"hardest" => {
+			let hardest = [Technique::Single, Technique::Subset, Technique::MineCount, Technique::Guess]
+				.into_iter()
+				.find(|technique| technique.name() == value)
+				.ok_or_else(|| format!("unknown technique {}", value))?;
+			*difficulty = difficulty.clone().with_hardest_technique(hardest);
+		},
+		key => return Err(format!("unknown setting {}", key)),
+	}
+	Ok(())
+}
+
+impl fmt::Display for SavedSettings {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "# Settings of the settings menu")?;
+		writeln!(f, "master_volume = {}", self.audio.master)?;
+		writeln!(f, "effects_volume = {}", self.audio.effects)?;
+		writeln!(f, "muted = {}", self.audio.muted)?;
+		writeln!(f, "reduce_motion = {}", self.reduce_motion)?;
+		let Some(difficulty) = &self.difficulty else { return Ok(()) };
+		writeln!(f)?;
+		writeln!(f, "# Difficulty of the settings menu")?;
+		writeln!(f, "width = {}", difficulty.grid_size.x)?;
+		writeln!(f, "height = {}", difficulty.grid_size.y)?;
+		writeln!(f, "mines = {}", difficulty.bomb_count)?;
+		writeln!(f, "topology = {}", difficulty.topology)?;
+		writeln!(f, "kernel = {}", difficulty.kernel)?;
+		writeln!(f, "mines_per_cell = {}", difficulty.mines_per_cell)?;
+		writeln!(f, "lives = {}", difficulty.lives)?;
+		match difficulty.time_limit {
+			Some(secs) => writeln!(f, "time_limit = {}", secs)?,
+			None => writeln!(f, "time_limit = none")?,
+		}
+		writeln!(f, "time_bonus = {}", difficulty.time_bonus)?;
+		match difficulty.filter {
+			Some(filter) if filter.has_three_bv_range() =>
+				writeln!(f, "three_bv = {}-{}", filter.min_three_bv, filter.max_three_bv)?,
+			_ => writeln!(f, "three_bv = any")?,
+		}
+		writeln!(f, "hardest = {}", difficulty.filter.map_or(Technique::Guess, |filter| filter.hardest).name())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::resources::{Kernel, TopologyKind};
+
+	#[test]
+	fn the_difficulty_of_the_menu_is_saved() {
+		let difficulty = DifficultySettings::expert()
+			.with_topology(TopologyKind::Torus)
+			.with_kernel(Kernel::Knight)
+			.with_lives(3)
+			.with_countdown(Some(400), 1.)
+			.with_hardest_technique(Technique::Subset);
+		let settings = SavedSettings { difficulty: Some(difficulty), ..Default::default() };
+		assert_eq!(settings.to_string().parse::<SavedSettings>(), Ok(settings));
+		// The files saved without a difficulty leave the difficulty of the game as it is
+		let old = "master_volume = 0.5\nmuted = true\n".parse::<SavedSettings>().unwrap();
+		assert_eq!(old.difficulty, None);
+		assert!("width = 3\nheight = 3\nmines = 9".parse::<SavedSettings>().is_err());
+	}
+}
@@ -0,0 +1,496 @@
+// This module contains the rules of a minesweeper game, independently of bevy's ECS
+// The GameSession is the single source of truth about a game, the bevy systems only mirror its state
+
+use std::collections::VecDeque;
+
+use crate::components::Coordinates;
+use super::{Cell, Grid};
+
+
+/// The state of a single cell, as seen by the player
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum CellState {
+	#[default]
+	Covered,
+	Flagged,
+	Revealed,
+}
+
+/// The outcome of a game
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+	#[default]
+	Playing,
+	Victory,
+	Defeat,
+}
+
+/// What happened after trying to reveal a cell
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RevealOutcome {
+	/// Nothing happened: the cell is out of bounds, flagged, already revealed or the game is over
+	Ignored,
+	/// The cells contained in the vector were safe and are now revealed
+	Safe(Vec<Coordinates>),
+	/// The cell contained a bomb, the game is lost
+	Explosion(Coordinates),
+	/// The cell contained a bomb but the player had a life left, the bomb is now flagged
+	LifeLost(Coordinates),
+	/// The cell contained a bomb, claimed by the player who found it and flagged, in the Flags duel
+	Claimed(Coordinates),
+	/// The last safe cells were revealed, the game is won
+	Victory(Vec<Coordinates>),
+}
+
+/// What happened after trying to toggle a flag on a cell
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FlagOutcome {
+	/// Nothing happened: the cell is out of bounds, already revealed, a bomb that exploded or the game is over
+	Ignored,
+	Placed,
+	/// One more flag was added to a flagged cell, on grids allowing several mines per cell
+	Stacked(u8),
+	Removed,
+}
+
+
+/// A structure owning everything there is to know about a single game of minesweeper
+///
+/// It stores the grid, the state of every cell and the outcome of the game,
+/// every change to the game has to go through its methods so the rules can't be bypassed
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GameSession {
+	grid: Grid,
+	states: Vec<Vec<CellState>>,
+	/// Number of flags on every cell, 0 unless the cell is flagged
+	flags: Vec<Vec<u8>>,
+	outcome: Outcome,
+	covered_safe_cells: u32,
+	flag_count: u16,
+	/// Lives left, every bomb costs one and the game is lost with the last one
+	lives: u8,
+	/// The bombs that exploded without ending the game
+	exploded: Vec<Coordinates>,
+	/// The bombs are claimed instead of exploding, in the Flags duel
+	claim_mines: bool,
+}
+
+impl GameSession {
+	/// Starts a new game on the given grid, with every cell covered
+	pub fn new(grid: Grid) -> Self {
+		let states = (0..grid.height())
+			.map(|_| vec![CellState::Covered; grid.width() as usize])
+			.collect();
+		let flags = (0..grid.height())
+			.map(|_| vec![0; grid.width() as usize])
+			.collect();
+		let covered_safe_cells = grid.cells().len() as u32 - grid.bomb_cell_count();
+		Self {
+			grid,
+			states,
+			flags,
+			outcome: Outcome::Playing,
+			covered_safe_cells,
+			flag_count: 0,
+			lives: 1,
+			exploded: Vec::new(),
+			claim_mines: false,
+		}
+	}
+
+	/// Resumes a game on the given grid, with the state of every cell already known
+	///
+	/// The flags and the outcome are deduced from the states,
+	/// which are rejected if they do not have the same dimensions as the grid or if a hole of the grid is not covered
+	pub fn with_states(grid: Grid, states: Vec<Vec<CellState>>) -> Result<Self, String> {
+		let (width, height) = (grid.width() as usize, grid.height() as usize);
+		if states.len() != height || states.iter().any(|line| line.len() != width) {
+			return Err(format!("The states of the cells do not match the {}x{} grid", width, height));
+		}
+		let mut session = Self::new(grid);
+		for (y, line) in states.into_iter().enumerate() {
+			for (x, state) in line.into_iter().enumerate() {
+				let coord = Coordinates::new(x as u16, y as u16);
+				match state {
+					CellState::Covered => continue,
+					_ if !session.grid.contains(coord) => {
+						return Err(format!("The cell {} is a hole of the grid, it can only be covered", coord));
+					},
+					CellState::Flagged => {
+						session.flag_count += 1;
+						session.flags[y][x] = 1;
+					},
+					CellState::Revealed if session.grid.is_bomb_at(coord) => session.outcome = Outcome::Defeat,
+					CellState::Revealed => session.covered_safe_cells -= 1,
+				}
+				session.set_state(coord, state);
+			}
+		}
+		if session.outcome == Outcome::Playing && session.covered_safe_cells == 0 {
+			session.outcome = Outcome::Victory;
+		}
+		Ok(session)
+	}
+
+	/// Reveals the cell at coordinates
+	///
+	/// If the cell is empty, the whole connected opening is revealed at once,
+	/// using a breadth-first search over the neighbors of every empty cell
+	pub fn reveal(&mut self, coordinates: Coordinates) -> RevealOutcome {
+		if !self.can_reveal(coordinates) { return RevealOutcome::Ignored }
+
+		if self.grid.is_bomb_at(coordinates) {
+			if self.claim_mines {
+				self.flag_exploded(coordinates);
+				return RevealOutcome::Claimed(coordinates);
+			}
+			self.lives -= 1;
+			if self.lives > 0 {
+				self.flag_exploded(coordinates);
+				return RevealOutcome::LifeLost(coordinates);
+			}
+			self.set_state(coordinates, CellState::Revealed);
+			self.outcome = Outcome::Defeat;
+			return RevealOutcome::Explosion(coordinates);
+		}
+
+		let mut revealed = Vec::new();
+		let mut queue = VecDeque::from([coordinates]);
+		self.set_state(coordinates, CellState::Revealed);
+		while let Some(coord) = queue.pop_front() {
+			revealed.push(coord);
+			if self.grid.get(coord) != Some(Cell::Empty) { continue }
+			for neighbor in self.grid.get_neighbors(coord) {
+				// Flagged cells are left untouched, the player has to remove the flag first
+				if self.state(neighbor) == Some(CellState::Covered) {
+					self.set_state(neighbor, CellState::Revealed);
+					queue.push_back(neighbor);
+				}
+			}
+		}
+
+		self.covered_safe_cells -= revealed.len() as u32;
+		if self.covered_safe_cells == 0 {
+			self.outcome = Outcome::Victory;
+			return RevealOutcome::Victory(revealed);
+		}
+		RevealOutcome::Safe(revealed)
+	}
+
+	/// Flags a bomb that exploded without ending the game, so the game can go on and the flags cannot be removed
+	fn flag_exploded(&mut self, coordinates: Coordinates) {
+		let mines = self.grid.mines_at(coordinates);
+		self.set_state(coordinates, CellState::Flagged);
+		self.set_flags(coordinates, mines);
+		self.flag_count += mines as u16;
+		self.exploded.push(coordinates);
+	}
+
+	/// Plays by the rules of the Flags duel: every bomb revealed is claimed and flagged, the game never explodes
+	pub fn with_claimed_mines(mut self) -> Self {
+		self.claim_mines = true;
+		self
+	}
+
+	/// Gives the player several lives, every bomb but the last one only costing a life
+	pub fn with_lives(mut self, lives: u8) -> Self {
+		self.lives = lives.max(1);
+		self
+	}
+
+	/// Sets the number of flags on flagged cells, for the grids allowing several mines per cell
+	///
+	/// The cells that are not flagged are left untouched
+	pub fn with_flag_counts(mut self, counts: &[(Coordinates, u8)]) -> Self {
+		for &(coord, count) in counts {
+			if self.state(coord) != Some(CellState::Flagged) || count == 0 { continue }
+			let flags = &mut self.flags[coord.y as usize][coord.x as usize];
+			self.flag_count = self.flag_count - *flags as u16 + count as u16;
+			*flags = count;
+		}
+		self
+	}
+
+	/// Places a flag on a covered cell, or removes it if the cell is already flagged
+	///
+	/// On grids allowing several mines per cell, the flags of a cell are cycled: every call adds a flag,
+	/// until there are as many flags as the maximum number of mines and the next call removes them all
+	pub fn toggle_flag(&mut self, coordinates: Coordinates) -> FlagOutcome {
+		if !self.can_toggle_flag(coordinates) { return FlagOutcome::Ignored }
+		let flags = self.flags_at(coordinates);
+		match self.state(coordinates) {
+			Some(CellState::Covered) => {
+				self.set_state(coordinates, CellState::Flagged);
+				self.set_flags(coordinates, 1);
+				self.flag_count += 1;
+				FlagOutcome::Placed
+			},
+			Some(CellState::Flagged) if flags < self.grid.mines_per_cell() => {
+				self.set_flags(coordinates, flags + 1);
+				self.flag_count += 1;
+				FlagOutcome::Stacked(flags + 1)
+			},
+			Some(CellState::Flagged) => {
+				self.set_state(coordinates, CellState::Covered);
+				self.set_flags(coordinates, 0);
+				self.flag_count -= flags as u16;
+				FlagOutcome::Removed
+			},
+			_ => FlagOutcome::Ignored,
+		}
+	}
+
+	/// Returns true if revealing the cell would change the game
+	pub fn can_reveal(&self, coordinates: Coordinates) -> bool {
+		self.outcome == Outcome::Playing && self.state(coordinates) == Some(CellState::Covered)
+	}
+
+	/// Returns true if toggling the flag of the cell would change the game
+	pub fn can_toggle_flag(&self, coordinates: Coordinates) -> bool {
+		self.outcome == Outcome::Playing && !self.exploded.contains(&coordinates)
+			&& matches!(self.state(coordinates), Some(CellState::Covered | CellState::Flagged))
+	}
+
+	/// Returns the cells a chord on the cell reveals: the covered neighbors of a revealed number with as many flags around it
+	///
+	/// The flags are trusted, a wrong flag makes the chord reveal a bomb. The list is empty when the chord does nothing.
+	pub fn chord(&self, coordinates: Coordinates) -> Vec<Coordinates> {
+		if self.outcome != Outcome::Playing || self.state(coordinates) != Some(CellState::Revealed) { return Vec::new() }
+		let Some(Cell::BombNeighbor(count)) = self.grid.get(coordinates) else { return Vec::new() };
+		let flags: u16 = self.grid.get_neighbors(coordinates).map(|neighbor| self.flags_at(neighbor) as u16).sum();
+		if flags != count { return Vec::new() }
+		self.grid.get_neighbors(coordinates)
+			.filter(|neighbor| self.state(*neighbor) == Some(CellState::Covered))
+			.collect()
+	}
+
+	/// Ends the game as lost, when the player ran out of time
+	pub fn time_out(&mut self) {
+		if self.outcome == Outcome::Playing {
+			self.outcome = Outcome::Defeat;
+		}
+	}
+
+	/// Returns the state of the cell at coordinates, or None if the coordinates are out of the grid or a hole
+	pub fn state(&self, coordinates: Coordinates) -> Option<CellState> {
+		if !self.grid.contains(coordinates) { return None }
+		self.states
+			.get(coordinates.y as usize)
+			.and_then(|line| line.get(coordinates.x as usize))
+			.copied()
+	}
+
+	fn set_state(&mut self, coordinates: Coordinates, state: CellState) {
+		self.states[coordinates.y as usize][coordinates.x as usize] = state;
+	}
+
+	/// Returns the number of flags on the cell at coordinates, 0 if it is not flagged
+	pub fn flags_at(&self, coordinates: Coordinates) -> u8 {
+		if !self.grid.contains(coordinates) { return 0 }
+		self.flags[coordinates.y as usize][coordinates.x as usize]
+	}
+
+	fn set_flags(&mut self, coordinates: Coordinates, count: u8) {
+		self.flags[coordinates.y as usize][coordinates.x as usize] = count;
+	}
+
+	/// Returns the content of the cell at coordinates, or None if the coordinates are out of the grid
+	pub fn cell(&self, coordinates: Coordinates) -> Option<Cell> {
+		self.grid.get(coordinates)
+	}
+
+	/// Returns a String containing the ASCII representation of the grid as seen by the player
+	///
+	/// Covered cells are shown as `#` and flagged cells as `F`
+	pub fn console_output(&self) -> String {
+		let mut buffer = format!(
+			"Game ({}, {}) with {} / {} flags:\n",
+			self.grid.width(), self.grid.height(), self.flag_count, self.grid.bomb_count()
+		);
+		let line: String = (0..=(self.grid.width() + 1)).map(|_| '-').collect();
+		buffer = format!("{}{}\n", buffer, line);
+		for (y, row) in self.grid.iter().enumerate().rev() {
+			buffer = format!("{}|", buffer);
+			for (x, cell) in row.iter().enumerate() {
+				let symbol = match self.states[y][x] {
+					_ if !self.grid.contains(Coordinates::new(x as u16, y as u16)) => " ".to_string(),
+					CellState::Covered => "#".to_string(),
+					CellState::Flagged => "F".to_string(),
+					CellState::Revealed => cell.console_output(),
+				};
+				buffer = format!("{}{}", buffer, symbol);
+			}
+			buffer = format!("{}|\n", buffer);
+		}
+		format!("{}{}", buffer, line)
+	}
+
+	// Some getters
+	pub fn grid(&self) -> &Grid { &self.grid }
+	pub fn outcome(&self) -> Outcome { self.outcome }
+	pub fn flag_count(&self) -> u16 { self.flag_count }
+	pub fn covered_safe_cells(&self) -> u32 { self.covered_safe_cells }
+	pub fn lives(&self) -> u8 { self.lives }
+	pub fn exploded(&self) -> &[Coordinates] { &self.exploded }
+
+	/// Returns the bomb that ended the game, if the game was lost by revealing it
+	pub fn mine_hit(&self) -> Option<Coordinates> {
+		self.grid.cells().into_iter()
+			.find(|&coord| self.grid.is_bomb_at(coord) && self.state(coord) == Some(CellState::Revealed))
+	}
+
+	/// Returns the bombs that are still covered, neither flagged nor exploded
+	pub fn hidden_mines(&self) -> Vec<Coordinates> {
+		self.grid.cells().into_iter()
+			.filter(|&coord| self.grid.is_bomb_at(coord) && self.state(coord) == Some(CellState::Covered))
+			.collect()
+	}
+
+	/// Returns the flagged cells holding fewer mines than flags, most often no mine at all
+	pub fn wrong_flags(&self) -> Vec<Coordinates> {
+		self.grid.cells().into_iter()
+			.filter(|&coord| self.state(coord) == Some(CellState::Flagged) && self.flags_at(coord) > self.grid.mines_at(coord))
+			.collect()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::resources::Mask;
+
+	/// A 5x3 grid cut in two by a wall of mines in its middle column
+	fn walled_grid() -> Grid {
+		Grid::from_bombs(5, 3, &[Coordinates::new(2, 0), Coordinates::new(2, 1), Coordinates::new(2, 2)])
+	}
+
+	#[test]
+	fn reveal_mine_loses_the_game() {
+		let mut session = GameSession::new(walled_grid());
+		let mine = Coordinates::new(2, 1);
+		assert_eq!(session.reveal(mine), RevealOutcome::Explosion(mine));
+		assert_eq!(session.outcome(), Outcome::Defeat);
+		assert_eq!(session.mine_hit(), Some(mine));
+		assert_eq!(session.reveal(Coordinates::new(0, 0)), RevealOutcome::Ignored);
+	}
+
+	#[test]
+	fn reveal_number_reveals_only_the_cell() {
+		let mut session = GameSession::new(walled_grid());
+		let number = Coordinates::new(1, 1);
+		assert_eq!(session.reveal(number), RevealOutcome::Safe(vec![number]));
+		assert_eq!(session.state(Coordinates::new(0, 1)), Some(CellState::Covered));
+		assert_eq!(session.covered_safe_cells(), 11);
+	}
+
+	#[test]
+	fn reveal_empty_cell_opens_the_area() {
+		let mut session = GameSession::new(walled_grid());
+		let RevealOutcome::Safe(mut revealed) = session.reveal(Coordinates::new(0, 1)) else { panic!("expected an opening") };
+		revealed.sort();
+		let mut expected: Vec<Coordinates> = (0..3).flat_map(|y| (0..2).map(move |x| Coordinates::new(x, y))).collect();
+		expected.sort();
+		assert_eq!(revealed, expected);
+		// The opening stops at the numbers, the other side of the wall stays covered
+		assert_eq!(session.state(Coordinates::new(3, 1)), Some(CellState::Covered));
+		assert_eq!(session.outcome(), Outcome::Playing);
+	}
+
+	#[test]
+	fn reveal_flagged_or_revealed_cell_is_ignored() {
+		let mut session = GameSession::new(walled_grid());
+		let number = Coordinates::new(1, 1);
+		assert_eq!(session.toggle_flag(number), FlagOutcome::Placed);
+		assert_eq!(session.reveal(number), RevealOutcome::Ignored);
+		assert_eq!(session.toggle_flag(number), FlagOutcome::Removed);
+		session.reveal(number);
+		assert_eq!(session.reveal(number), RevealOutcome::Ignored);
+		assert_eq!(session.toggle_flag(number), FlagOutcome::Ignored);
+		assert_eq!(session.flag_count(), 0);
+	}
+
+	#[test]
+	fn chord_reveals_the_neighbors_of_a_satisfied_number() {
+		let mut session = GameSession::new(walled_grid());
+		let number = Coordinates::new(1, 1);
+		assert!(session.chord(number).is_empty(), "a covered cell cannot be chorded");
+		session.reveal(number);
+		session.toggle_flag(Coordinates::new(2, 0));
+		session.toggle_flag(Coordinates::new(2, 1));
+		assert!(session.chord(number).is_empty(), "the 3 only has two flags around it");
+		session.toggle_flag(Coordinates::new(2, 2));
+		let mut chord = session.chord(number);
+		chord.sort();
+		let mut expected = vec![
+			Coordinates::new(0, 0), Coordinates::new(0, 1), Coordinates::new(0, 2), Coordinates::new(1, 0), Coordinates::new(1, 2),
+		];
+		expected.sort();
+		assert_eq!(chord, expected);
+	}
+
+	#[test]
+	fn chord_trusts_wrong_flags() {
+		let mut session = GameSession::new(walled_grid());
+		let number = Coordinates::new(1, 1);
+		session.reveal(number);
+		for flag in [Coordinates::new(2, 0), Coordinates::new(2, 1), Coordinates::new(0, 0)] {
+			session.toggle_flag(flag);
+		}
+		assert!(session.chord(number).contains(&Coordinates::new(2, 2)));
+		assert!(!session.can_toggle_flag(number) && session.can_toggle_flag(Coordinates::new(0, 0)));
+	}
+
+	#[test]
+	fn revealing_every_safe_cell_wins() {
+		let mut session = GameSession::new(walled_grid());
+		assert!(matches!(session.reveal(Coordinates::new(0, 1)), RevealOutcome::Safe(_)));
+		assert!(matches!(session.reveal(Coordinates::new(4, 1)), RevealOutcome::Victory(_)));
+		assert_eq!(session.outcome(), Outcome::Victory);
+		assert_eq!(session.toggle_flag(Coordinates::new(2, 1)), FlagOutcome::Ignored);
+	}
+
+	#[test]
+	fn lives_flag_the_mines_until_the_last_one() {
+		let mut session = GameSession::new(walled_grid()).with_lives(2);
+		let (first, second) = (Coordinates::new(2, 0), Coordinates::new(2, 2));
+		assert_eq!(session.reveal(first), RevealOutcome::LifeLost(first));
+		assert_eq!(session.state(first), Some(CellState::Flagged));
+		assert_eq!(session.lives(), 1);
+		// The flag of an exploded mine cannot be removed
+		assert_eq!(session.toggle_flag(first), FlagOutcome::Ignored);
+		assert_eq!(session.reveal(second), RevealOutcome::Explosion(second));
+		assert_eq!(session.outcome(), Outcome::Defeat);
+	}
+
+	#[test]
+	fn claimed_mines_never_end_the_game() {
+		let mut session = GameSession::new(walled_grid()).with_claimed_mines();
+		for y in 0..3 {
+			let mine = Coordinates::new(2, y);
+			assert_eq!(session.reveal(mine), RevealOutcome::Claimed(mine));
+		}
+		assert_eq!(session.flag_count(), 3);
+		assert_eq!(session.outcome(), Outcome::Playing);
+	}
+
+	#[test]
+	fn states_must_match_the_grid() {
+		let states = vec![vec![CellState::Covered; 5]; 2];
+		assert!(GameSession::with_states(walled_grid(), states).is_err());
+		let mut states = vec![vec![CellState::Covered; 5]; 3];
+		states[1][0] = CellState::Revealed;
+		states[1][2] = CellState::Flagged;
+		let session = GameSession::with_states(walled_grid(), states.clone()).unwrap();
+		assert_eq!((session.covered_safe_cells(), session.flag_count()), (11, 1));
+
+		// The top left corner is a hole, which can neither be revealed nor flagged
+		let holed = walled_grid().with_mask(Mask::from_drawing(".XXXX\nXXXXX\nXXXXX").unwrap());
+		assert!(GameSession::with_states(holed.clone(), states.clone()).is_ok());
+		for state in [CellState::Revealed, CellState::Flagged] {
+			let mut states = states.clone();
+			states[2][0] = state;
+			assert!(GameSession::with_states(holed.clone(), states).is_err());
+		}
+	}
+}
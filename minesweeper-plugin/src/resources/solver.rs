@@ -0,0 +1,257 @@
+// This module contains a simple minesweeper solver, working only with what the player can see
+// It is used to generate grids that can be solved without ever having to guess
+
+use std::collections::HashSet;
+use bevy::reflect::Reflect;
+use rand::Rng;
+
+use crate::components::Coordinates;
+use super::{Cell, CellState, GameSession, Grid, Outcome};
+
+
+/// How many grids are generated before giving up on finding one that can be solved without guessing
+pub const NO_GUESS_ATTEMPTS: u32 = 10_000;
+/// Highest share of the cells holding a mine for which a grid solvable without guessing can be found in a reasonable time
+///
+/// At the density of the expert grids, about one random grid in twenty can be solved, past 23% hardly any can
+pub const MAX_NO_GUESS_DENSITY: f32 = 0.23;
+
+/// A set of covered cells, containing exactly `bombs` bombs
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Constraint {
+	cells: HashSet<Coordinates>,
+	bombs: usize,
+}
+
+
+/// The deductions of the solver, from the simplest to the hardest
+///
+/// A board needing a guess somewhere is rated with `Guess`, the hardest of all
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Reflect)]
+pub enum Technique {
+	/// A single number, either satisfied or needing all its covered neighbors
+	Single,
+	/// A number whose covered neighbors are a subset of another number's covered neighbors
+	Subset,
+	/// The count of the mines left on the whole board, for the endgames
+	MineCount,
+	Guess,
+}
+
+impl Technique {
+	/// The techniques the solver can use, in the order they are tried
+	pub const LOGICAL: [Technique; 3] = [Technique::Single, Technique::Subset, Technique::MineCount];
+
+	/// Returns the name of the technique, in lowercase
+	pub fn name(&self) -> &'static str {
+		match self {
+			Technique::Single => "single",
+			Technique::Subset => "subset",
+			Technique::MineCount => "mine-count",
+			Technique::Guess => "guess",
+		}
+	}
+}
+
+/// A solver that only uses logical deductions, it never guesses
+///
+/// It keeps track of the bombs it found, since the flags placed by a player could be wrong
+#[derive(Debug, Default, Clone)]
+pub struct Solver {
+	bombs: HashSet<Coordinates>,
+}
+
+impl Solver {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns every covered cell that is proven to be safe by the revealed numbers
+	///
+	/// Two rules are used: a single number either satisfied or needing all its covered neighbors,
+	/// and a number whose covered neighbors are a subset of another number's covered neighbors.
+	/// The total bomb count of the grid is used as an additional constraint for endgames.
+	/// When a cell can hold several mines, a number needing as many bombs as it has covered neighbors
+	/// does not prove that every one of them is a bomb, so only the safe cells are deduced.
+	pub fn deduce(&mut self, session: &GameSession) -> Vec<Coordinates> {
+		self.deduce_with(session, Technique::MineCount)
+	}
+
+	/// Returns every covered cell proven to be safe, using only the techniques up to the given one
+	pub fn deduce_with(&mut self, session: &GameSession, hardest: Technique) -> Vec<Coordinates> {
+		let single_mines = session.grid().mines_per_cell() == 1;
+		let mut safe = HashSet::new();
+		loop {
+			let constraints = self.constraints(session, &safe, hardest >= Technique::MineCount);
+			let mut progress = false;
+
+			let mut apply = |cells: &HashSet<Coordinates>, bombs: usize, safe: &mut HashSet<Coordinates>, solver: &mut Solver| {
+				if bombs == 0 {
+					for &cell in cells {
+						progress |= safe.insert(cell);
+					}
+				} else if bombs == cells.len() && single_mines {
+					for &cell in cells {
+						progress |= solver.bombs.insert(cell);
+					}
+				}
+			};
+
+			for constraint in &constraints {
+				apply(&constraint.cells, constraint.bombs, &mut safe, self);
+			}
+			if hardest >= Technique::Subset {
+				for a in &constraints {
+					for b in &constraints {
+						if a == b || a.cells.len() >= b.cells.len() || !a.cells.is_subset(&b.cells) { continue }
+						let difference = b.cells.difference(&a.cells).copied().collect();
+						apply(&difference, b.bombs - a.bombs, &mut safe, self);
+					}
+				}
+			}
+
+			if !progress { break }
+		}
+		let mut safe: Vec<Coordinates> = safe.into_iter().collect();
+		safe.sort();
+		safe
+	}
+
+	/// Returns the bombs found by the previous deductions
+	pub fn bombs(&self) -> &HashSet<Coordinates> {
+		&self.bombs
+	}
+
+	/// Builds the constraints given by every revealed number, ignoring the cells already solved
+	///
+	/// With `mine_count`, the count of the mines left adds a constraint on every unknown cell
+	fn constraints(&self, session: &GameSession, safe: &HashSet<Coordinates>, mine_count: bool) -> Vec<Constraint> {
+		let grid = session.grid();
+		let unknown = |coord: &Coordinates| {
+			matches!(session.state(*coord), Some(CellState::Covered) | Some(CellState::Flagged))
+				&& !safe.contains(coord)
+				&& !self.bombs.contains(coord)
+		};
+
+		let mut constraints = Vec::new();
+		for coord in grid.cells() {
+			if session.state(coord) != Some(CellState::Revealed) { continue }
+			let Some(Cell::BombNeighbor(count)) = grid.get(coord) else { continue };
+			let cells: HashSet<Coordinates> = grid.get_neighbors(coord).filter(&unknown).collect();
+			if cells.is_empty() { continue }
+			let found = grid.get_neighbors(coord).filter(|c| self.bombs.contains(c)).count();
+			constraints.push(Constraint { cells, bombs: count as usize - found });
+		}
+
+		if !mine_count { return constraints }
+		// Every remaining bomb is somewhere in the unknown cells
+		let cells: HashSet<Coordinates> = grid.cells().into_iter()
+			.filter(&unknown)
+			.collect();
+		if !cells.is_empty() {
+			constraints.push(Constraint { cells, bombs: grid.bomb_count() as usize - self.bombs.len() });
+		}
+		constraints
+	}
+}
+
+
+/// Returns true if the session can be won from its current state without guessing
+pub fn is_solvable(mut session: GameSession) -> bool {
+	let mut solver = Solver::new();
+	while session.outcome() == Outcome::Playing {
+		let safe = solver.deduce(&session);
+		if safe.is_empty() { return false }
+		for coord in safe {
+			session.reveal(coord);
+		}
+	}
+	session.outcome() == Outcome::Victory
+}
+
+/// Generates a grid that can be solved without guessing, starting by revealing the returned coordinates
+///
+/// The bombs are placed on copies of the empty grid given, which decides the size, the topology, the kernel and the mask.
+/// The starting cell and its neighbors never contain a bomb, so the game always starts with an opening.
+/// Returns None if no such grid was found after `NO_GUESS_ATTEMPTS` tries.
+pub fn generate_no_guess(empty: &Grid, bomb_count: u16, rng: &mut impl Rng) -> Option<(Grid, Coordinates)> {
+	(0..NO_GUESS_ATTEMPTS)
+		.filter_map(|_| no_guess_candidate(empty, bomb_count, rng))
+		.find(|(grid, start)| is_solvable_from(grid, *start))
+}
+
+/// Places the bombs on a copy of the empty grid, keeping a random starting cell and its neighbors free of bombs
+///
+/// Returns None if the starting cell drawn is a hole of the mask, or if the bombs do not fit out of its neighborhood
+pub(super) fn no_guess_candidate(empty: &Grid, bomb_count: u16, rng: &mut impl Rng) -> Option<(Grid, Coordinates)> {
+	let start = Coordinates::new(rng.gen_range(0..empty.width()), rng.gen_range(0..empty.height()));
+	if !empty.contains(start) { return None }
+	let mut grid = empty.clone();
+	let safe_zone: Vec<Coordinates> = grid.get_neighbors(start).chain([start]).collect();
+	if grid.bomb_room(&safe_zone) < bomb_count as usize { return None }
+	grid.set_bombs_avoiding(bomb_count, rng, &safe_zone);
+	Some((grid, start))
+}
+
+/// Returns true if the grid can be solved without guessing, the game starting by revealing the given cell
+pub(super) fn is_solvable_from(grid: &Grid, start: Coordinates) -> bool {
+	let mut session = GameSession::new(grid.clone());
+	session.reveal(start);
+	is_solvable(session)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use rand::SeedableRng;
+	use rand::rngs::StdRng;
+	use super::*;
+
+	#[test]
+	fn the_bombs_must_fit_out_of_the_opening() {
+		let mut rng = StdRng::seed_from_u64(1);
+		// A corner keeps 4 cells free, the 12 others cannot hold 13 bombs
+		let empty = Grid::empty(4, 4);
+		for _ in 0..100 {
+			assert!(no_guess_candidate(&empty, 13, &mut rng).is_none());
+		}
+		assert!(generate_no_guess(&empty, 13, &mut rng).is_none());
+		// Only the corners leave room for 12 bombs
+		let (grid, start) = (0..100).find_map(|_| no_guess_candidate(&empty, 12, &mut rng)).unwrap();
+		assert_eq!((grid.bomb_count(), grid.mines_at(start)), (12, 0));
+	}
+
+	/// Starts a game on the grid drawn in text, from its bottom left corner
+	fn opened(text: &str) -> GameSession {
+		let mut session = GameSession::new(text.parse().unwrap());
+		session.reveal(Coordinates::new(0, 0));
+		session
+	}
+
+	#[test]
+	fn a_single_number_proves_its_neighbors() {
+		// The 2 on the right needs both mines above it, which satisfies the 1 on the left
+		let mut session = opened(".**\n...\n...\n");
+		let mut solver = Solver::new();
+		assert_eq!(solver.deduce_with(&session, Technique::Single), vec![Coordinates::new(0, 2)]);
+		session.reveal(Coordinates::new(0, 2));
+		assert!(is_solvable(session));
+	}
+
+	#[test]
+	fn the_one_two_one_needs_a_subset() {
+		let session = opened("*.*\n...\n...\n");
+		let mut solver = Solver::new();
+		assert!(solver.deduce_with(&session, Technique::Single).is_empty());
+		assert_eq!(solver.deduce_with(&session, Technique::Subset), vec![Coordinates::new(1, 2)]);
+		assert_eq!(solver.bombs(), &HashSet::from([Coordinates::new(0, 2), Coordinates::new(2, 2)]));
+		assert!(is_solvable(session));
+	}
+
+	#[test]
+	fn a_fifty_fifty_cannot_be_solved() {
+		let session = opened("*.\n..\n..\n");
+		assert!(Solver::new().deduce(&session).is_empty());
+		assert!(!is_solvable(session));
+	}
+}
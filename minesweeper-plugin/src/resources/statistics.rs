@@ -0,0 +1,128 @@
+// This module contains the statistics of the player, saved in a small text file next to the high scores
+// The games are counted in buckets: one per standard difficulty and one for the custom ones,
+// the retries of a board being counted in buckets of their own so they don't inflate the win rate of the new boards
+
+use core::fmt;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+
+
+/// File where the statistics are saved, next to the high scores
+pub const STATISTICS_FILE: &str = "statistics.txt";
+
+/// The bucket of the custom difficulties
+pub const CUSTOM_BUCKET: &str = "custom";
+
+/// Returns the bucket of the retries of a board, like `expert-retry`
+pub fn retry_bucket(bucket: &str) -> String {
+	format!("{}-retry", bucket)
+}
+
+
+/// The games played in a bucket
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct GameRecord {
+	pub played: u32,
+	pub won: u32,
+}
+
+impl GameRecord {
+	pub fn lost(&self) -> u32 {
+		self.played - self.won
+	}
+
+	/// Returns the percentage of games won
+	pub fn win_rate(&self) -> u32 {
+		if self.played == 0 { return 0 }
+		self.won * 100 / self.played
+	}
+}
+
+/// Shows the record like `12 games, 5 won (41%)`
+impl fmt::Display for GameRecord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let games = if self.played == 1 { "game" } else { "games" };
+		write!(f, "{} {}, {} won ({}%)", self.played, games, self.won, self.win_rate())
+	}
+}
+
+/// The games played in every bucket
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Statistics {
+	buckets: BTreeMap<String, GameRecord>,
+}
+
+impl Statistics {
+	/// Loads the statistics from a file, see `Statistics::from_str` for the format
+	///
+	/// A missing file gives empty statistics, since no game was played yet
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+		let path = path.as_ref();
+		match fs::read_to_string(path) {
+			Ok(text) => text.parse(),
+			Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+			Err(err) => Err(format!("Cannot read statistics file {}: {}", path.display(), err)),
+		}
+	}
+
+	/// Saves the statistics to a file
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+		let path = path.as_ref();
+		fs::write(path, self.to_string())
+			.map_err(|err| format!("Cannot write statistics file {}: {}", path.display(), err))
+	}
+
+	/// Counts a finished game in a bucket, returning the updated record of the bucket
+	pub fn record(&mut self, bucket: &str, won: bool) -> GameRecord {
+		let record = self.buckets.entry(bucket.to_string()).or_default();
+		record.played += 1;
+		record.won += won as u32;
+		*record
+	}
+
+	/// Returns the games played in a bucket
+	pub fn get(&self, bucket: &str) -> GameRecord {
+		self.buckets.get(bucket).copied().unwrap_or_default()
+	}
+}
+
+/// Parses the statistics from text
+///
+/// Every line contains a bucket, its number of games and its number of victories, like `expert = 12, 5`.
+/// Empty lines and lines starting with `#` are ignored.
+impl FromStr for Statistics {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let mut statistics = Statistics::default();
+		for (number, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') { continue }
+			let (key, value) = line.split_once('=')
+				.ok_or_else(|| format!("Line {}: expected `bucket = played, won`", number + 1))?;
+			let (played, won) = value.split_once(',')
+				.ok_or_else(|| format!("Line {}: expected `played, won`", number + 1))?;
+			let parse = |count: &str| count.trim().parse::<u32>()
+				.map_err(|_| format!("Line {}: invalid number {}", number + 1, count.trim()));
+			let record = GameRecord { played: parse(played)?, won: parse(won)? };
+			if record.won > record.played {
+				return Err(format!("Line {}: more games won than played", number + 1));
+			}
+			statistics.buckets.insert(key.trim().to_string(), record);
+		}
+		Ok(statistics)
+	}
+}
+
+impl fmt::Display for Statistics {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "# Games played and won")?;
+		for (bucket, record) in &self.buckets {
+			writeln!(f, "{} = {}, {}", bucket, record.played, record.won)?;
+		}
+		Ok(())
+	}
+}
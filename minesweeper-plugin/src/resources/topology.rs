@@ -0,0 +1,368 @@
+// This module contains the shapes of board the game can be played on
+// A topology decides which cells exist, which cells are neighbors, and where every cell is drawn in the world.
+// Every part of the game goes through the `Topology` trait, so a new shape of board only needs a new implementation.
+
+use std::fmt;
+use std::str::FromStr;
+use bevy::math::Vec2;
+use bevy::reflect::Reflect;
+
+use crate::components::Coordinates;
+use super::Kernel;
+
+
+/// Slice containing the position of all neighbors of a cell on a square grid
+pub const NEIGHBORS: [(i8, i8); 8] = [
+	(-1,  1), (0,  1), (1,  1),
+	(-1,  0),          (1,  0),
+	(-1, -1), (0, -1), (1, -1),
+];
+
+/// Neighbors of a cell on an even row of a hexagonal grid, the odd rows being shifted half a cell to the right
+pub const HEX_NEIGHBORS_EVEN_ROW: [(i8, i8); 6] = [
+	(-1,  1), (0,  1),
+	(-1,  0),          (1,  0),
+	(-1, -1), (0, -1),
+];
+
+/// Neighbors of a cell on an odd row of a hexagonal grid
+pub const HEX_NEIGHBORS_ODD_ROW: [(i8, i8); 6] = [
+	(0,  1), (1,  1),
+	(-1,  0),         (1,  0),
+	(0, -1), (1, -1),
+];
+
+
+/// The shape of a board: its cells, their neighbors and their layout in the world
+///
+/// The cells are always stored in a `width` x `height` grid, but a topology may decide how they are connected and drawn.
+/// Positions in the world are relative to the bottom left corner of the board, without any padding.
+pub trait Topology: Send + Sync {
+	/// Returns every cell of a board
+	fn cells(&self, width: u16, height: u16) -> Vec<Coordinates> {
+		(0..height).flat_map(|y| (0..width).map(move |x| Coordinates::new(x, y))).collect()
+	}
+
+	/// Returns true if the cell is part of the board
+	fn contains(&self, coordinates: Coordinates, width: u16, height: u16) -> bool {
+		coordinates.x < width && coordinates.y < height
+	}
+
+	/// Returns the neighbors of a cell counted by the kernel, every one of them is part of the board
+	fn neighbors(&self, coordinates: Coordinates, width: u16, height: u16, kernel: &Kernel) -> Vec<Coordinates>;
+
+	/// Returns the position of the bottom left corner of the square containing the cell
+	fn cell_origin(&self, coordinates: Coordinates, cell_size: f32) -> Vec2;
+
+	/// Returns the size of the whole board
+	fn board_size(&self, width: u16, height: u16, cell_size: f32) -> Vec2;
+
+	/// Returns the cell at a position, or None if there is no cell there
+	fn cell_at(&self, position: Vec2, width: u16, height: u16, cell_size: f32) -> Option<Coordinates>;
+
+	/// Returns the corners of a cell of the given size, relative to its origin, or None for a plain square
+	fn cell_outline(&self, _size: f32) -> Option<Vec<Vec2>> {
+		None
+	}
+
+	/// Returns the copies of cells drawn outside of the board, as the origin of the copy and the cell it shows
+	fn ghosts(&self, _width: u16, _height: u16, _cell_size: f32) -> Vec<(Vec2, Coordinates)> {
+		Vec::new()
+	}
+}
+
+/// Applies offsets to a cell, keeping only the results inside the board
+fn offset_neighbors(offsets: &[(i8, i8)], coordinates: Coordinates, width: u16, height: u16) -> Vec<Coordinates> {
+	offsets.iter()
+		.filter_map(|&(dx, dy)| Some(Coordinates::new(
+			coordinates.x.checked_add_signed(dx as i16)?,
+			coordinates.y.checked_add_signed(dy as i16)?,
+		)))
+		.filter(|coord| coord.x < width && coord.y < height)
+		.collect()
+}
+
+/// Returns the cell at a position of a square grid
+fn square_cell_at(position: Vec2, width: u16, height: u16, cell_size: f32) -> Option<Coordinates> {
+	let cell = position / cell_size;
+	if cell.x < 0. || cell.y < 0. || cell.x >= width as f32 || cell.y >= height as f32 { return None }
+	Some(Coordinates::new(cell.x as u16, cell.y as u16))
+}
+
+
+/// The classic board, every cell has 8 neighbors except on the edges, unless another kernel is used
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SquareTopology;
+
+impl Topology for SquareTopology {
+	fn neighbors(&self, coordinates: Coordinates, width: u16, height: u16, kernel: &Kernel) -> Vec<Coordinates> {
+		offset_neighbors(kernel.offsets(), coordinates, width, height)
+	}
+
+	fn cell_origin(&self, coordinates: Coordinates, cell_size: f32) -> Vec2 {
+		Vec2::new(coordinates.x as f32, coordinates.y as f32) * cell_size
+	}
+
+	fn board_size(&self, width: u16, height: u16, cell_size: f32) -> Vec2 {
+		Vec2::new(width as f32, height as f32) * cell_size
+	}
+
+	fn cell_at(&self, position: Vec2, width: u16, height: u16, cell_size: f32) -> Option<Coordinates> {
+		square_cell_at(position, width, height, cell_size)
+	}
+}
+
+
+/// A square board whose opposite edges are adjacent, so every cell has 8 neighbors (or as many as its kernel)
+///
+/// The board is surrounded by a ring of ghost cells, showing the cells of the opposite edges.
+/// Clicking on a ghost cell plays on the cell it shows.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TorusTopology;
+
+impl Topology for TorusTopology {
+	fn neighbors(&self, coordinates: Coordinates, width: u16, height: u16, kernel: &Kernel) -> Vec<Coordinates> {
+		// Nothing to wrap around on an empty board
+		if width == 0 || height == 0 {
+			return Vec::new();
+		}
+		let mut neighbors: Vec<Coordinates> = kernel.offsets().iter()
+			.map(|&(dx, dy)| Coordinates::new(
+				(coordinates.x as i32 + dx as i32).rem_euclid(width as i32) as u16,
+				(coordinates.y as i32 + dy as i32).rem_euclid(height as i32) as u16,
+			))
+			// On tiny boards, wrapping around can lead back to the cell itself or to the same neighbor twice
+			.filter(|&coord| coord != coordinates)
+			.collect();
+		neighbors.sort();
+		neighbors.dedup();
+		neighbors
+	}
+
+	fn cell_origin(&self, coordinates: Coordinates, cell_size: f32) -> Vec2 {
+		// Leaving room for the ghost cells
+		SquareTopology.cell_origin(coordinates, cell_size) + Vec2::splat(cell_size)
+	}
+
+	fn board_size(&self, width: u16, height: u16, cell_size: f32) -> Vec2 {
+		SquareTopology.board_size(width + 2, height + 2, cell_size)
+	}
+
+	fn cell_at(&self, position: Vec2, width: u16, height: u16, cell_size: f32) -> Option<Coordinates> {
+		if width == 0 || height == 0 {
+			return None;
+		}
+		let cell = square_cell_at(position, width + 2, height + 2, cell_size)?;
+		// The ghost cells are the cells of the opposite edge
+		Some(Coordinates::new(
+			(cell.x as i32 - 1).rem_euclid(width as i32) as u16,
+			(cell.y as i32 - 1).rem_euclid(height as i32) as u16,
+		))
+	}
+
+	fn ghosts(&self, width: u16, height: u16, cell_size: f32) -> Vec<(Vec2, Coordinates)> {
+		if width == 0 || height == 0 {
+			return Vec::new();
+		}
+		let (width, height) = (width as i32, height as i32);
+		(-1..=height)
+			.flat_map(|y| (-1..=width).map(move |x| (x, y)))
+			.filter(|&(x, y)| x < 0 || y < 0 || x == width || y == height)
+			.map(|(x, y)| (
+				Vec2::new((x + 1) as f32, (y + 1) as f32) * cell_size,
+				Coordinates::new(x.rem_euclid(width) as u16, y.rem_euclid(height) as u16),
+			))
+			.collect()
+	}
+}
+
+
+/// A board of hexagonal cells, every cell has 6 neighbors except on the edges, whatever the kernel
+///
+/// It uses offset coordinates: every odd row is shifted half a cell to the right,
+/// so the grid can still be stored as rows and columns
+#[derive(Debug, Default, Copy, Clone)]
+pub struct HexTopology;
+
+impl HexTopology {
+	/// Distance from the center of a hexagon to its corners, so that the flat sides of two neighbors touch
+	pub fn radius(cell_size: f32) -> f32 {
+		cell_size / 3f32.sqrt()
+	}
+
+	/// Vertical distance between the centers of two rows of hexagons
+	fn row_height(cell_size: f32) -> f32 {
+		Self::radius(cell_size) * 1.5
+	}
+
+	fn center(coordinates: Coordinates, cell_size: f32) -> Vec2 {
+		let shift = if coordinates.y % 2 == 1 { cell_size / 2. } else { 0. };
+		Vec2::new(
+			coordinates.x as f32 * cell_size + shift + cell_size / 2.,
+			coordinates.y as f32 * Self::row_height(cell_size) + Self::radius(cell_size),
+		)
+	}
+}
+
+impl Topology for HexTopology {
+	fn neighbors(&self, coordinates: Coordinates, width: u16, height: u16, _kernel: &Kernel) -> Vec<Coordinates> {
+		let offsets = if coordinates.y % 2 == 0 { &HEX_NEIGHBORS_EVEN_ROW } else { &HEX_NEIGHBORS_ODD_ROW };
+		offset_neighbors(offsets, coordinates, width, height)
+	}
+
+	fn cell_origin(&self, coordinates: Coordinates, cell_size: f32) -> Vec2 {
+		Self::center(coordinates, cell_size) - Vec2::splat(cell_size / 2.)
+	}
+
+	fn board_size(&self, width: u16, height: u16, cell_size: f32) -> Vec2 {
+		let shift = if height > 1 { cell_size / 2. } else { 0. };
+		Vec2::new(
+			width as f32 * cell_size + shift,
+			height.saturating_sub(1) as f32 * Self::row_height(cell_size) + 2. * Self::radius(cell_size),
+		)
+	}
+
+	fn cell_at(&self, position: Vec2, width: u16, height: u16, cell_size: f32) -> Option<Coordinates> {
+		// The closest center among the cells around the position is the cell containing it
+		let row = ((position.y - Self::radius(cell_size)) / Self::row_height(cell_size)).round() as i32;
+		(row - 1..=row + 1)
+			.filter(|&y| y >= 0 && y < height as i32)
+			.flat_map(|y| {
+				let shift = if y % 2 == 1 { cell_size / 2. } else { 0. };
+				let x = ((position.x - shift) / cell_size).floor() as i32;
+				(x - 1..=x + 1).map(move |x| (x, y))
+			})
+			.filter(|&(x, _)| x >= 0 && x < width as i32)
+			.map(|(x, y)| Coordinates::new(x as u16, y as u16))
+			.map(|coord| (coord, Self::center(coord, cell_size).distance(position)))
+			.filter(|&(_, distance)| distance <= Self::radius(cell_size))
+			.min_by(|(_, a), (_, b)| a.total_cmp(b))
+			.map(|(coord, _)| coord)
+	}
+
+	fn cell_outline(&self, size: f32) -> Option<Vec<Vec2>> {
+		// Pointy top hexagon, centered in the square of the cell
+		let center = Vec2::splat(size / 2.);
+		Some((0..6)
+			.map(|corner| (90. + 60. * corner as f32).to_radians())
+			.map(|angle| center + Vec2::new(angle.cos(), angle.sin()) * Self::radius(size))
+			.collect())
+	}
+}
+
+
+/// The topologies that can be chosen in the `DifficultySettings`
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Reflect)]
+pub enum TopologyKind {
+	#[default]
+	Square,
+	Torus,
+	Hex,
+}
+
+impl TopologyKind {
+	/// Returns the implementation of the topology
+	pub fn topology(&self) -> &'static dyn Topology {
+		match self {
+			TopologyKind::Square => &SquareTopology,
+			TopologyKind::Torus => &TorusTopology,
+			TopologyKind::Hex => &HexTopology,
+		}
+	}
+
+	/// Checks that the kernel can be played on the topology, hexagonal cells always having their 6 neighbors
+	pub fn check_kernel(&self, kernel: &Kernel) -> Result<(), String> {
+		match (self, kernel) {
+			(TopologyKind::Hex, Kernel::Moore) | (TopologyKind::Square | TopologyKind::Torus, _) => Ok(()),
+			(TopologyKind::Hex, _) => Err("Hexagonal cells always count their 6 neighbors, no other kernel can be used".to_string()),
+		}
+	}
+}
+
+impl fmt::Display for TopologyKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TopologyKind::Square => write!(f, "square"),
+			TopologyKind::Torus => write!(f, "torus"),
+			TopologyKind::Hex => write!(f, "hex"),
+		}
+	}
+}
+
+impl FromStr for TopologyKind {
+	type Err = String;
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		match name {
+			"square" => Ok(TopologyKind::Square),
+			"torus" => Ok(TopologyKind::Torus),
+			"hex" => Ok(TopologyKind::Hex),
+			other => Err(format!("Unknown topology '{}'", other)),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn coords(cells: &[(u16, u16)]) -> Vec<Coordinates> {
+		let mut cells: Vec<Coordinates> = cells.iter().map(|&(x, y)| Coordinates::new(x, y)).collect();
+		cells.sort();
+		cells
+	}
+
+	fn sorted(mut cells: Vec<Coordinates>) -> Vec<Coordinates> {
+		cells.sort();
+		cells
+	}
+
+	#[test]
+	fn the_torus_wraps_around_its_edges() {
+		let corner = TorusTopology.neighbors(Coordinates::new(0, 0), 4, 3, &Kernel::Moore);
+		assert_eq!(corner, coords(&[(3, 2), (0, 2), (1, 2), (3, 0), (1, 0), (3, 1), (0, 1), (1, 1)]));
+		// On a 2x2 torus, the cells left and right of a cell are the same one
+		assert_eq!(TorusTopology.neighbors(Coordinates::new(0, 0), 2, 2, &Kernel::Moore), coords(&[(1, 0), (0, 1), (1, 1)]));
+		assert!(TorusTopology.neighbors(Coordinates::new(0, 0), 0, 3, &Kernel::Moore).is_empty());
+	}
+
+	#[test]
+	fn the_ghosts_of_the_torus_show_the_opposite_edges() {
+		assert_eq!(TorusTopology.ghosts(4, 3, 10.).len(), 6 * 5 - 4 * 3);
+		// Left of the board, in the ghost of the right column, and in the board itself
+		assert_eq!(TorusTopology.cell_at(Vec2::new(5., 15.), 4, 3, 10.), Some(Coordinates::new(3, 0)));
+		assert_eq!(TorusTopology.cell_at(Vec2::new(15., 15.), 4, 3, 10.), Some(Coordinates::new(0, 0)));
+		assert_eq!(TorusTopology.cell_at(Vec2::new(5., 5.), 4, 3, 10.), Some(Coordinates::new(3, 2)));
+		assert_eq!(TorusTopology.cell_at(Vec2::new(5., 5.), 0, 0, 10.), None);
+	}
+
+	#[test]
+	fn hexagonal_cells_have_six_neighbors_inside_the_board() {
+		let neighbors = |x, y| sorted(HexTopology.neighbors(Coordinates::new(x, y), 3, 3, &Kernel::Moore));
+		assert_eq!(neighbors(1, 1), coords(&[(1, 2), (2, 2), (0, 1), (2, 1), (1, 0), (2, 0)]));
+		// An even row on the left edge, an odd row on the right edge
+		assert_eq!(neighbors(0, 0), coords(&[(0, 1), (1, 0)]));
+		assert_eq!(neighbors(0, 2), coords(&[(1, 2), (0, 1)]));
+		assert_eq!(neighbors(2, 1), coords(&[(2, 2), (1, 1), (2, 0)]));
+		// The kernel is ignored
+		assert_eq!(sorted(HexTopology.neighbors(Coordinates::new(1, 1), 3, 3, &Kernel::Knight)), neighbors(1, 1));
+	}
+
+	#[test]
+	fn hexagonal_cells_are_found_on_both_sides_of_their_edges() {
+		let size = 10.;
+		let cell_at = |position| HexTopology.cell_at(position, 3, 3, size);
+		// Across the vertical edge of a row, the slanted edge up to an odd row, and the slanted edge down from it
+		for (a, b) in [((0, 0), (1, 0)), ((0, 0), (0, 1)), ((1, 1), (2, 0))] {
+			let (a, b) = (Coordinates::new(a.0, a.1), Coordinates::new(b.0, b.1));
+			let (center_a, center_b) = (HexTopology::center(a, size), HexTopology::center(b, size));
+			let (middle, step) = ((center_a + center_b) / 2., (center_b - center_a).normalize() * 0.5);
+			assert_eq!((cell_at(middle - step), cell_at(middle + step)), (Some(a), Some(b)));
+			assert_eq!(cell_at(center_a), Some(a));
+		}
+		// Farther than the corners of any cell, left of the board and in the notch left of the shifted odd row
+		let beyond = Vec2::new(HexTopology::radius(size) + 0.5, 0.);
+		assert_eq!(cell_at(HexTopology::center(Coordinates::new(0, 0), size) - beyond), None);
+		assert_eq!(cell_at(HexTopology::center(Coordinates::new(0, 1), size) - beyond), None);
+	}
+}
@@ -1,18 +1,31 @@
 use bevy::log::debug;
 use std::time::Instant;
-use bevy::prelude::{Res, ResMut, Resource};
+use bevy::prelude::{Component, Entity, EventWriter, Query};
 use bevy::time::Stopwatch;
 
-#[derive(Resource)]
+use crate::events::TimeoutEvent;
+use crate::resources::{GameSession, Outcome};
+
+/// The time of the game on a board, either a stopwatch or a countdown
+///
+/// The stopwatch always runs, so the replays are timed the same way in both modes,
+/// the countdown is the time limit plus the bonuses minus the elapsed time
+#[derive(Component)]
 pub struct GameTime {
 	pub stopwatch: Stopwatch,
 	last_tick: Instant,
+	/// Seconds the player had at the start of the game, None for a stopwatch
+	time_limit: Option<f32>,
+	/// Seconds earned since the start of the game
+	bonus: f32,
 }
 impl Default for GameTime {
 	fn default() -> Self {
 		Self {
 			stopwatch: Stopwatch::new(),
-			last_tick: Instant::now()
+			last_tick: Instant::now(),
+			time_limit: None,
+			bonus: 0.,
 		}
 	}
 }
@@ -26,8 +39,23 @@ impl GameTime {
 	// pub fn toggle_pause(&mut self) {
 	// 	if self.stopwatch.paused() { self.stopwatch.unpause() } else { self.stopwatch.pause() }
 	// }
-	pub fn restart(&mut self) {
-		self.stopwatch.reset()
+	/// Switches between the stopwatch and a countdown, taking effect from the start of the game
+	pub fn set_time_limit(&mut self, time_limit: Option<f32>) {
+		self.time_limit = time_limit;
+	}
+	/// Adds seconds to the countdown, does nothing on a stopwatch
+	pub fn add_bonus(&mut self, secs: f32) {
+		if self.time_limit.is_some() {
+			self.bonus += secs;
+		}
+	}
+	/// Returns the seconds left on the countdown, or None for a stopwatch
+	pub fn remaining_secs(&self) -> Option<f32> {
+		self.time_limit.map(|limit| (limit + self.bonus - self.stopwatch.elapsed_secs()).max(0.))
+	}
+	/// Returns true if the countdown reached zero
+	pub fn is_timed_out(&self) -> bool {
+		self.remaining_secs() == Some(0.)
 	}
 	pub fn update(&mut self) {
 		let now = Instant::now();
@@ -36,24 +64,41 @@ impl GameTime {
 		self.last_tick = now;
 	}
 	pub fn to_string(&self) -> String {
-		let secs = self.stopwatch.elapsed_secs();
-		if secs > 60.0 { format!("{} mins {} secs", (secs / 60.).trunc(), (secs % 60.).trunc()) }
-		else { format!("{} secs", secs.trunc()) }
+		// The countdown is rounded up, so it only shows 0 when the time is over
+		let secs = match self.remaining_secs() {
+			Some(remaining) => remaining.ceil(),
+			None => self.stopwatch.elapsed_secs(),
+		};
+		let time = if secs > 60.0 { format!("{} mins {} secs", (secs / 60.).trunc(), (secs % 60.).trunc()) }
+		else { format!("{} secs", secs.trunc()) };
+		if self.time_limit.is_some() { format!("{} left", time) } else { time }
 	}
 }
 
-pub fn update_timer( mut timer: ResMut<GameTime> ) {
-	timer.update()
+/// Ticks the timers of the boards still being played, the others keep the time their game ended at
+pub fn update_timer( mut q_boards: Query<(&mut GameTime, &GameSession)> ) {
+	for (mut time, session) in &mut q_boards {
+		if session.outcome() == Outcome::Playing {
+			time.update()
+		}
+	}
 }
-pub fn pause_timer( mut time: ResMut<GameTime> ) {
+pub fn pause_timer( mut q_times: Query<&mut GameTime> ) {
 	debug!("Pausing timer");
-	time.pause();
+	q_times.iter_mut().for_each(|mut time| time.pause());
 }
-pub fn unpause_timer( mut time: ResMut<GameTime> ) {
+pub fn unpause_timer( mut q_times: Query<&mut GameTime> ) {
 	debug!("Unpausing timer");
-	time.unpause();
+	q_times.iter_mut().for_each(|mut time| time.unpause());
 }
-pub fn restart_timer( mut time: ResMut<GameTime> ) {
-	debug!("Restarting timer");
-	time.restart()
+/// Sends a TimeoutEvent when the countdown of a board reaches zero while its game is still being played
+pub fn check_timeout(
+	q_boards: Query<(Entity, &GameTime, &GameSession)>,
+	mut ev_timeout: EventWriter<TimeoutEvent>,
+) {
+	for (board, time, session) in &q_boards {
+		if time.is_timed_out() && session.outcome() == Outcome::Playing {
+			ev_timeout.send(TimeoutEvent(board));
+		}
+	}
 }
@@ -3,26 +3,51 @@ use bevy::asset::AssetServer;
 use bevy::ui::UiImage;
 
 use crate::config::GameConfig;
-use crate::events::RestartEvent;
+use crate::events::{RestartEvent, RetryBoardEvent, statistics_bucket};
+use crate::resources::{Clicks, GameMetrics, GameSession, LoadedBoard, ReplayPlayback, RetryCount, Statistics, ThreeBv};
+use crate::time::GameTime;
 
 #[derive(Component)]
 pub struct GameOverUI;
+/// The panel of the game over screen, which can be hidden to look at the board
+#[derive(Component)]
+pub struct GameOverPanel;
 #[derive(Component)]
 pub struct ButtonQuit;
 #[derive(Component)]
 pub struct ButtonRetry;
+#[derive(Component)]
+pub struct ButtonShowBoard;
+/// Restarts with the same mines
+#[derive(Component)]
+pub struct ButtonRetryBoard;
 
 pub fn spawn_game_over_ui(
 	mut cmd: Commands,
 		cfg: Res<GameConfig>,
 		srv: Res<AssetServer>,
+		q_boards: Query<(&GameSession, &GameTime, &ThreeBv, &Clicks)>,
+		statistics: Res<Statistics>,
+		retry: Res<RetryCount>,
+		loaded: Res<LoadedBoard>,
+		playback: Res<ReplayPlayback>,
 ) {
+	let Ok((session, time, three_bv, clicks)) = q_boards.get_single() else { return };
 	let font = srv.load("fonts/FiraSans-Black.ttf");
+	let screen_box = NodeBundle {
+		style: Style {
+			width: Val::Percent(100.),
+			height: Val::Percent(100.),
+			..default()
+		},
+		..default()
+	};
 	let gameover_box = NodeBundle {
 		style: Style {
 			width: Val::Percent(100.),
 			height: Val::Percent(40.),
 			top: Val::Percent(40.),
+			position_type: PositionType::Absolute,
 			flex_direction: FlexDirection::Column,
 			..default()
 		},
@@ -57,8 +82,9 @@ pub fn spawn_game_over_ui(
 		..default()
 	};
 
+	let message = if time.is_timed_out() { "Time's up, you were too slow !" } else { "Oh shit, you blew up !" };
 	let gameover_text = TextBundle::from_section(
-		"Oh shit, you blew up !",
+		message,
 		TextStyle {
 			font_size: 50.,
 			font: font.clone(),
@@ -67,6 +93,28 @@ pub fn spawn_game_over_ui(
 		},
 	).with_text_justify(JustifyText::Center);
 
+	// The metrics of the game, with the part of the 3BV solved before the explosion
+	let metrics = GameMetrics::new(three_bv, session, *clicks, time.stopwatch.elapsed_secs());
+	// The record is only shown for the games counted in the statistics
+	let bucket = statistics_bucket(&cfg, &retry);
+	let record = match loaded.0 || playback.has_replay() {
+		true => String::new(),
+		false => format!("\n{}: {}", bucket, statistics.get(&bucket)),
+	};
+	let record_text = TextBundle::from_section(
+		format!("{}{}", metrics, record),
+		TextStyle {
+			font_size: 20.,
+			font: font.clone(),
+			color: cfg.theme.menus_msg,
+			..default()
+		},
+	).with_style(Style {
+		position_type: PositionType::Absolute,
+		bottom: Val::Percent(32.),
+		align_self: AlignSelf::Center,
+		..default()
+	});
 
 	let button_box = NodeBundle {
 		style: Style {
@@ -75,16 +123,16 @@ pub fn spawn_game_over_ui(
 			height: Val::Percent(20.),
 			position_type: PositionType::Absolute,
 			bottom: Val::Percent(10.),
+			justify_content: JustifyContent::SpaceEvenly,
 			..default()
 		},
 		// background_color: BackgroundColor(Color::GREEN),
 		..default()
 	};
-	let try_again_btn = ButtonBundle {
+	let menu_btn = ButtonBundle {
 		style: Style {
 			height: Val::Percent(100.),
-			width: Val::Percent(40.),
-			left: Val::Percent(5.),
+			width: Val::Percent(28.),
 			justify_content: JustifyContent::Center,
 			align_items: AlignItems::Center,
 			..default()
@@ -92,20 +140,35 @@ pub fn spawn_game_over_ui(
 		background_color: BackgroundColor(cfg.theme.menus_msg),
 		..default()
 	};
-	let try_again_text = TextBundle::from_section("Wanna try again ?", TextStyle {
+	let retry_board_text = TextBundle::from_section("Retry this board", TextStyle {
+		font_size: 25.,
+		font: font.clone(),
+		color: cfg.theme.menus_bg,
+		..default()
+	}).with_text_justify(JustifyText::Center);
+	let try_again_text = TextBundle::from_section("New board", TextStyle {
 		font_size: 25.,
 		font: font.clone(),
 		color: cfg.theme.menus_bg,
 		..default()
 	}).with_text_justify(JustifyText::Center);
 
-	let quit_game_btn = ButtonBundle {
+	let quit_game_text = TextBundle::from_section("Hell nah, get me out", TextStyle {
+		font_size: 25.,
+		font: font.clone(),
+		color: cfg.theme.menus_bg,
+		..default()
+	}).with_text_justify(JustifyText::Center);
+
+	// Below the board, so it stays in sight while the panel is hidden
+	let show_board_btn = ButtonBundle {
 		style: Style {
-			height: Val::Percent(100.),
-			width: Val::Percent(40.),
+			width: Val::Px(160.),
+			height: Val::Px(40.),
 			position_type: PositionType::Absolute,
-			flex_direction: FlexDirection::Column,
-			right: Val::Percent(5.),
+			bottom: Val::Px(5.),
+			left: Val::Percent(50.),
+			margin: UiRect::left(Val::Px(-80.)),
 			justify_content: JustifyContent::Center,
 			align_items: AlignItems::Center,
 			..default()
@@ -113,23 +176,27 @@ pub fn spawn_game_over_ui(
 		background_color: BackgroundColor(cfg.theme.menus_msg),
 		..default()
 	};
-	let quit_game_text = TextBundle::from_section("Hell nah, get me out", TextStyle {
-		font_size: 25.,
+	let show_board_text = TextBundle::from_section("Show board", TextStyle {
+		font_size: 20.,
 		font: font.clone(),
 		color: cfg.theme.menus_bg,
 		..default()
 	}).with_text_justify(JustifyText::Center);
 
-	cmd.spawn(gameover_box).with_children(|main_box| {
-		main_box.spawn(skull_img);
-		main_box.spawn(text_box).with_children(|txt_box| {
-			txt_box.spawn(gameover_text);
-		});
-		main_box.spawn(button_box).with_children(|btn_box| {
-			btn_box.spawn(try_again_btn).with_children(|btn| { btn.spawn(try_again_text); }).insert(ButtonRetry);
-			btn_box.spawn(quit_game_btn).with_children(|btn| { btn.spawn(quit_game_text); }).insert(ButtonQuit);
-		});
-
+	cmd.spawn(screen_box).with_children(|screen| {
+		screen.spawn(gameover_box).with_children(|main_box| {
+			main_box.spawn(skull_img);
+			main_box.spawn(text_box).with_children(|txt_box| {
+				txt_box.spawn(gameover_text);
+			});
+			main_box.spawn(record_text);
+			main_box.spawn(button_box).with_children(|btn_box| {
+				btn_box.spawn(menu_btn.clone()).with_children(|btn| { btn.spawn(retry_board_text); }).insert(ButtonRetryBoard);
+				btn_box.spawn(menu_btn.clone()).with_children(|btn| { btn.spawn(try_again_text); }).insert(ButtonRetry);
+				btn_box.spawn(menu_btn).with_children(|btn| { btn.spawn(quit_game_text); }).insert(ButtonQuit);
+			});
+		}).insert(GameOverPanel);
+		screen.spawn(show_board_btn).with_children(|btn| { btn.spawn(show_board_text); }).insert(ButtonShowBoard);
 	}).insert(GameOverUI);
 }
 
@@ -167,6 +234,41 @@ pub fn quit_button_system(
 	}
 }
 
+/// Hides the panel to study the board, or shows it again
+pub fn show_board_button_system(
+	mut q_interaction: Query<
+		(&Interaction, &mut BackgroundColor, &Children),
+		(Changed<Interaction>, With<Button>, With<ButtonShowBoard>),
+	>,
+	mut q_panel: Query<&mut Visibility, With<GameOverPanel>>,
+	mut q_text: Query<&mut Text>,
+		cfg: Res<GameConfig>,
+) {
+	for (interaction, mut background, children) in &mut q_interaction {
+		match *interaction {
+			Interaction::Pressed => {
+				let Ok(mut visibility) = q_panel.get_single_mut() else { continue };
+				let (shown, label) = match *visibility {
+					Visibility::Hidden => (Visibility::Inherited, "Show board"),
+					_ => (Visibility::Hidden, "Show menu"),
+				};
+				*visibility = shown;
+				for &child in children {
+					if let Ok(mut text) = q_text.get_mut(child) {
+						text.sections[0].value = label.to_string();
+					}
+				}
+			}
+			Interaction::Hovered => {
+				background.0 = cfg.theme.menus_btn;
+			}
+			Interaction::None => {
+				background.0 = cfg.theme.menus_msg;
+			}
+		}
+	}
+}
+
 pub fn retry_button_system(
 	mut q_interaction: Query<
 		(&Interaction, &mut BackgroundColor),
@@ -190,3 +292,26 @@ pub fn retry_button_system(
 	}
 }
 
+/// Restarts with the mines of the game just lost
+pub fn retry_board_button_system(
+	mut q_interaction: Query<
+		(&Interaction, &mut BackgroundColor),
+		(Changed<Interaction>, With<Button>, With<ButtonRetryBoard>),
+	>,
+	mut ev_retry: EventWriter<RetryBoardEvent>,
+		cfg: Res<GameConfig>,
+) {
+	for (interaction, mut background) in &mut q_interaction {
+		match *interaction {
+			Interaction::Pressed => {
+				ev_retry.send(RetryBoardEvent);
+			}
+			Interaction::Hovered => {
+				background.0 = cfg.theme.menus_btn;
+			}
+			Interaction::None => {
+				background.0 = cfg.theme.menus_msg;
+			}
+		}
+	}
+}
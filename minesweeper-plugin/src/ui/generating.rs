@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+
+use crate::board::PendingSession;
+use crate::config::GameConfig;
+
+
+/// The message shown in the middle of the window while the grid of the next game is generated
+#[derive(Component)]
+pub struct GeneratingUI;
+
+/// Shows the message while a `PendingSession` is being generated, and removes it once the board is spawned
+pub fn update_generating_ui(
+	mut cmd: Commands,
+	cfg: Res<GameConfig>,
+	srv: Res<AssetServer>,
+	pending: Option<Res<PendingSession>>,
+	q_ui: Query<Entity, With<GeneratingUI>>,
+) {
+	match (pending.is_some(), q_ui.get_single()) {
+		(true, Err(_)) => {
+			let message_box = NodeBundle {
+				style: Style {
+					position_type: PositionType::Absolute,
+					width: Val::Percent(100.),
+					height: Val::Percent(100.),
+					justify_content: JustifyContent::Center,
+					align_items: AlignItems::Center,
+					..default()
+				}, ..default()
+			};
+			let message = TextBundle::from_section("Generating the board...", TextStyle {
+				font_size: 30.,
+				font: srv.load("fonts/FiraSans-Black.ttf"),
+				color: cfg.theme.main_ui,
+			});
+			cmd.spawn(message_box).with_children(|message_box| { message_box.spawn(message); }).insert(GeneratingUI);
+		},
+		(false, Ok(entity)) => cmd.entity(entity).despawn_recursive(),
+		_ => {},
+	}
+}
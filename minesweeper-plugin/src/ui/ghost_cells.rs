@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::components::Coordinates;
+use crate::config::GameConfig;
+use crate::resources::{Cell, CellState, GameSession};
+
+
+// Ghost cells are copies of cells drawn outside of the board, like the opposite edges of a torus.
+// They are only a visual help, clicking on one of them plays on the cell it copies.
+
+/// Transparency of the ghost cells, so they are not mistaken for real ones
+const GHOST_ALPHA: f32 = 0.5;
+
+/// Marks a ghost cell, with the coordinates of the cell it copies
+#[derive(Component)]
+pub struct GhostCell(pub Coordinates);
+
+
+/// Returns what a ghost shows of its cell: its background color, and the symbol drawn over it with its color
+fn ghost_look(session: &GameSession, coord: Coordinates, cfg: &GameConfig) -> (Color, String, Color) {
+	let theme = &cfg.theme;
+	let (background, symbol, color) = match (session.state(coord), session.cell(coord)) {
+		(Some(CellState::Flagged), _) => (theme.hidden_cell, "F".to_string(), theme.flags),
+		(Some(CellState::Revealed), Some(Cell::Bomb(_))) => (theme.cell, "*".to_string(), theme.menus_msg),
+		(Some(CellState::Revealed), Some(Cell::BombNeighbor(count))) => (theme.cell, count.to_string(), theme.neighbor_color(count)),
+		(Some(CellState::Revealed), _) => (theme.cell, String::new(), theme.cell),
+		_ => (theme.hidden_cell, String::new(), theme.hidden_cell),
+	};
+	(background.with_a(GHOST_ALPHA), symbol, color.with_a(GHOST_ALPHA))
+}
+
+/// Spawns the ghost cells given by the topology of the board, as children of the board
+pub fn spawn_ghost_cells(parent: &mut ChildBuilder, session: &GameSession, cfg: &GameConfig, font: Handle<Font>) {
+	let grid = session.grid();
+	let cell_size = cfg.ui_style.cell_size as f32;
+	for (origin, coord) in grid.shape().ghosts(grid.width(), grid.height(), cell_size) {
+		if !grid.contains(coord) { continue }
+		let (background, symbol, color) = ghost_look(session, coord, cfg);
+		parent.spawn(SpriteBundle {
+			sprite: Sprite {
+				color: background,
+				custom_size: Some(Vec2::splat((cfg.ui_style.cell_size - cfg.ui_style.cell_padding) as f32)),
+				anchor: Anchor::BottomLeft,
+				..default()
+			},
+			transform: Transform::from_translation((origin + Vec2::splat(cfg.ui_style.cell_padding as f32)).extend(1.)),
+			..default()
+		}).insert(GhostCell(coord))
+		  .insert(Name::new(format!("Ghost of cell {} - {}", coord.x, coord.y)))
+		  .with_children(|parent| {
+			parent.spawn(Text2dBundle {
+				text: Text::from_section(symbol, TextStyle {
+					color,
+					font: font.clone(),
+					font_size: cfg.ui_style.font_size as f32,
+				}),
+				transform: Transform::from_xyz(cell_size / 2., cell_size / 2., 1.),
+				..default()
+			});
+		});
+	}
+}
+
+/// Updates the ghost cells of the boards that changed, to show the current state of the cells they copy
+///
+/// The ghosts are children of their board, like the cells
+pub fn update_ghost_cells(
+	q_boards: Query<&GameSession, Changed<GameSession>>,
+	cfg: Res<GameConfig>,
+	mut q_ghosts: Query<(&GhostCell, &Parent, &mut Sprite, &Children)>,
+	mut q_text: Query<&mut Text>,
+) {
+	for (ghost, parent, mut sprite, children) in &mut q_ghosts {
+		let Ok(session) = q_boards.get(parent.get()) else { continue };
+		update_ghost_cell(session, &cfg, ghost, &mut sprite, children, &mut q_text);
+	}
+}
+
+/// Updates a single ghost cell, to show the current state of the cell it copies in a session
+fn update_ghost_cell(
+	session: &GameSession,
+	cfg: &GameConfig,
+	ghost: &GhostCell,
+	sprite: &mut Sprite,
+	children: &Children,
+	q_text: &mut Query<&mut Text>,
+) {
+	let (background, symbol, color) = ghost_look(session, ghost.0, cfg);
+	sprite.color = background;
+	for &child in children {
+		if let Ok(mut text) = q_text.get_mut(child) {
+			text.sections[0].value = symbol.clone();
+			text.sections[0].style.color = color;
+		}
+	}
+}
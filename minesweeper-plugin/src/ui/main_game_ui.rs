@@ -8,9 +8,9 @@ use bevy::asset::AssetServer;
 use bevy::prelude::*;
 use crate::AppState;
 
-use crate::components::Flag;
 use crate::config::GameConfig;
 use crate::events::RestartEvent;
+use crate::resources::{FlagsDuel, GameSession};
 use crate::time::GameTime;
 
 
@@ -20,6 +20,14 @@ pub struct TimerUI;
 #[derive(Component)]
 pub struct FlagsUI;
 
+/// The lives counter, only shown when playing with several lives
+#[derive(Component)]
+pub struct LivesUI;
+
+/// The scores of the Flags duel, only shown during a duel
+#[derive(Component)]
+pub struct DuelUI;
+
 #[derive(Component)]
 pub struct SettingsButton;
 #[derive(Component)]
@@ -93,6 +101,18 @@ pub fn spawn_main_game_ui(
 		TextSection { value: "0".to_string(), style: text_style.clone() }
 	]).with_text_justify(JustifyText::Center);
 
+	let lives_text = TextBundle::from_sections([
+		TextSection { value: "   Lives : ".to_string(), style: text_style.clone() },
+		TextSection { value: cfg.difficulty.lives.to_string(), style: text_style.clone() }
+	]).with_text_justify(JustifyText::Center);
+
+	// The scores of both players, in the color of their flags
+	let duel_text = TextBundle::from_sections([
+		TextSection { value: String::new(), style: TextStyle { color: cfg.theme.player_one, ..text_style.clone() } },
+		TextSection { value: " - ".to_string(), style: text_style.clone() },
+		TextSection { value: String::new(), style: TextStyle { color: cfg.theme.player_two, ..text_style.clone() } },
+	]).with_text_justify(JustifyText::Center);
+
 	let time_box = NodeBundle {
 		style: Style {
 			height: Val::Percent(40.), ..default()
@@ -132,7 +152,11 @@ pub fn spawn_main_game_ui(
 
 	cmd.spawn(header_box).with_children(|main_box| {
 		main_box.spawn(text_box).with_children(|txt_box| {
-			txt_box.spawn(flags_box).with_children(|txt| { txt.spawn(flags_text).insert(FlagsUI); });
+			txt_box.spawn(flags_box).with_children(|txt| {
+				txt.spawn(flags_text).insert(FlagsUI);
+				txt.spawn(lives_text).insert(LivesUI);
+				txt.spawn(duel_text).insert(DuelUI);
+			});
 			txt_box.spawn(time_box).with_children(|txt| { txt.spawn(time_text).insert(TimerUI); });
 		});
 		main_box.spawn(button_box).with_children(|btn_box| {
@@ -144,9 +168,10 @@ pub fn spawn_main_game_ui(
 
 
 pub fn update_ui_timer(
-	timer: Res<GameTime>,
+	q_boards: Query<&GameTime>,
 	mut query: Query<&mut Text, With<TimerUI>>
 ) {
+	let Ok(timer) = q_boards.get_single() else { return };
 	if let Ok(mut text) = query.get_single_mut() {
 		text.sections[1].value = timer.to_string();
 	}
@@ -154,13 +179,41 @@ pub fn update_ui_timer(
 
 
 pub fn update_ui_flags(
-	cfg:   Res<GameConfig>,
-	flags: Query<Entity, With<Flag>>,
+	q_boards: Query<&GameSession>,
 	mut query: Query<&mut Text, With<FlagsUI>>
 ) {
+	let Ok(session) = q_boards.get_single() else { return };
 	if let Ok(mut text) = query.get_single_mut() {
-		let flags_placed = flags.iter().count();
-		text.sections[1].value = format!("{} / {}", flags_placed, cfg.difficulty.bomb_count);
+		text.sections[1].value = format!("{} / {}", session.flag_count(), session.grid().bomb_count());
+	}
+}
+
+pub fn update_ui_lives(
+	q_boards: Query<&GameSession>,
+	cfg: Res<GameConfig>,
+	mut query: Query<(&mut Text, &mut Visibility), With<LivesUI>>
+) {
+	let Ok(session) = q_boards.get_single() else { return };
+	if let Ok((mut text, mut visibility)) = query.get_single_mut() {
+		*visibility = if cfg.difficulty.lives > 1 { Visibility::Inherited } else { Visibility::Hidden };
+		text.sections[1].value = session.lives().to_string();
+	}
+}
+
+/// Shows the scores of the duel, the player whose turn it is being marked by an arrow
+pub fn update_ui_duel(
+	duel: Option<Res<FlagsDuel>>,
+	mut query: Query<(&mut Text, &mut Visibility), With<DuelUI>>
+) {
+	let Ok((mut text, mut visibility)) = query.get_single_mut() else { return };
+	let Some(duel) = duel else {
+		*visibility = Visibility::Hidden;
+		return
+	};
+	*visibility = Visibility::Inherited;
+	for (player, section, spacing) in [(0, 0, "   "), (1, 2, "")] {
+		let arrow = if duel.turn() == player { "> " } else { "" };
+		text.sections[section].value = format!("{}{}{}: {}", spacing, arrow, duel.name(player), duel.score(player));
 	}
 }
 
@@ -1,12 +1,22 @@
 mod game_over;
+mod generating;
+mod ghost_cells;
 mod main_game_ui;
+mod neighbor_preview;
+mod race_panel;
 mod settings;
+mod versus_header;
 mod victory;
 
 
 pub use game_over::*;
+pub use generating::*;
+pub use ghost_cells::*;
 pub use main_game_ui::*;
+pub use neighbor_preview::*;
+pub use race_panel::*;
 pub use settings::*;
+pub use versus_header::*;
 pub use victory::*;
 
 
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::components::Coordinates;
+use crate::config::GameConfig;
+use crate::input::cursor_cell;
+use crate::resources::GameSession;
+
+
+// The neighbor preview marks the cells counted by the number of the hovered cell.
+// With the classic rules it only lights up the cells around the mouse, but it makes the other kernels readable at a glance.
+
+/// Transparency of the markers, so the cells under them stay readable
+const MARKER_ALPHA: f32 = 0.6;
+
+/// Marks a marker of the preview, with the coordinates of the neighbor it is drawn on
+#[derive(Component)]
+pub struct NeighborMarker(pub Coordinates);
+
+
+/// Marks the neighbors of the cell under the mouse, moving the markers when the mouse moves to another cell
+pub fn update_neighbor_preview(
+	mut cmd: Commands,
+	cfg: Res<GameConfig>,
+	q_boards: Query<&GameSession>,
+	windows_q: Query<&Window>,
+	camera_q: Query<(&Camera, &GlobalTransform)>,
+	q_markers: Query<(Entity, &NeighborMarker)>,
+) {
+	let Ok(session) = q_boards.get_single() else { return };
+	let window = windows_q.single();
+	let (camera, camera_transform) = camera_q.single();
+
+	let grid = session.grid();
+	let mut neighbors: Vec<Coordinates> = cursor_cell(&cfg, window, camera, camera_transform)
+		.map(|coord| grid.get_neighbors(coord).collect())
+		.unwrap_or_default();
+	let mut shown: Vec<Coordinates> = q_markers.iter().map(|(_, marker)| marker.0).collect();
+	neighbors.sort();
+	shown.sort();
+	if neighbors == shown { return }
+
+	for (entity, _) in &q_markers {
+		cmd.entity(entity).despawn();
+	}
+	let cell_size = cfg.ui_style.cell_size as f32;
+	let padding = cfg.ui_style.cell_padding as f32;
+	for coord in neighbors {
+		// In the middle of the cell, over its cover
+		let center = Vec2::splat(cfg.ui_style.margin as f32)
+			+ grid.shape().cell_origin(coord, cell_size)
+			+ Vec2::splat((cell_size + padding) / 2.);
+		cmd.spawn(SpriteBundle {
+			sprite: Sprite {
+				color: cfg.theme.flags.with_a(MARKER_ALPHA),
+				custom_size: Some(Vec2::splat(cell_size / 3.)),
+				..default()
+			},
+			transform: Transform::from_translation(center.extend(10.)),
+			..default()
+		}).insert(NeighborMarker(coord))
+		  .insert(Name::new(format!("Neighbor marker {} - {}", coord.x, coord.y)));
+	}
+}
+
+/// Removes the markers, when the mouse can no longer play on the board
+pub fn despawn_neighbor_preview(mut cmd: Commands, q_markers: Query<Entity, With<NeighborMarker>>) {
+	for entity in &q_markers {
+		cmd.entity(entity).despawn();
+	}
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::config::{GameConfig, RaceSettings};
+use crate::race::Race;
+use crate::resources::{RaceProgress, RaceResult};
+
+
+/// The text of the side panel showing the progress of both players during a race
+#[derive(Component)]
+pub struct RacePanelUI;
+
+pub fn spawn_race_panel(
+	mut cmd: Commands,
+	cfg: Res<GameConfig>,
+	srv: Res<AssetServer>,
+) {
+	let font = srv.load("fonts/FiraSans-Black.ttf");
+	let text_style = TextStyle {
+		font_size: 20.,
+		font: font.clone(),
+		color: cfg.theme.main_ui,
+		..default()
+	};
+
+	let panel_box = NodeBundle {
+		style: Style {
+			position_type: PositionType::Absolute,
+			width: Val::Px(cfg.ui_style.side_panel.into()),
+			top: Val::Px(cfg.ui_style.header_size.into()),
+			right: Val::Px(0.),
+			padding: UiRect::all(Val::Px(cfg.ui_style.margin.into())),
+			flex_direction: FlexDirection::Column,
+			..default()
+		}, ..default()
+	};
+	let title_text = TextBundle::from_section("Race\n", TextStyle { font_size: 30., ..text_style.clone() });
+	let panel_text = TextBundle::from_section("", text_style);
+
+	cmd.spawn(panel_box).with_children(|panel| {
+		panel.spawn(title_text);
+		panel.spawn(panel_text).insert(RacePanelUI);
+	});
+}
+
+pub fn update_race_panel(
+	race: Res<Race>,
+	mut query: Query<&mut Text, With<RacePanelUI>>
+) {
+	let Ok(mut text) = query.get_single_mut() else { return };
+	let progress = |progress: &RaceProgress| format!(
+		"{}% revealed\n{} {} hit",
+		progress.percent(), progress.mines_hit, if progress.mines_hit == 1 { "mine" } else { "mines" }
+	);
+	let status = match (&race.result, &race.start, &race.settings) {
+		(Some(RaceResult::Won), ..) => "You won the race !".to_string(),
+		(Some(RaceResult::Lost), ..) => "Your opponent won the race".to_string(),
+		(Some(RaceResult::Draw), ..) => "Draw, you both blew up".to_string(),
+		(Some(RaceResult::OpponentLeft), ..) => "Your opponent left".to_string(),
+		(None, Some(_), _) => "Racing...".to_string(),
+		(None, None, RaceSettings::Host { port }) => format!("Waiting for an opponent on port {}", port),
+		(None, None, RaceSettings::Join { .. }) => "Waiting for the board".to_string(),
+	};
+	text.sections[0].value = format!(
+		"You\n{}\n\nOpponent\n{}\n\n{}",
+		progress(&race.local), progress(&race.opponent), status
+	);
+}
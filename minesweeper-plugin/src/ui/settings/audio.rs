@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use crate::AppState;
+use crate::audio::save_settings;
+use crate::config::GameConfig;
+use super::{button_style, button_text_style};
+
+
+#[derive(Component)]
+pub struct AudioSettingsUI;
+
+#[derive(Debug, Copy, Clone, Event)]
+pub struct GoToAudioSettingsEvent;
+
+// All actions that can be triggered from a button click
+#[derive(Component)]
+pub enum AudioUIButtons {
+	MasterVolume,
+	EffectsVolume,
+	ToggleMute,
+	BackToSettings,
+}
+
+/// Returns the next volume of the buttons, going up by a fifth then back to silence
+fn next_volume(volume: f32) -> f32 {
+	let step = (volume * 5.).round() + 1.;
+	if step > 5. { 0. } else { step / 5. }
+}
+
+
+pub fn spawn_audio_settings_ui(mut commands: Commands, cfg: Res<GameConfig>, srv: Res<AssetServer>) {
+	let font = srv.load("fonts/FiraSans-Black.ttf");
+	// The buttons show the current volumes, and switch to the next ones when clicked
+	let master_text = format!("Master: {:.0}%", cfg.audio.master * 100.);
+	let effects_text = format!("Effects: {:.0}%", cfg.audio.effects * 100.);
+	let mute_text = if cfg.audio.muted { "Sound: off" } else { "Sound: on" };
+
+	commands.spawn((
+		NodeBundle {
+			style: Style {
+				width: Val::Percent(100.0),
+				height: Val::Percent(100.0),
+				align_items: AlignItems::Center,
+				justify_content: JustifyContent::Center,
+				..default()
+			},
+			..default()
+		}, AudioSettingsUI
+	)).with_children(|parent| {
+		parent.spawn(NodeBundle {
+			style: Style {
+				flex_direction: FlexDirection::Column,
+				align_items: AlignItems::Center,
+				..default()
+			},
+			background_color: cfg.theme.menus_bg.into(),
+			..default()
+		}).with_children(|parent| {
+			for (action, text) in [
+				(AudioUIButtons::MasterVolume, master_text.as_str()),
+				(AudioUIButtons::EffectsVolume, effects_text.as_str()),
+				(AudioUIButtons::ToggleMute, mute_text),
+				(AudioUIButtons::BackToSettings, "<== Back"),
+			] {
+				parent.spawn((
+					ButtonBundle {
+						style: button_style(),
+						background_color: cfg.theme.menus_msg.into(),
+						..default()
+					},
+					action,
+				)).with_children(|parent| {
+					parent.spawn(TextBundle::from_section(
+						text,
+						button_text_style(cfg.theme.menus_bg, font.clone()),
+					));
+				});
+			}
+		});
+	});
+}
+
+/// Changes the audio settings and saves them, the page being spawned again to show the new values
+pub fn audio_settings_interaction(
+	mut q_interaction: Query<(&Interaction, &mut BackgroundColor, &AudioUIButtons), (Changed<Interaction>, With<Button>)>,
+	q_ui: Query<Entity, With<AudioSettingsUI>>,
+	mut ev_audio_settings: EventWriter<GoToAudioSettingsEvent>,
+	mut next_state: ResMut<NextState<AppState>>,
+	mut cmd: Commands,
+	mut cfg: ResMut<GameConfig>,
+) {
+	for (interaction, mut background, action) in &mut q_interaction {
+		match *interaction {
+			Interaction::Pressed => {
+				if let Ok(entity) = q_ui.get_single() {
+					cmd.entity(entity).despawn_recursive()
+				}
+				match *action {
+					AudioUIButtons::MasterVolume => cfg.audio.master = next_volume(cfg.audio.master),
+					AudioUIButtons::EffectsVolume => cfg.audio.effects = next_volume(cfg.audio.effects),
+					AudioUIButtons::ToggleMute => cfg.audio.muted = !cfg.audio.muted,
+					AudioUIButtons::BackToSettings => {
+						next_state.set(AppState::InGame);  // Reloading the Settings App State to spawn the settings menu again
+						next_state.set(AppState::Settings);
+						continue
+					},
+				}
+				save_settings(&cfg);
+				ev_audio_settings.send(GoToAudioSettingsEvent);
+			}
+			Interaction::Hovered => {
+				background.0 = cfg.theme.menus_btn;
+			}
+			Interaction::None => {
+				background.0 = cfg.theme.menus_msg;
+			}
+		}
+	}
+}
+
+pub fn despawn_audio_settings_ui(
+	mut cmd: Commands,
+	q_ui: Query<Entity, With<AudioSettingsUI>>,
+) {
+	if let Ok(entity) = q_ui.get_single() {
+		cmd.entity(entity).despawn_recursive()
+	}
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use crate::AppState;
 use crate::config::{GameConfig, DifficultySettings};
+use crate::resources::{Kernel, Technique, TopologyKind};
 use crate::events::{RestartEvent, ChangeDifficultyEvent};
 use crate::ui::{GoToDifficultySettingsEvent, MainSettingsUI};
 use super::{
@@ -22,12 +23,36 @@ pub enum DifficultyUIButtons {
 	Beginner,
 	Intermediate,
 	Expert,
+	ToggleTopology,
+	ToggleKernel,
+	ToggleMinesPerCell,
+	ToggleLives,
+	ToggleCountdown,
+	ToggleHardest,
 	BackToSettings,
 }
 
 
 pub fn spawn_difficulty_settings_ui(mut commands: Commands, cfg: Res<GameConfig>, srv: Res<AssetServer>) {
 	let font = srv.load("fonts/FiraSans-Black.ttf");
+	// The button shows the current topology, and switches to the next one when clicked
+	let topology_text = format!("Grid: {}", cfg.difficulty.topology);
+	let kernel_text = match cfg.difficulty.kernel {
+		Kernel::Custom(_) => "Neighbors: custom".to_string(),
+		ref kernel => format!("Neighbors: {}", kernel),
+	};
+	let mines_text = format!("Mines per cell: {}", cfg.difficulty.mines_per_cell);
+	let lives_text = format!("Lives: {}", cfg.difficulty.lives);
+	let countdown_text = match cfg.difficulty.time_limit {
+		None => "Countdown: off".to_string(),
+		Some(secs) if cfg.difficulty.time_bonus > 0. => format!("Countdown: {}s +{}s", secs, cfg.difficulty.time_bonus),
+		Some(secs) => format!("Countdown: {}s", secs),
+	};
+	// The hardest technique the generated boards may need, a 3BV range only being set on the command line
+	let hardest_text = match cfg.difficulty.filter.map(|filter| filter.hardest) {
+		None | Some(Technique::Guess) => "Hardest deduction: any".to_string(),
+		Some(hardest) => format!("Hardest deduction: {}", hardest.name()),
+	};
 
 	commands.spawn((
 		NodeBundle {
@@ -54,6 +79,12 @@ pub fn spawn_difficulty_settings_ui(mut commands: Commands, cfg: Res<GameConfig>
 				(DifficultyUIButtons::Beginner, "Beginner"),
 				(DifficultyUIButtons::Intermediate, "Intermediate"),
 				(DifficultyUIButtons::Expert, "Expert"),
+				(DifficultyUIButtons::ToggleTopology, topology_text.as_str()),
+				(DifficultyUIButtons::ToggleKernel, kernel_text.as_str()),
+				(DifficultyUIButtons::ToggleMinesPerCell, mines_text.as_str()),
+				(DifficultyUIButtons::ToggleLives, lives_text.as_str()),
+				(DifficultyUIButtons::ToggleCountdown, countdown_text.as_str()),
+				(DifficultyUIButtons::ToggleHardest, hardest_text.as_str()),
 				(DifficultyUIButtons::BackToSettings, "<== Back"),
 			] {
 				parent.spawn((
@@ -101,21 +132,120 @@ pub fn difficulty_settings_interaction(
 				if let Ok(entity) = q_ui.get_single_mut() {
 					cmd.entity(entity).despawn_recursive()
 				}
+				// The standard difficulties keep the current topology, kernel, mines per cell, lives, countdown and filter,
+				// the time limit being adapted to the size of the new grid
+				let topology = cfg.difficulty.topology;
+				let kernel = cfg.difficulty.kernel.clone();
+				let mines_per_cell = cfg.difficulty.mines_per_cell;
+				let lives = cfg.difficulty.lives;
+				let countdown = cfg.difficulty.time_limit.is_some();
+				let time_bonus = cfg.difficulty.time_bonus;
+				let with_countdown = |difficulty: DifficultySettings| {
+					let time_limit = countdown.then(|| difficulty.suggested_time_limit());
+					let difficulty = difficulty.with_countdown(time_limit, time_bonus);
+					// A 3BV range only fits the grid it was chosen for, another grid only keeps the hardest technique
+					match cfg.difficulty.filter {
+						Some(filter) if filter.has_three_bv_range()
+							&& (difficulty.grid_size != cfg.difficulty.grid_size || difficulty.bomb_count != cfg.difficulty.bomb_count) => {
+							info!("The 3BV range of the filter {} does not fit the new grid, only its hardest technique is kept", filter.name());
+							difficulty.with_hardest_technique(filter.hardest)
+						},
+						filter => DifficultySettings { filter, ..difficulty },
+					}
+				};
 				match *action {
 					DifficultyUIButtons::Beginner => {
 						ev_change_difficulty.send(
-							ChangeDifficultyEvent(DifficultySettings::beginner())
+							ChangeDifficultyEvent(with_countdown(DifficultySettings::beginner()
+								.with_topology(topology)
+								.with_kernel(kernel)
+								.with_mines_per_cell(mines_per_cell)
+								.with_lives(lives)
+							))
 						);
 					},
 					DifficultyUIButtons::Intermediate => {
 						ev_change_difficulty.send(
-							ChangeDifficultyEvent(DifficultySettings::intermediate())
+							ChangeDifficultyEvent(with_countdown(DifficultySettings::intermediate()
+								.with_topology(topology)
+								.with_kernel(kernel)
+								.with_mines_per_cell(mines_per_cell)
+								.with_lives(lives)
+							))
 						);
 					},
 					DifficultyUIButtons::Expert => {
 
 						ev_change_difficulty.send(
-							ChangeDifficultyEvent(DifficultySettings::expert())
+							ChangeDifficultyEvent(with_countdown(DifficultySettings::expert()
+								.with_topology(topology)
+								.with_kernel(kernel)
+								.with_mines_per_cell(mines_per_cell)
+								.with_lives(lives)
+							))
+						);
+					},
+					DifficultyUIButtons::ToggleTopology => {
+						let topology = match topology {
+							TopologyKind::Square => TopologyKind::Hex,
+							TopologyKind::Hex => TopologyKind::Torus,
+							TopologyKind::Torus => TopologyKind::Square,
+						};
+						// Hexagonal cells always count their 6 neighbors
+						let kernel = match topology {
+							TopologyKind::Hex => Kernel::Moore,
+							_ => kernel,
+						};
+						ev_change_difficulty.send(
+							ChangeDifficultyEvent(cfg.difficulty.clone().with_topology(topology).with_kernel(kernel))
+						);
+					},
+					DifficultyUIButtons::ToggleKernel => {
+						// The menu is despawned already, so the hexagonal cells keep their 6 neighbors through the same event
+						let kernel = match topology {
+							TopologyKind::Hex => Kernel::Moore,
+							_ => kernel.next(),
+						};
+						ev_change_difficulty.send(
+							ChangeDifficultyEvent(cfg.difficulty.clone().with_kernel(kernel))
+						);
+					},
+					DifficultyUIButtons::ToggleMinesPerCell => {
+						ev_change_difficulty.send(
+							ChangeDifficultyEvent(cfg.difficulty.clone().with_mines_per_cell(mines_per_cell % 3 + 1))
+						);
+					},
+					DifficultyUIButtons::ToggleLives => {
+						let lives = match lives {
+							1 => 3,
+							3 => 5,
+							_ => 1,
+						};
+						ev_change_difficulty.send(
+							ChangeDifficultyEvent(cfg.difficulty.clone().with_lives(lives))
+						);
+					},
+					DifficultyUIButtons::ToggleCountdown => {
+						// Off, then a time limit fitting the grid, then the same limit with a second earned per safe cell
+						let (time_limit, time_bonus) = match (cfg.difficulty.time_limit, cfg.difficulty.time_bonus > 0.) {
+							(None, _) => (Some(cfg.difficulty.suggested_time_limit()), 0.),
+							(Some(secs), false) => (Some(secs), 1.),
+							(Some(_), true) => (None, 0.),
+						};
+						ev_change_difficulty.send(
+							ChangeDifficultyEvent(cfg.difficulty.clone().with_countdown(time_limit, time_bonus))
+						);
+					},
+					DifficultyUIButtons::ToggleHardest => {
+						// Any board, then the boards needing the simplest technique only, then harder and harder ones
+						let hardest = match cfg.difficulty.filter.map(|filter| filter.hardest) {
+							None | Some(Technique::Guess) => Technique::Single,
+							Some(Technique::Single) => Technique::Subset,
+							Some(Technique::Subset) => Technique::MineCount,
+							Some(Technique::MineCount) => Technique::Guess,
+						};
+						ev_change_difficulty.send(
+							ChangeDifficultyEvent(cfg.difficulty.clone().with_hardest_technique(hardest))
 						);
 					},
 					DifficultyUIButtons::BackToSettings=> {
@@ -1,7 +1,9 @@
+mod audio;
 mod difficulty;
 mod themes;
 
 
+pub use audio::*;
 pub use difficulty::*;
 
 use bevy::prelude::*;
@@ -9,10 +11,19 @@ use bevy::prelude::*;
 use crate::{AppState, config::GameConfig, ui::GameOverUI};
 pub use MainSettings::{
 	SettingsDifficulty,
+	SettingsAudio,
+	Daily,
+	Duel,
+	Motion,
 	// SettingsTheme,
+	ImportBoard,
+	ExportBoard,
 	BackToGame
 };
-use crate::events::UncoverCellEvent;
+use crate::animations::ToggleReduceMotionEvent;
+use crate::duel::ToggleDuelEvent;
+use crate::events::{ExportBoardEvent, LoadBoardEvent, StartDailyEvent, UncoverCellEvent};
+use crate::resources::{latest_board_path, BOARDS_DIR};
 
 
 #[derive(Component)]
@@ -23,7 +34,13 @@ pub struct GoToDifficultyMenu;
 #[derive(Component)]
 pub enum MainSettings {
 	SettingsDifficulty,
+	SettingsAudio,
+	Daily,
+	Duel,
+	Motion,
 	// SettingsTheme,
+	ImportBoard,
+	ExportBoard,
 	BackToGame,
 }
 
@@ -90,7 +107,13 @@ pub fn spawn_settings_ui(mut commands: Commands, cfg: Res<GameConfig>, srv: Res<
 		}).with_children(|parent| {
 			for (action, text) in [
 				(MainSettings::SettingsDifficulty, "Difficulty"),
+				(MainSettings::SettingsAudio, "Audio"),
+				(MainSettings::Daily, "Daily"),
+				(MainSettings::Duel, "Duel"),
+				(MainSettings::Motion, "Motion"),
 				// (MainSettings::SettingsTheme, "Themes"),
+				(MainSettings::ImportBoard, "Import"),
+				(MainSettings::ExportBoard, "Export"),
 				(MainSettings::BackToGame, "Back"),
 			] {
 				parent.spawn((
@@ -130,6 +153,12 @@ pub fn main_settings_interaction(
 	q_settings_ui: Query<Entity, With<MainSettingsUI>>,
 	mut cmd: Commands,
 	mut ev_difficulty_settings: EventWriter<GoToDifficultySettingsEvent>,
+	mut ev_audio_settings: EventWriter<GoToAudioSettingsEvent>,
+	mut ev_load_board: EventWriter<LoadBoardEvent>,
+	mut ev_export_board: EventWriter<ExportBoardEvent>,
+	mut ev_daily: EventWriter<StartDailyEvent>,
+	mut ev_duel: EventWriter<ToggleDuelEvent>,
+	mut ev_motion: EventWriter<ToggleReduceMotionEvent>,
 	// mut ev_theme_settings: EventWriter<GoToThemeSettingsEvent>,
 	cfg: Res<GameConfig>,
 	mut next_state: ResMut<NextState<AppState>>,
@@ -144,12 +173,41 @@ pub fn main_settings_interaction(
 						};
 						ev_difficulty_settings.send(GoToDifficultySettingsEvent);
 					},
+					SettingsAudio => {
+						if let Ok(ui_entity) = q_settings_ui.get_single() {
+							cmd.entity(ui_entity).despawn_recursive();
+						};
+						ev_audio_settings.send(GoToAudioSettingsEvent);
+					},
+					// The board of the day, at the current difficulty
+					Daily => {
+						ev_daily.send(StartDailyEvent);
+						next_state.set(AppState::InGame);
+					},
+					// Cycles between the classic game and the Flags duel, on the same computer or against it
+					Duel => {
+						ev_duel.send(ToggleDuelEvent);
+					},
+					// Turns the animations of the board on or off
+					Motion => {
+						ev_motion.send(ToggleReduceMotionEvent);
+					},
 					// SettingsTheme => {
 					// 	if let Ok(ui_entity) = q_settings_ui.get_single() {
 					// 		cmd.entity(ui_entity).despawn_recursive();
 					// 	};
 					// 	ev_theme_settings.send(ThemeSettingsEvent);
 					// },
+					// Imports the most recent board of the boards directory, other boards can be dropped on the window
+					ImportBoard => {
+						match latest_board_path() {
+							Some(path) => { ev_load_board.send(LoadBoardEvent(path)); },
+							None => warn!("No board to import in the {} directory", BOARDS_DIR),
+						}
+					},
+					ExportBoard => {
+						ev_export_board.send(ExportBoardEvent);
+					},
 					BackToGame => {
 						next_state.set(AppState::InGame);
 					},
@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use crate::config::GameConfig;
+use crate::resources::{Clicks, GameMetrics, GameSession, Outcome, RaceResult, ThreeBv};
+use crate::time::GameTime;
+use crate::versus::{Player, VersusBoard, VersusInput, VersusResult};
+
+
+/// The text above the board of a player, showing their device, time, flags and outcome, then the metrics of their game
+#[derive(Component)]
+pub struct VersusHeaderUI(pub Player);
+
+pub fn spawn_versus_header(
+	mut cmd: Commands,
+	cfg: Res<GameConfig>,
+	srv: Res<AssetServer>,
+) {
+	let font = srv.load("fonts/FiraSans-Black.ttf");
+	let text_style = TextStyle {
+		font_size: 20.,
+		font: font.clone(),
+		color: cfg.theme.main_ui,
+		..default()
+	};
+
+	let header_box = NodeBundle {
+		style: Style {
+			position_type: PositionType::Absolute,
+			width: Val::Percent(100.),
+			height: Val::Px(cfg.ui_style.header_size.into()),
+			top: Val::Percent(0.),
+			..default()
+		}, ..default()
+	};
+
+	cmd.spawn(header_box).with_children(|header| {
+		for (player, left) in [(Player::One, 0.), (Player::Two, 50.)] {
+			header.spawn(NodeBundle {
+				style: Style {
+					position_type: PositionType::Absolute,
+					width: Val::Percent(50.),
+					height: Val::Percent(100.),
+					left: Val::Percent(left),
+					padding: UiRect::all(Val::Px(cfg.ui_style.margin.into())),
+					flex_direction: FlexDirection::Column,
+					justify_content: JustifyContent::Center,
+					..default()
+				}, ..default()
+			}).with_children(|text_box| {
+				text_box.spawn(TextBundle::from_section("", text_style.clone()))
+					.insert(VersusHeaderUI(player));
+			});
+		}
+	});
+}
+
+pub fn update_versus_header(
+	cfg: Res<GameConfig>,
+	gamepads: Res<Gamepads>,
+	result: Res<VersusResult>,
+	q_boards: Query<(&VersusBoard, &GameSession, &GameTime, &ThreeBv, &Clicks)>,
+	mut q_text: Query<(&mut Text, &VersusHeaderUI)>,
+) {
+	let inputs = VersusInput::of_players(&gamepads);
+	for (mut text, header) in &mut q_text {
+		let Some((_, session, time, three_bv, clicks)) = q_boards.iter().find(|(board, ..)| board.player == header.0) else { continue };
+		// The result of the game, as seen by this player
+		let result = match (result.0, header.0) {
+			(Some(result), Player::One) => Some(result),
+			(Some(result), Player::Two) => Some(result.opposite()),
+			(None, _) => None,
+		};
+		let status = match (result, session.outcome()) {
+			(Some(RaceResult::Won), _) => "Winner !  Press R to play again",
+			(Some(RaceResult::Lost), _) => "Lost, press R to play again",
+			(Some(_), _) => "Draw, press R to play again",
+			(None, Outcome::Defeat) => "Blew up, hoping for a draw...",
+			(None, _) => "Playing...",
+		};
+		// Once the game of the player is over, the metrics of their game replace the flags
+		let details = match session.outcome() {
+			Outcome::Playing => {
+				let lives = if cfg.difficulty.lives > 1 { format!("   Lives: {}", session.lives()) } else { String::new() };
+				format!("Flags: {}/{}{}", session.flag_count(), session.grid().bomb_count(), lives)
+			},
+			_ => GameMetrics::new(three_bv, session, *clicks, time.stopwatch.elapsed_secs()).to_string(),
+		};
+		text.sections[0].value = format!(
+			"{} ({})\n{}   {}\n{}",
+			header.0, inputs[header.0.index()],
+			time.to_string(), details,
+			status
+		);
+	}
+}
@@ -3,7 +3,8 @@ use bevy::log::info;
 use bevy::prelude::*;
 use crate::config::GameConfig;
 use crate::events::RestartEvent;
-use crate::resources::CoveredCells;
+use crate::resources::{Clicks, CoveredCells, DuelResult, FlagsDuel, GameMetrics, GameSession, HighScores, RankedGame, ThreeBv};
+use crate::time::GameTime;
 
 #[derive(Component)]
 pub struct VictoryUI;
@@ -16,8 +17,32 @@ pub fn spawn_victory_ui(
 	mut cmd: Commands,
 	cfg: Res<GameConfig>,
 	srv: Res<AssetServer>,
+	q_boards: Query<(&GameSession, &GameTime, &ThreeBv, &Clicks)>,
+	ranked: Res<RankedGame>,
+	scores: Res<HighScores>,
+	duel: Option<Res<FlagsDuel>>,
 ) {
+	let Ok((session, time, three_bv, clicks)) = q_boards.get_single() else { return };
 	let font = srv.load("fonts/FiraSans-Black.ttf");
+	// The time of the game, compared to the best one when the game is ranked, and the metrics of the game
+	let secs = time.stopwatch.elapsed_secs();
+	let metrics = GameMetrics::new(three_bv, session, *clicks, secs);
+	let time_text = match ranked.0.as_deref().and_then(|bucket| scores.best(bucket)) {
+		Some(best) => format!("\n{:.2} secs, best: {:.2} secs\n{}", secs, best, metrics),
+		None => format!("\n{:.2} secs\n{}", secs, metrics),
+	};
+	// The duel shows its winner and the scores instead
+	let (title_text, time_text) = match duel.as_deref().and_then(|duel| Some((duel, duel.result(session)?))) {
+		Some((duel, result)) => {
+			let title = match result {
+				DuelResult::Won(player) => format!("{} won the duel !", duel.name(player)),
+				DuelResult::Draw => "It's a draw !".to_string(),
+			};
+			let [first, second] = duel.final_scores(session);
+			(title, format!("\n{} {} - {} {}", duel.name(0), first, second, duel.name(1)))
+		},
+		None => ("Oh woah, You survived !".to_string(), time_text),
+	};
 	let victory_box = NodeBundle {
 		style: Style {
 			width: Val::Percent(100.),
@@ -46,15 +71,20 @@ pub fn spawn_victory_ui(
 	};
 
 
-	let victory_text = TextBundle::from_section(
-		"Oh woah, You survived !",
-		TextStyle {
+	let victory_text = TextBundle::from_sections([
+		TextSection::new(title_text, TextStyle {
 			font_size: 50.,
 			font: font.clone(),
 			color: cfg.theme.menus_msg,
 			..default()
-		},
-	).with_text_justify(JustifyText::Center).with_style(
+		}),
+		TextSection::new(time_text, TextStyle {
+			font_size: 25.,
+			font: font.clone(),
+			color: cfg.theme.menus_msg,
+			..default()
+		}),
+	]).with_text_justify(JustifyText::Center).with_style(
 		Style {
 			align_self: AlignSelf::Center,
 			width: Val::Percent(100.),
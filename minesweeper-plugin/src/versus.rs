@@ -0,0 +1,309 @@
+// This module contains the local versus mode: two players on the same computer, each on their own board.
+// Both boards are entities carrying their own session and timer, played through the same systems as the board of the solo game.
+// The first player plays with the mouse and the second one with the keyboard, or both of them with gamepads.
+
+use core::fmt;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::{
+	BoardRng, GameCamera, request_session, spawn_board, spawn_camera, update_window,
+	audio::{Sound, SoundEvent},
+	board::{BoardBundle, PendingSession},
+	components::Coordinates,
+	config::GameConfig,
+	events::{FlagCellEvent, RestartEvent, UncoverCellEvent, despawn_grid, timeout_event_handler, uncover_event_handler},
+	input::{board_cell, cursor_position, play_click},
+	resources::{Click, Clicks, GameSession, RaceProgress, RaceResult},
+	time::GameTime,
+	ui::{spawn_versus_header, update_versus_header},
+};
+
+
+/// The two players of a versus game, the first one on the left
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Player {
+	One,
+	Two,
+}
+impl Player {
+	pub fn index(&self) -> usize {
+		match self {
+			Player::One => 0,
+			Player::Two => 1,
+		}
+	}
+}
+impl fmt::Display for Player {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Player {}", self.index() + 1)
+	}
+}
+
+/// The device a player plays with
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VersusInput {
+	Mouse,
+	Keyboard,
+	Gamepad(Gamepad),
+}
+impl VersusInput {
+	/// Returns the devices of both players: the gamepads replace the keyboard, then the mouse, as they are connected
+	pub fn of_players(gamepads: &Gamepads) -> [VersusInput; 2] {
+		let mut pads = gamepads.iter();
+		match (pads.next(), pads.next()) {
+			(Some(first), Some(second)) => [VersusInput::Gamepad(first), VersusInput::Gamepad(second)],
+			(Some(pad), None) => [VersusInput::Mouse, VersusInput::Gamepad(pad)],
+			(None, _) => [VersusInput::Mouse, VersusInput::Keyboard],
+		}
+	}
+}
+impl fmt::Display for VersusInput {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			VersusInput::Mouse => write!(f, "mouse"),
+			VersusInput::Keyboard => write!(f, "arrows, space and F"),
+			VersusInput::Gamepad(pad) => write!(f, "gamepad {}", pad.id + 1),
+		}
+	}
+}
+
+/// The board of a player, carrying the `BoardBundle` like the board of the solo game
+#[derive(Component)]
+pub struct VersusBoard {
+	pub player: Player,
+	/// The cell selected by the keyboard or the gamepad
+	pub cursor: Coordinates,
+}
+
+/// The highlight of the cell selected on a board, as a child of the board
+#[derive(Component)]
+pub struct VersusCursor;
+
+/// The result of the game for the first player, None while both players are still playing
+#[derive(Resource, Default)]
+pub struct VersusResult(pub Option<RaceResult>);
+
+
+/// Replaces the solo game with two boards, when the config asks for a versus game
+///
+/// The boards are played by the systems of the `BoardPlugin`, this plugin only adds the inputs of the players and decides the winner
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<VersusResult>()
+			.add_systems(Startup, (update_window, spawn_camera, versus_setup, spawn_versus_header))
+			.add_systems(Update, (
+				versus_mouse_input, versus_cursor_input, versus_restart_input,
+				decide_versus_result.after(uncover_event_handler).after(timeout_event_handler),
+				update_versus_cursors, update_versus_header,
+				finish_versus_setup.run_if(resource_exists::<PendingSession>).before(despawn_grid),
+				(despawn_grid, versus_setup.after(despawn_grid)).run_if(on_event::<RestartEvent>()),
+			));
+	}
+}
+
+
+/// Returns the position of the bottom left corner of a player's board
+fn board_position(cfg: &GameConfig, player: Player) -> Vec2 {
+	let margin = cfg.ui_style.margin as f32;
+	Vec2::new(margin + player.index() as f32 * (cfg.board_size().x + margin), margin)
+}
+
+/// Spawns the boards of both players, on the same grid so the game is fair
+fn versus_setup(
+	mut cmd: Commands,
+	cfg: Res<GameConfig>,
+	srv: Res<AssetServer>,
+	mut rng: ResMut<BoardRng>,
+	mut result: ResMut<VersusResult>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+	result.0 = None;
+	cmd.remove_resource::<PendingSession>();
+	if let Some(session) = request_session(&mut cmd, &cfg, &mut rng.0) {
+		spawn_versus_boards(&mut cmd, &session, &cfg, &srv, &mut meshes, &mut materials);
+	}
+}
+
+/// Spawns the boards of both players once their grid is generated in the background
+fn finish_versus_setup(
+	mut cmd: Commands,
+	cfg: Res<GameConfig>,
+	srv: Res<AssetServer>,
+	mut pending: ResMut<PendingSession>,
+	mut meshes: ResMut<Assets<Mesh>>,
+	mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+	let Some(session) = pending.poll() else { return };
+	cmd.remove_resource::<PendingSession>();
+	spawn_versus_boards(&mut cmd, &session, &cfg, &srv, &mut meshes, &mut materials);
+}
+
+/// Spawns a board for each player, both playing the same session
+fn spawn_versus_boards(
+	cmd: &mut Commands,
+	session: &GameSession,
+	cfg: &GameConfig,
+	srv: &AssetServer,
+	meshes: &mut Assets<Mesh>,
+	materials: &mut Assets<ColorMaterial>,
+) {
+	debug!("{}", session.grid().console_output());
+	let start = Coordinates::new(session.grid().width() / 2, session.grid().height() / 2);
+
+	for player in [Player::One, Player::Two] {
+		let (board, covers) = spawn_board(cmd, session, cfg, srv, meshes, materials, board_position(cfg, player));
+		cmd.entity(board)
+		   .insert(BoardBundle::new(session.clone(), covers, cfg))
+		   .insert(VersusBoard { player, cursor: start })
+		   .insert(Name::new(format!("Board of {}", player)))
+		   .with_children(|parent| {
+			   parent.spawn(SpriteBundle {
+				   sprite: Sprite {
+					   color: cfg.theme.flags.with_a(0.4),
+					   custom_size: Some(Vec2::splat((cfg.ui_style.cell_size - cfg.ui_style.cell_padding) as f32)),
+					   anchor: Anchor::BottomLeft,
+					   ..default()
+				   },
+				   ..default()
+			   }).insert(VersusCursor).insert(Name::new("Cursor"));
+		   });
+	}
+}
+
+/// Plays with the mouse on the board of the player using it
+fn versus_mouse_input(
+	cfg: Res<GameConfig>,
+	gamepads: Res<Gamepads>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	windows_q: Query<&Window>,
+	camera_q: Query<(&Camera, &GlobalTransform), With<GameCamera>>,
+	mut q_boards: Query<(Entity, &VersusBoard, &GlobalTransform, &GameSession, &mut Clicks)>,
+	mut ev_uncover: EventWriter<UncoverCellEvent>,
+	mut ev_flag: EventWriter<FlagCellEvent>,
+	mut ev_sound: EventWriter<SoundEvent>,
+) {
+	let inputs = VersusInput::of_players(&gamepads);
+	let (Ok(window), Ok((camera, camera_transform))) = (windows_q.get_single(), camera_q.get_single()) else { return };
+	let Some(world_position) = cursor_position(window, camera, camera_transform) else { return };
+
+	for button in mouse_button.get_just_pressed() {
+		let click = match button {
+			MouseButton::Left => Click::Left,
+			MouseButton::Middle => Click::Chord,
+			MouseButton::Right => Click::Right,
+			_ => continue,
+		};
+		for (board, versus, transform, session, mut clicks) in &mut q_boards {
+			if inputs[versus.player.index()] != VersusInput::Mouse { continue }
+			let Some(coordinates) = board_cell(&cfg, world_position - transform.translation().truncate()) else { continue };
+			if play_click(click, board, coordinates, session, &mut clicks, &mut ev_uncover, &mut ev_flag) {
+				ev_sound.send(SoundEvent(Sound::Chord));
+			}
+		}
+	}
+}
+
+/// Moves the cursors of the players using the keyboard or a gamepad, and plays on the selected cells
+fn versus_cursor_input(
+	cfg: Res<GameConfig>,
+	gamepads: Res<Gamepads>,
+	keys: Res<ButtonInput<KeyCode>>,
+	pad_buttons: Res<ButtonInput<GamepadButton>>,
+	mut q_boards: Query<(Entity, &mut VersusBoard, &GameSession, &mut Clicks)>,
+	mut ev_uncover: EventWriter<UncoverCellEvent>,
+	mut ev_flag: EventWriter<FlagCellEvent>,
+	mut ev_sound: EventWriter<SoundEvent>,
+) {
+	let inputs = VersusInput::of_players(&gamepads);
+	let size = cfg.difficulty.grid_size;
+	for (entity, mut board, session, mut clicks) in &mut q_boards {
+		// The moves, the reveal and the flag of the player's device
+		let pressed = match inputs[board.player.index()] {
+			VersusInput::Mouse => continue,
+			VersusInput::Keyboard => [
+				KeyCode::ArrowLeft, KeyCode::ArrowRight, KeyCode::ArrowDown, KeyCode::ArrowUp, KeyCode::Space, KeyCode::KeyF,
+			].map(|key| keys.just_pressed(key)),
+			VersusInput::Gamepad(gamepad) => [
+				GamepadButtonType::DPadLeft, GamepadButtonType::DPadRight, GamepadButtonType::DPadDown, GamepadButtonType::DPadUp,
+				GamepadButtonType::South, GamepadButtonType::East,
+			].map(|button| pad_buttons.just_pressed(GamepadButton::new(gamepad, button))),
+		};
+		let [left, right, down, up, reveal, flag] = pressed;
+		let cursor = board.cursor;
+		let x = (cursor.x as i32 + right as i32 - left as i32).clamp(0, size.x as i32 - 1) as u16;
+		let y = (cursor.y as i32 + up as i32 - down as i32).clamp(0, size.y as i32 - 1) as u16;
+		if x != cursor.x || y != cursor.y {
+			board.cursor = Coordinates::new(x, y);
+		}
+		// Revealing a revealed number chords on it, like a left click
+		if reveal {
+			if play_click(Click::Left, entity, board.cursor, session, &mut clicks, &mut ev_uncover, &mut ev_flag) {
+				ev_sound.send(SoundEvent(Sound::Chord));
+			}
+		}
+		if flag {
+			play_click(Click::Right, entity, board.cursor, session, &mut clicks, &mut ev_uncover, &mut ev_flag);
+		}
+	}
+}
+
+/// Starts a new game on both boards with R, or the start button of a gamepad
+fn versus_restart_input(
+	keys: Res<ButtonInput<KeyCode>>,
+	gamepads: Res<Gamepads>,
+	pad_buttons: Res<ButtonInput<GamepadButton>>,
+	mut ev_restart: EventWriter<RestartEvent>,
+) {
+	let start = gamepads.iter().any(|gamepad| pad_buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::Start)));
+	if keys.just_pressed(KeyCode::KeyR) || start {
+		ev_restart.send(RestartEvent);
+	}
+}
+
+/// Decides the winner as soon as possible: the first player to clear the board wins, like in a race
+fn decide_versus_result(
+	q_boards: Query<(&VersusBoard, &GameSession, &GameTime)>,
+	mut result: ResMut<VersusResult>,
+) {
+	if result.0.is_some() { return }
+	let mut progress = [RaceProgress::default(); 2];
+	for (board, session, _) in &q_boards {
+		progress[board.player.index()] = RaceProgress::of(session);
+	}
+	if let Some(decided) = RaceResult::decide(&progress[0], &progress[1]) {
+		match decided {
+			RaceResult::Won => info!("{} wins!", Player::One),
+			RaceResult::Lost => info!("{} wins!", Player::Two),
+			_ => info!("Both players blew up, it's a draw"),
+		}
+		result.0 = Some(decided);
+		for (board, _, time) in &q_boards {
+			debug!("{} played for {:.2} secs", board.player, time.stopwatch.elapsed_secs());
+		}
+	}
+}
+
+/// Moves the highlights to the selected cells, only showing them to the players without a mouse
+fn update_versus_cursors(
+	cfg: Res<GameConfig>,
+	gamepads: Res<Gamepads>,
+	q_boards: Query<&VersusBoard>,
+	mut q_cursors: Query<(&Parent, &mut Transform, &mut Visibility), With<VersusCursor>>,
+) {
+	let inputs = VersusInput::of_players(&gamepads);
+	for (parent, mut transform, mut visibility) in &mut q_cursors {
+		let Ok(board) = q_boards.get(parent.get()) else { continue };
+		let origin = cfg.difficulty.topology.topology().cell_origin(board.cursor, cfg.ui_style.cell_size as f32)
+			+ Vec2::splat(cfg.ui_style.cell_padding as f32);
+		// Over the covers and the flags
+		transform.translation = origin.extend(7.);
+		*visibility = match inputs[board.player.index()] {
+			VersusInput::Mouse => Visibility::Hidden,
+			_ => Visibility::Inherited,
+		};
+	}
+}
@@ -0,0 +1,54 @@
+// Tests of the text format of the boards, on a fixture board and on the boards written by the game
+
+use minesweeper_plugin::components::Coordinates;
+use minesweeper_plugin::resources::{CellState, GameSession, Grid, Kernel, Outcome, TopologyKind};
+
+
+/// A game saved in the middle, with holes, flags, a custom kernel and a stack of mines
+const MID_GAME: &str = include_str!("boards/mid_game.txt");
+
+#[test]
+fn the_fixture_board_loads_as_it_was_saved() {
+	let session: GameSession = MID_GAME.parse().unwrap();
+	let grid = session.grid();
+	assert_eq!((grid.width(), grid.height(), grid.bomb_count()), (7, 5, 6));
+	assert_eq!((grid.kernel(), grid.mines_per_cell()), (&Kernel::Knight, 2));
+	assert_eq!(grid.mines_at(Coordinates::new(5, 4)), 2);
+	assert!(!grid.contains(Coordinates::new(0, 0)) && !grid.contains(Coordinates::new(6, 4)));
+	assert_eq!(session.state(Coordinates::new(0, 1)), Some(CellState::Revealed));
+	assert_eq!(session.state(Coordinates::new(4, 3)), Some(CellState::Flagged));
+	assert_eq!((session.flags_at(Coordinates::new(5, 4)), session.flag_count()), (2, 3));
+	assert_eq!(session.outcome(), Outcome::Playing);
+	// The game writes it back as it was, without the comment
+	let written: String = MID_GAME.lines().filter(|line| !line.starts_with('#')).map(|line| format!("{}\n", line)).collect();
+	assert_eq!(session.to_string(), written);
+}
+
+#[test]
+fn the_written_boards_are_read_back() {
+	let hex = "topology: hex\n..*\n*.o\nf..\n";
+	let torus = "topology: torus\nkernel: custom 1,2 -1,2\n.*..\n..X.\n";
+	for text in [MID_GAME, hex, torus] {
+		let session: GameSession = text.parse().unwrap();
+		assert_eq!(session.to_string().parse::<GameSession>().unwrap(), session);
+		let grid = session.grid().clone();
+		assert_eq!(grid.to_string().parse::<Grid>().unwrap(), grid);
+	}
+	assert_eq!(hex.parse::<Grid>().unwrap().topology(), TopologyKind::Hex);
+}
+
+#[test]
+fn a_finished_board_loads_finished() {
+	let lost: GameSession = "o.*\noX.\n".parse().unwrap();
+	assert_eq!(lost.outcome(), Outcome::Defeat);
+	assert_eq!(lost.mine_hit(), Some(Coordinates::new(1, 0)));
+	let won: GameSession = "oo*\nooF\n".parse().unwrap();
+	assert_eq!(won.outcome(), Outcome::Victory);
+	assert!("oo\no".parse::<GameSession>().is_err());
+}
+
+#[test]
+fn a_hex_board_only_counts_its_six_neighbors() {
+	assert!("topology: hex\nkernel: knight\n..*\n*..\n".parse::<Grid>().is_err());
+	assert!("topology: hex\nkernel: moore\n..*\n*..\n".parse::<Grid>().is_ok());
+}
@@ -0,0 +1,59 @@
+// Tests of the formats of the community tools, the MBF boards and the RawVF replays
+
+use minesweeper_plugin::components::Coordinates;
+use minesweeper_plugin::resources::{Grid, Replay, ReplayAction};
+
+
+/// A 4x3 board with its mines in three corners, the top left one being the first of the MBF file
+fn board() -> Grid {
+	"*..*\n....\n*...\n".parse().unwrap()
+}
+
+/// A short game on the board, with times written exactly with two decimals
+fn replay() -> Replay {
+	let mut replay = Replay::new(board());
+	replay.record(0.5, ReplayAction::Reveal, Coordinates::new(2, 0));
+	replay.record(1.25, ReplayAction::Flag, Coordinates::new(0, 0));
+	replay.record(2., ReplayAction::Reveal, Coordinates::new(1, 2));
+	replay
+}
+
+#[test]
+fn mbf_boards_are_read_back() {
+	let bytes = board().to_mbf().unwrap();
+	assert_eq!(bytes, [4, 3, 0, 3, 0, 0, 3, 0, 0, 2]);
+	assert_eq!(Grid::from_mbf(&bytes).unwrap(), board());
+}
+
+#[test]
+fn truncated_mbf_boards_are_rejected() {
+	let bytes = board().to_mbf().unwrap();
+	for len in 0..bytes.len() {
+		assert!(Grid::from_mbf(&bytes[..len]).is_err(), "{} bytes", len);
+	}
+	// A mine out of the grid
+	assert!(Grid::from_mbf(&[4, 3, 0, 1, 4, 0]).is_err());
+}
+
+#[test]
+fn rawvf_replays_are_read_back() {
+	let text = replay().to_rawvf().unwrap();
+	assert!(text.contains("Board:\n*00*\n0000\n*000\n"), "{}", text);
+	let read = Replay::from_rawvf(&text).unwrap();
+	assert_eq!(read.grid, board());
+	assert_eq!(read.events, replay().events);
+}
+
+#[test]
+fn truncated_rawvf_replays_are_rejected() {
+	let text = replay().to_rawvf().unwrap();
+	// The last row of the board ends one character before its line break
+	let board_end = text.find("Events:").unwrap() - 2;
+	// Cut anywhere before the end of the board, even in the middle of a row
+	for len in (0..board_end).filter(|&len| text.is_char_boundary(len)) {
+		assert!(Replay::from_rawvf(&text[..len]).is_err(), "{:?}", &text[..len]);
+	}
+	// Cut in the events, the events read so far are kept
+	let cut = text.find("2.00").unwrap();
+	assert_eq!(Replay::from_rawvf(&text[..cut]).unwrap().events, replay().events[..2]);
+}
@@ -0,0 +1,374 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use minesweeper_plugin::components::Coordinates;
+use minesweeper_plugin::config::{ColorTheme, CoverAnimation, DifficultySettings, DuelOpponent, GameConfig, RaceSettings, StandardDifficulty};
+use minesweeper_plugin::resources::{BoardFile, BoardFilter, BoardToLoad, DailyChallenge, Kernel, Mask, Technique, TopologyKind, MAX_NO_GUESS_DENSITY, RACE_PORT};
+
+
+/// The three standard minesweeper difficulties
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Difficulty {
+	Beginner,
+	Intermediate,
+	Expert,
+}
+
+/// The shapes of board
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Shape {
+	Square,
+	Hex,
+	Torus,
+}
+
+/// The predefined neighborhoods, deciding which cells are counted by the numbers
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Neighbors {
+	/// The 8 cells around a cell
+	Moore,
+	/// The 4 cells sharing a side with a cell
+	Orthogonal,
+	/// The 8 cells a chess knight could jump to
+	Knight,
+	/// The 24 cells of the 5x5 square around a cell
+	Radius2,
+}
+
+/// The opponents of the Flags duel
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Duel {
+	/// Two players taking turns on the same computer
+	HotSeat,
+	/// Playing against the computer
+	Computer,
+}
+
+/// The hardest deductions a generated board may need, from the simplest
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Deduction {
+	/// A single number, either satisfied or needing all its covered neighbors
+	Single,
+	/// Two numbers, the covered neighbors of one being part of the covered neighbors of the other
+	Subset,
+	/// The count of the mines left on the board
+	MineCount,
+}
+
+/// The animations of the covers of the revealed cells
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Animation {
+	Fade,
+	Flip,
+}
+
+/// A minesweeper game, made with bevy
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+	/// Starts the game with one of the standard difficulties
+	#[arg(long, value_enum, conflicts_with_all = ["width", "height", "mines"])]
+	pub difficulty: Option<Difficulty>,
+
+	/// Width of a custom grid, requires --height and --mines
+	#[arg(long, requires_all = ["height", "mines"])]
+	pub width: Option<u16>,
+
+	/// Height of a custom grid, requires --width and --mines
+	#[arg(long, requires_all = ["width", "mines"])]
+	pub height: Option<u16>,
+
+	/// Number of mines of a custom grid, requires --width and --height, or --mask
+	#[arg(long)]
+	pub mines: Option<u16>,
+
+	/// Shapes the board with a mask, a small PNG image or a text drawing with `X` for the cells and `.` for the holes
+	///
+	/// Without --mines, the board has the same density of mines as the difficulty
+	#[arg(long, value_name = "FILE", conflicts_with_all = ["width", "height", "board"])]
+	pub mask: Option<PathBuf>,
+
+	/// Shape of the board, hexagonal cells have 6 neighbors and the edges of a torus wrap around
+	#[arg(long, value_enum, conflicts_with = "board")]
+	pub topology: Option<Shape>,
+
+	/// Cells counted by the numbers, on square and torus boards
+	#[arg(long, value_enum, conflicts_with_all = ["board", "kernel_file"])]
+	pub kernel: Option<Neighbors>,
+
+	/// Loads a custom kernel drawn in a text file, with `X` for the cell and `o` for the counted cells
+	#[arg(long, value_name = "FILE", conflicts_with = "board")]
+	pub kernel_file: Option<PathBuf>,
+
+	/// Maximum number of mines in a single cell, the numbers count every mine and the flags of a cell are cycled
+	#[arg(long, value_parser = clap::value_parser!(u8).range(1..=9), conflicts_with = "board")]
+	pub mines_per_cell: Option<u8>,
+
+	/// Number of mines the player can hit, every mine but the last one costing a life instead of ending the game
+	#[arg(long, value_parser = clap::value_parser!(u8).range(1..))]
+	pub lives: Option<u8>,
+
+	/// Plays against a countdown of this many seconds, the game is lost when it reaches zero
+	#[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u32).range(1..))]
+	pub time_limit: Option<u32>,
+
+	/// Seconds added to the countdown for every safe cell revealed, requires --time-limit
+	#[arg(long, value_name = "SECS", requires = "time_limit")]
+	pub time_bonus: Option<f32>,
+
+	/// Seed of the grid generation, the same seed always gives the same grids
+	#[arg(long)]
+	pub seed: Option<u64>,
+
+	/// Plays the board of the day, the same for every player, at the difficulty given by --difficulty or intermediate
+	///
+	/// Only the first attempt of the day is ranked
+	#[arg(long, conflicts_with_all = [
+		"width", "height", "mines", "mask", "topology", "kernel", "kernel_file", "mines_per_cell",
+		"lives", "time_limit", "seed", "no_guess", "board",
+	])]
+	pub daily: bool,
+
+	/// Hosts a race on the local network, the other player joins with --join-race and both play the same board
+	///
+	/// The race is played with the grid size, the mines and the lives of the host, on a classic board
+	#[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "7878", conflicts_with_all = ["board", "daily", "join_race"])]
+	pub host_race: Option<u16>,
+
+	/// Joins a race hosted on the local network, like `--join-race 192.168.1.12` or `--join-race localhost:7878`
+	#[arg(long, value_name = "ADDRESS", conflicts_with_all = ["board", "daily"])]
+	pub join_race: Option<String>,
+
+	/// Two players on the same computer, each on their own copy of the board: the first one plays with the mouse,
+	/// the second one with the arrows, space to reveal and F to flag
+	///
+	/// Connected gamepads replace the keyboard, then the mouse, R starts a new game
+	#[arg(long, conflicts_with_all = ["board", "host_race", "join_race"])]
+	pub versus: bool,
+
+	/// Plays the Flags duel: finding a mine scores a point and grants another turn, the first to claim more than half of the mines wins
+	#[arg(long, value_enum, num_args = 0..=1, default_missing_value = "hot-seat", conflicts_with_all = [
+		"daily", "versus", "host_race", "join_race",
+	])]
+	pub duel: Option<Duel>,
+
+	/// Loads the colors from a theme file, containing lines like `background = 203c56`
+	#[arg(long, value_name = "FILE")]
+	pub theme: Option<PathBuf>,
+
+	/// Size of a cell in pixels
+	#[arg(long, value_parser = clap::value_parser!(u16).range(10..=100))]
+	pub cell_size: Option<u16>,
+
+	/// Animation of the covers leaving the revealed cells
+	#[arg(long, value_enum, conflicts_with = "reduce_motion")]
+	pub cover_animation: Option<Animation>,
+
+	/// Removes the covers and places the flags at once, without any animation
+	#[arg(long)]
+	pub reduce_motion: bool,
+
+	/// Plays without any sound, the volumes are set in the settings menu
+	#[arg(long)]
+	pub mute: bool,
+
+	/// Only generate grids that can be solved without guessing, with at most 23% of the cells holding a mine
+	#[arg(long)]
+	pub no_guess: bool,
+
+	/// Only generate grids whose 3BV, the least number of clicks needed to clear them, is in a range like `150-180`
+	///
+	/// The ranked games played on filtered grids get their own high scores
+	#[arg(long, value_name = "MIN-MAX", conflicts_with_all = ["daily", "board"])]
+	pub three_bv: Option<String>,
+
+	/// Only generate grids that can be solved without guessing, using no deduction harder than this one
+	#[arg(long, value_enum, conflicts_with_all = ["daily", "board"])]
+	pub hardest: Option<Deduction>,
+
+	/// Retrying a board after a loss also replays its first click, instead of starting on a covered board
+	#[arg(long)]
+	pub retry_first_click: bool,
+
+	/// Starts the game on a board file, the following games use the same grid size
+	///
+	/// MBF boards (`.mbf`) and RawVF replays (`.rawvf`) are also accepted, a replay is played back
+	#[arg(long, value_name = "FILE", conflicts_with_all = ["difficulty", "width", "height", "mines", "no_guess"])]
+	pub board: Option<PathBuf>,
+}
+
+impl Cli {
+	/// Builds the game config from the options, checking the ones clap cannot check by itself
+	///
+	/// Also returns the board to start the game on, if one was given
+	pub fn into_config(self) -> Result<(GameConfig, BoardToLoad), String> {
+		// Without any option about the difficulty, the difficulty saved from the settings menu is played
+		let difficulty_given = self.board.is_some() || self.daily || self.no_guess
+			|| self.difficulty.is_some() || self.width.is_some() || self.height.is_some() || self.mines.is_some()
+			|| self.mask.is_some() || self.topology.is_some() || self.kernel.is_some() || self.kernel_file.is_some()
+			|| self.mines_per_cell.is_some() || self.lives.is_some() || self.time_limit.is_some() || self.time_bonus.is_some()
+			|| self.three_bv.is_some() || self.hardest.is_some();
+		let mut cfg = GameConfig { difficulty_given, ..GameConfig::default() };
+		if self.time_bonus.is_some_and(|bonus| bonus.is_nan() || bonus < 0.) {
+			return Err("The time bonus cannot be negative".to_string());
+		}
+
+		if let Some(path) = &self.board {
+			let file = BoardFile::load(path)?;
+			let grid = file.grid();
+			cfg.difficulty.grid_size = Coordinates::new(grid.width(), grid.height());
+			cfg.difficulty.bomb_count = grid.bomb_count();
+			cfg.difficulty.topology = grid.topology();
+			cfg.difficulty.kernel = grid.kernel().clone();
+			cfg.difficulty.mask = grid.mask().cloned();
+			cfg.difficulty.mines_per_cell = grid.mines_per_cell();
+			cfg.difficulty.lives = self.lives.unwrap_or(1);
+			cfg.difficulty = cfg.difficulty.with_countdown(self.time_limit, self.time_bonus.unwrap_or(0.));
+			cfg.generation.seed = self.seed;
+			return Ok((self.apply_style(cfg)?, BoardToLoad(Some(file))));
+		}
+
+		if self.daily {
+			let level = match self.difficulty {
+				Some(Difficulty::Beginner) => StandardDifficulty::Beginner,
+				Some(Difficulty::Intermediate) | None => StandardDifficulty::Intermediate,
+				Some(Difficulty::Expert) => StandardDifficulty::Expert,
+			};
+			let daily = DailyChallenge::today(level);
+			cfg.difficulty = daily.difficulty();
+			cfg.generation.daily = Some(daily);
+			return Ok((self.apply_style(cfg)?, BoardToLoad(None)));
+		}
+
+		cfg.difficulty = match (self.difficulty, self.width, self.height, self.mines) {
+			(Some(Difficulty::Beginner), ..) => DifficultySettings::beginner(),
+			(Some(Difficulty::Intermediate), ..) => DifficultySettings::intermediate(),
+			(Some(Difficulty::Expert), ..) => DifficultySettings::expert(),
+			(None, Some(width), Some(height), Some(mines)) => {
+				if width < 2 || height < 2 {
+					return Err(format!("The grid must be at least 2x2, got {width}x{height}"));
+				}
+				// The shape, the lives, the countdown and the filter are set by their own options below
+				DifficultySettings {
+					grid_size: Coordinates::new(width, height),
+					bomb_count: mines,
+					..DifficultySettings::default()
+				}
+			},
+			(None, None, None, Some(_)) if self.mask.is_none() => {
+				return Err("--mines requires --width and --height, or --mask".to_string());
+			},
+			_ => DifficultySettings::default(),
+		};
+		if let Some(path) = &self.mask {
+			let mask = Mask::load(path)?;
+			let density = cfg.difficulty.bomb_count as f32 / (cfg.difficulty.grid_size.x as f32 * cfg.difficulty.grid_size.y as f32);
+			cfg.difficulty.bomb_count = self.mines.unwrap_or(((mask.enabled_count() as f32 * density).round() as u16).max(1));
+			cfg.difficulty = cfg.difficulty.with_mask(mask);
+		}
+		cfg.difficulty.topology = match self.topology {
+			Some(Shape::Hex) => TopologyKind::Hex,
+			Some(Shape::Torus) => TopologyKind::Torus,
+			Some(Shape::Square) | None => TopologyKind::Square,
+		};
+		cfg.difficulty.kernel = match (self.kernel, &self.kernel_file) {
+			(_, Some(path)) => Kernel::from_file(path)?,
+			(Some(Neighbors::Orthogonal), _) => Kernel::Orthogonal,
+			(Some(Neighbors::Knight), _) => Kernel::Knight,
+			(Some(Neighbors::Radius2), _) => Kernel::Radius2,
+			(Some(Neighbors::Moore) | None, _) => Kernel::Moore,
+		};
+		cfg.difficulty.topology.check_kernel(&cfg.difficulty.kernel)?;
+
+		let cells = match &cfg.difficulty.mask {
+			Some(mask) => mask.enabled_count(),
+			None => cfg.difficulty.grid_size.x as u32 * cfg.difficulty.grid_size.y as u32,
+		};
+		// The first opening of a no-guess grid needs a cell and all its neighbors free of mines
+		let opening = match cfg.difficulty.topology {
+			TopologyKind::Square | TopologyKind::Torus => cfg.difficulty.kernel.offsets().len() as u32 + 1,
+			TopologyKind::Hex => 7,
+		};
+		let mines_per_cell = self.mines_per_cell.unwrap_or(1);
+		cfg.difficulty.mines_per_cell = mines_per_cell;
+		let safe_cells = if self.no_guess { opening } else { 1 };
+		let max_mines = cells.saturating_sub(safe_cells) * mines_per_cell as u32;
+		if cfg.difficulty.bomb_count as u32 > max_mines {
+			return Err(format!(
+				"Too many mines: a {}x{} grid{} holds at most {} mines{}",
+				cfg.difficulty.grid_size.x, cfg.difficulty.grid_size.y,
+				if cfg.difficulty.mask.is_some() { " shaped by the mask" } else { "" },
+				max_mines,
+				if self.no_guess { " with --no-guess" } else { "" }
+			));
+		}
+		if cfg.difficulty.bomb_count == 0 {
+			return Err("The grid needs at least one mine".to_string());
+		}
+		// Denser grids can hardly ever be solved without guessing, the generation would only give up after a long time
+		let density = cfg.difficulty.bomb_count as f32 / cells as f32;
+		if self.no_guess && density > MAX_NO_GUESS_DENSITY {
+			return Err(format!(
+				"Too many mines for --no-guess: {:.0}% of the cells hold a mine, at most {:.0}% can be solved without guessing",
+				density * 100., MAX_NO_GUESS_DENSITY * 100.
+			));
+		}
+
+		if let Some(range) = &self.three_bv {
+			let bounds = range.split_once('-')
+				.and_then(|(min, max)| Some((min.trim().parse::<u32>().ok()?, max.trim().parse::<u32>().ok()?)))
+				.filter(|(min, max)| min <= max);
+			let Some((min, max)) = bounds else {
+				return Err(format!("Invalid 3BV range {range}, expected a range like 150-180"));
+			};
+			cfg.difficulty = cfg.difficulty.with_filter(BoardFilter::three_bv(min, max));
+		}
+		if let Some(hardest) = self.hardest {
+			cfg.difficulty = cfg.difficulty.with_hardest_technique(match hardest {
+				Deduction::Single => Technique::Single,
+				Deduction::Subset => Technique::Subset,
+				Deduction::MineCount => Technique::MineCount,
+			});
+		}
+
+		cfg.difficulty.lives = self.lives.unwrap_or(1);
+		cfg.difficulty = cfg.difficulty.with_countdown(self.time_limit, self.time_bonus.unwrap_or(0.));
+		cfg.generation.seed = self.seed;
+		cfg.generation.no_guess = self.no_guess;
+		Ok((self.apply_style(cfg)?, BoardToLoad(None)))
+	}
+
+	/// Applies the options changing the look of the game, the race to join, the versus mode, the duel and the retries
+	fn apply_style(self, mut cfg: GameConfig) -> Result<GameConfig, String> {
+		cfg.versus = self.versus;
+		cfg.generation.retry_first_click = self.retry_first_click;
+		cfg.duel = self.duel.map(|duel| match duel {
+			Duel::HotSeat => DuelOpponent::HotSeat,
+			Duel::Computer => DuelOpponent::Computer,
+		});
+		cfg.race = match (self.host_race, &self.join_race) {
+			(Some(port), _) => Some(RaceSettings::Host { port }),
+			// The default port is used when the address has none
+			(None, Some(address)) if address.contains(':') => Some(RaceSettings::Join { address: address.clone() }),
+			(None, Some(address)) => Some(RaceSettings::Join { address: format!("{}:{}", address, RACE_PORT) }),
+			(None, None) => None,
+		};
+		if let Some(cell_size) = self.cell_size {
+			// Keeping the same ratio between the numbers and the cells as the default style
+			cfg.ui_style.font_size = cell_size * cfg.ui_style.font_size / cfg.ui_style.cell_size;
+			cfg.ui_style.cell_size = cell_size;
+		}
+		if let Some(animation) = self.cover_animation {
+			cfg.ui_style.cover_animation = match animation {
+				Animation::Fade => CoverAnimation::Fade,
+				Animation::Flip => CoverAnimation::Flip,
+			};
+		}
+		cfg.ui_style.reduce_motion = self.reduce_motion;
+		cfg.audio.muted = self.mute;
+		if let Some(path) = self.theme {
+			cfg.theme = ColorTheme::from_file(path)?;
+		}
+		Ok(cfg)
+	}
+}
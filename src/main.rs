@@ -1,10 +1,19 @@
 use bevy::log::LogPlugin;
 use bevy::prelude::*;
 use bevy::window::EnabledButtons;
+use clap::{CommandFactory, Parser};
+use clap::error::ErrorKind;
 use minesweeper_plugin::MinesweeperPlugin;
 
+mod cli;
+
 
 fn main() {
+    let (config, board) = match cli::Cli::parse().into_config() {
+        Ok(options) => options,
+        Err(msg) => cli::Cli::command().error(ErrorKind::ValueValidation, msg).exit(),
+    };
+
     let mut app = App::new();
      app.add_plugins(DefaultPlugins.set(
          WindowPlugin {
@@ -24,6 +33,9 @@ fn main() {
          filter: "warn,wgpu_core=warn,wgpu_hal=warn,minesweeper=debug".into(),
          ..default()
      }));
+    // Inserted before the plugin, so it doesn't initialize the default config
+    app.insert_resource(config);
+    app.insert_resource(board);
     app.add_plugins(MinesweeperPlugin);
     app.run();
 }
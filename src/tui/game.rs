@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use minesweeper_plugin::components::Coordinates;
+use minesweeper_plugin::config::DifficultySettings;
+use minesweeper_plugin::resources::{GameSession, Grid, Outcome, RevealOutcome};
+
+
+/// Everything the player can do in the terminal frontend
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Action {
+	Move(i8, i8),
+	Reveal,
+	Flag,
+	RevealAt(Coordinates),
+	FlagAt(Coordinates),
+	Restart,
+	Print,
+	Quit,
+}
+
+impl Action {
+	/// Maps a single character to an action, used both by the keyboard and by scripts
+	pub fn from_char(c: char) -> Option<Self> {
+		match c {
+			'h' | 'a' => Some(Action::Move(-1, 0)),
+			'l' | 'd' => Some(Action::Move(1, 0)),
+			'k' | 'w' => Some(Action::Move(0, 1)),
+			'j' | 's' => Some(Action::Move(0, -1)),
+			' ' | 'x' => Some(Action::Reveal),
+			'f' => Some(Action::Flag),
+			'r' => Some(Action::Restart),
+			'p' => Some(Action::Print),
+			'q' => Some(Action::Quit),
+			_ => None,
+		}
+	}
+}
+
+
+/// A game played in the terminal: the session, the cursor and the timer
+pub struct TuiGame {
+	pub session: GameSession,
+	pub cursor: Coordinates,
+	difficulty: DifficultySettings,
+	rng: StdRng,
+	started: Option<Instant>,
+	finished: Option<Duration>,
+}
+
+impl TuiGame {
+	pub fn new(difficulty: DifficultySettings, seed: Option<u64>) -> Self {
+		let rng = match seed {
+			Some(seed) => StdRng::seed_from_u64(seed),
+			None => StdRng::from_entropy(),
+		};
+		let mut game = Self {
+			session: GameSession::new(Grid::empty(0, 0)),
+			cursor: Coordinates::default(),
+			difficulty,
+			rng,
+			started: None,
+			finished: None,
+		};
+		game.restart();
+		game
+	}
+
+	/// Generates a new grid, the cursor is placed in the middle of it
+	pub fn restart(&mut self) {
+		let size = self.difficulty.grid_size;
+		let mut grid = Grid::empty(size.x, size.y);
+		grid.set_bombs_with_rng(self.difficulty.bomb_count, &mut self.rng);
+		self.session = GameSession::new(grid);
+		self.cursor = Coordinates::new(size.x / 2, size.y / 2);
+		self.started = None;
+		self.finished = None;
+	}
+
+	/// Applies an action, returns false if the player wants to quit
+	pub fn apply(&mut self, action: Action) -> bool {
+		match action {
+			Action::Move(x, y) => {
+				let grid = self.session.grid();
+				let moved = self.cursor + (x, y);
+				if grid.contains(moved) { self.cursor = moved }
+			},
+			Action::Reveal => self.reveal(self.cursor),
+			Action::Flag => { self.session.toggle_flag(self.cursor); },
+			Action::RevealAt(coord) => {
+				self.cursor = coord;
+				self.reveal(coord);
+			},
+			Action::FlagAt(coord) => {
+				self.cursor = coord;
+				self.session.toggle_flag(coord);
+			},
+			Action::Restart => self.restart(),
+			Action::Print => {},
+			Action::Quit => return false,
+		}
+		true
+	}
+
+	fn reveal(&mut self, coord: Coordinates) {
+		let started = *self.started.get_or_insert_with(Instant::now);
+		match self.session.reveal(coord) {
+			RevealOutcome::Explosion(_) | RevealOutcome::Victory(_) => {
+				self.finished = Some(started.elapsed());
+			},
+			_ => {}
+		}
+	}
+
+	/// Returns the time elapsed since the first reveal, frozen when the game is over
+	pub fn elapsed(&self) -> Duration {
+		match (self.started, self.finished) {
+			(_, Some(finished)) => finished,
+			(Some(started), None) => started.elapsed(),
+			(None, None) => Duration::ZERO,
+		}
+	}
+
+	/// Returns a one-line summary of the game, shown under the header
+	pub fn status(&self) -> &'static str {
+		match self.session.outcome() {
+			Outcome::Playing => "arrows/hjkl: move  space: reveal  f: flag  r: restart  q: quit",
+			Outcome::Victory => "Oh woah, You survived !  r: restart  q: quit",
+			Outcome::Defeat => "Oh shit, you blew up !  r: restart  q: quit",
+		}
+	}
+
+	/// Returns the header line, containing the flags and the timer
+	pub fn header(&self) -> String {
+		format!(
+			"Flags : {} / {}    Time : {} secs",
+			self.session.flag_count(),
+			self.session.grid().bomb_count(),
+			self.elapsed().as_secs()
+		)
+	}
+}
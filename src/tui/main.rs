@@ -0,0 +1,131 @@
+// Terminal frontend for the minesweeper game, sharing the rules of the bevy plugin through `GameSession`
+//
+// When stdin is a terminal, the game is played interactively with the keyboard or the mouse.
+// Otherwise every character read on stdin is applied as a keystroke, and the board is printed at the end
+// (or whenever a `p` is read), which allows scripting games for testing:
+//
+//     echo "jjx f p" | minesweeper-tui --difficulty beginner --seed 42
+
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
+use crossterm::{cursor, event, execute, terminal};
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
+use minesweeper_plugin::config::{ColorTheme, DifficultySettings};
+
+mod game;
+mod render;
+
+use game::{Action, TuiGame};
+
+
+/// The three standard minesweeper difficulties
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+enum Difficulty {
+	Beginner,
+	Intermediate,
+	Expert,
+}
+
+/// Minesweeper in the terminal, with the keyboard or the mouse
+///
+/// Without a terminal on stdin, every character read is applied as a keystroke
+#[derive(Debug, Parser)]
+#[command(name = "minesweeper-tui", version, about)]
+struct Cli {
+	/// Plays one of the standard difficulties
+	#[arg(long, value_enum, default_value_t = Difficulty::Intermediate)]
+	difficulty: Difficulty,
+
+	/// Seed of the random generator, the same seed always giving the same boards
+	#[arg(long)]
+	seed: Option<u64>,
+}
+
+fn main() -> ExitCode {
+	let cli = Cli::parse();
+	let difficulty = match cli.difficulty {
+		Difficulty::Beginner => DifficultySettings::beginner(),
+		Difficulty::Intermediate => DifficultySettings::intermediate(),
+		Difficulty::Expert => DifficultySettings::expert(),
+	};
+	let game = TuiGame::new(difficulty, cli.seed);
+
+	let result = if io::stdin().is_terminal() {
+		run_interactive(game)
+	} else {
+		run_scripted(game)
+	};
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(err) => {
+			eprintln!("minesweeper-tui: {err}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Applies every character of stdin as a keystroke, printing the board on `p` and at the end
+fn run_scripted(mut game: TuiGame) -> io::Result<()> {
+	let mut script = String::new();
+	io::stdin().read_to_string(&mut script)?;
+	let mut out = io::stdout().lock();
+	for action in script.chars().filter_map(Action::from_char) {
+		if action == Action::Print {
+			print_game(&mut out, &game)?;
+		}
+		if !game.apply(action) { break }
+	}
+	print_game(&mut out, &game)
+}
+
+fn print_game(out: &mut impl Write, game: &TuiGame) -> io::Result<()> {
+	writeln!(out, "{}", game.header())?;
+	writeln!(out, "Outcome : {:?}    Cursor : {}", game.session.outcome(), game.cursor)?;
+	writeln!(out, "{}", game.session.console_output())
+}
+
+/// Plays the game in the terminal's alternate screen, until the player quits
+fn run_interactive(mut game: TuiGame) -> io::Result<()> {
+	let theme = ColorTheme::default();
+	let mut out = io::stdout();
+	terminal::enable_raw_mode()?;
+	execute!(out, terminal::EnterAlternateScreen, cursor::Hide, event::EnableMouseCapture)?;
+
+	let result = (|| {
+		loop {
+			render::draw(&mut out, &game, &theme)?;
+			// Polling with a timeout so the timer keeps ticking without input
+			if !event::poll(Duration::from_millis(250))? { continue }
+			let action = match event::read()? {
+				Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+					KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
+					KeyCode::Left => Some(Action::Move(-1, 0)),
+					KeyCode::Right => Some(Action::Move(1, 0)),
+					KeyCode::Up => Some(Action::Move(0, 1)),
+					KeyCode::Down => Some(Action::Move(0, -1)),
+					KeyCode::Enter => Some(Action::Reveal),
+					KeyCode::Esc => Some(Action::Quit),
+					KeyCode::Char(c) => Action::from_char(c),
+					_ => None,
+				},
+				Event::Mouse(mouse) => match (mouse.kind, render::cell_at(&game, mouse.column, mouse.row)) {
+					(MouseEventKind::Down(MouseButton::Left), Some(coord)) => Some(Action::RevealAt(coord)),
+					(MouseEventKind::Down(MouseButton::Right), Some(coord)) => Some(Action::FlagAt(coord)),
+					_ => None,
+				},
+				_ => None,
+			};
+			if let Some(action) = action {
+				if !game.apply(action) { return Ok(()) }
+			}
+		}
+	})();
+
+	// Always give the terminal back, even if drawing failed
+	execute!(out, event::DisableMouseCapture, cursor::Show, terminal::LeaveAlternateScreen)?;
+	terminal::disable_raw_mode()?;
+	result
+}
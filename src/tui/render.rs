@@ -0,0 +1,68 @@
+use std::io::{self, Write};
+
+use crossterm::{cursor, queue, style, terminal};
+use crossterm::style::{Attribute, Color, Stylize};
+use minesweeper_plugin::components::Coordinates;
+use minesweeper_plugin::config::ColorTheme;
+use minesweeper_plugin::resources::{Cell, CellState};
+
+use crate::game::TuiGame;
+
+
+// Layout of the screen, the board is drawn with a border like `Grid::console_output`
+const BOARD_TOP: u16 = 3;
+const BOARD_LEFT: u16 = 0;
+// Every cell is drawn as a symbol followed by a space, so the board looks roughly square
+const CELL_WIDTH: u16 = 2;
+
+
+/// Converts a bevy color from the theme into a terminal color
+fn term_color(color: bevy::render::color::Color) -> Color {
+	let [r, g, b, _] = color.as_rgba_u8();
+	Color::Rgb { r, g, b }
+}
+
+/// Returns the coordinates of the cell drawn at a given terminal position, if any
+pub fn cell_at(game: &TuiGame, column: u16, row: u16) -> Option<Coordinates> {
+	let grid = game.session.grid();
+	let x = column.checked_sub(BOARD_LEFT + 2)? / CELL_WIDTH;
+	let line = row.checked_sub(BOARD_TOP + 1)?;
+	if line >= grid.height() { return None }
+	let coord = Coordinates::new(x, grid.height() - 1 - line);
+	grid.contains(coord).then_some(coord)
+}
+
+/// Draws the whole game: header, status line and board
+pub fn draw(out: &mut impl Write, game: &TuiGame, theme: &ColorTheme) -> io::Result<()> {
+	let grid = game.session.grid();
+	queue!(out, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+	queue!(out, style::PrintStyledContent(game.header().with(term_color(theme.main_ui)).bold()))?;
+	queue!(out, cursor::MoveTo(0, 1), style::Print(game.status()))?;
+
+	let border: String = "-".repeat((grid.width() * CELL_WIDTH + 1) as usize);
+	queue!(out, cursor::MoveTo(BOARD_LEFT, BOARD_TOP), style::Print(format!("+{}+", border)))?;
+	for line in 0..grid.height() {
+		let y = grid.height() - 1 - line;
+		queue!(out, cursor::MoveTo(BOARD_LEFT, BOARD_TOP + 1 + line), style::Print("| "))?;
+		for x in 0..grid.width() {
+			let coord = Coordinates::new(x, y);
+			let mut symbol = match (game.session.state(coord), game.session.cell(coord)) {
+				(Some(CellState::Covered), _) => "#".to_string().with(term_color(theme.hidden_cell)),
+				(Some(CellState::Flagged), _) => "F".to_string().with(term_color(theme.flags)).bold(),
+				(_, Some(Cell::Bomb(_))) => "*".to_string().with(term_color(theme.menus_msg)).bold(),
+				(_, Some(Cell::BombNeighbor(count))) => {
+					let color = theme.neighbor_color(count);
+					count.to_string().with(term_color(color)).bold()
+				},
+				_ => ".".to_string().with(term_color(theme.cell)),
+			};
+			if coord == game.cursor {
+				symbol = symbol.attribute(Attribute::Reverse);
+			}
+			queue!(out, style::PrintStyledContent(symbol), style::Print(" "))?;
+		}
+		queue!(out, style::Print("|"))?;
+	}
+	queue!(out, cursor::MoveTo(BOARD_LEFT, BOARD_TOP + 1 + grid.height()), style::Print(format!("+{}+", border)))?;
+	out.flush()
+}
@@ -0,0 +1,80 @@
+// Plays the terminal frontend with scripts piped into its stdin, like a player typing the keystrokes
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+
+/// Runs the terminal frontend on a beginner board of a fixed seed, typing the script
+fn play(args: &[&str], script: &str) -> Output {
+	let mut child = Command::new(env!("CARGO_BIN_EXE_minesweeper-tui"))
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.expect("the terminal frontend starts");
+	// Dropping stdin closes it, the game then prints the final board
+	child.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+	child.wait_with_output().unwrap()
+}
+
+const BEGINNER: [&str; 4] = ["--difficulty", "beginner", "--seed", "42"];
+
+#[test]
+fn the_cursor_stays_on_the_board_and_flags_are_counted() {
+	let output = play(&BEGINNER, "hhhhhhhhhhkkkkkkkkkkf");
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("Flags : 1 / 10"), "{stdout}");
+	assert!(stdout.contains("Outcome : Playing    Cursor : (0, 8)"), "{stdout}");
+	// The top left cell is flagged on the printed board
+	assert!(stdout.lines().any(|line| line.starts_with("|F")), "{stdout}");
+}
+
+#[test]
+fn sweeping_the_whole_board_blows_up_on_the_first_mine() {
+	// From the bottom left corner, every cell is revealed row after row
+	let mut script = "hhhhjjjj".to_string();
+	for row in 0..9 {
+		let step = if row % 2 == 0 { 'l' } else { 'h' };
+		for column in 0..9 {
+			script.push('x');
+			if column < 8 { script.push(step) }
+		}
+		script.push('k');
+	}
+	let output = play(&BEGINNER, &script);
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	// The sweep goes on after the explosion, but the board of the seed only shows the mine of the second row
+	assert!(stdout.contains("Outcome : Defeat    Cursor : (8, 8)"), "{stdout}");
+	assert!(stdout.lines().any(|line| line == "| 112*1 11|"), "{stdout}");
+	assert_eq!(stdout.matches('*').count(), 1, "{stdout}");
+	// The same seed always plays the same game
+	assert_eq!(play(&BEGINNER, &script).stdout, stdout.into_bytes());
+}
+
+#[test]
+fn printing_and_quitting_stop_the_script() {
+	let output = play(&BEGINNER, "fpqf");
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	// Once when printed, once at the end, the flag after the quit being ignored
+	assert_eq!(stdout.matches("Flags : 1 / 10").count(), 2, "{stdout}");
+}
+
+#[test]
+fn invalid_arguments_are_rejected() {
+	let output = play(&["--difficulty", "impossible"], "");
+	assert!(!output.status.success());
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("invalid value 'impossible' for '--difficulty <DIFFICULTY>'"), "{stderr}");
+	assert!(!play(&["--seed", "forty-two"], "").status.success());
+}
+
+#[test]
+fn the_help_lists_the_options() {
+	let output = play(&["--help"], "");
+	assert!(output.status.success());
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	assert!(stdout.contains("Usage: minesweeper-tui [OPTIONS]") && stdout.contains("--difficulty") && stdout.contains("--seed"), "{stdout}");
+}